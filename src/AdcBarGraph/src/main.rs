@@ -0,0 +1,185 @@
+use adc::Adc;
+use adc::ready::{self, ReadyPin};
+use common::args::{Args, Opt};
+use common::error::{self, AppError, open_gpio};
+use common::led::{Led, LedPolarity};
+use common::lock;
+use common::pins;
+use common::shutdown::{self, install_panic_hook};
+use common::style;
+use std::error::Error;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+// LightWater's bar, except that the last three segments move off GPIO 2
+// and 3 (the I2C bus the ADC is on) and GPIO 8 (SPI0 CE0)
+const LED_PINS: [u8; 10] = [17, 18, 27, 22, 23, 24, 25, 5, 6, 12];
+const DEFAULT_CHANNEL: u8 = 0;
+const LOOP_DELAY: Duration = Duration::from_millis(20);
+
+/// Number of LEDs to light for a raw 8-bit reading, rounded to the nearest
+/// LED so a full-scale reading lights the whole bar.
+fn lit_count(raw: u8, led_count: usize) -> usize {
+    (raw as usize * led_count + 127) / 255
+}
+
+/// Remembers the highest recent level for `hold`, like the peak dot of a
+/// VU meter.
+struct PeakHold {
+    hold: Duration,
+    level: usize,
+    since: Instant,
+}
+
+impl PeakHold {
+    fn new(hold: Duration, now: Instant) -> Self {
+        Self {
+            hold,
+            level: 0,
+            since: now,
+        }
+    }
+
+    /// Records the current level and returns the peak to show.
+    fn update(&mut self, level: usize, now: Instant) -> usize {
+        if level >= self.level || now.duration_since(self.since) >= self.hold {
+            self.level = level;
+            self.since = now;
+        }
+        self.level
+    }
+}
+
+const OPTIONS: &[Opt] = &[
+    Opt::Value("--channel"),
+    Opt::Value("--peak-hold"),
+    Opt::Value("--ready-pin"),
+    Opt::Value("--lock-file"),
+];
+
+fn main() {
+    error::run(try_main);
+}
+
+fn try_main() -> Result<(), Box<dyn Error>> {
+    let args = Args::from_env()?.with_version(env!("CARGO_PKG_VERSION")).with_options(OPTIONS);
+    let channel = args.value_or("--channel", DEFAULT_CHANNEL)?;
+    let peak_hold_ms: Option<u64> = args.value("--peak-hold")?;
+    // GPIO wired to the board's end-of-conversion line, if it has one;
+    // without it the ADC is polled every LOOP_DELAY
+    let ready_pin: Option<u8> = args.value("--ready-pin")?;
+    let lock_file: Option<String> = args.value("--lock-file")?;
+    args.finish();
+    if let Some(pin) = ready_pin
+        && LED_PINS.contains(&pin)
+    {
+        return Err(format!("--ready-pin {} is already used by the LED bar", pin).into());
+    }
+    let gpio_pins: Vec<u8> = LED_PINS.iter().copied().chain(ready_pin).collect();
+    pins::warn_special_pins(&gpio_pins);
+    args.exit_if_dry_run();
+    let _lock = lock::acquire(lock_file.as_deref())?;
+
+    style::banner("Program is starting...");
+    let mut adc = Adc::detect(&adc::DEFAULT_BUSES, adc::DEFAULT_RETRIES)
+        .map_err(|e| AppError::AdcNotFound(e.to_string()))?;
+    adc::check_channel(adc.chip(), channel)?;
+    println!("Detected I2C device: {} on bus {}", adc.chip().name(), adc.bus());
+
+    let gpio = open_gpio()?;
+    let mut leds = Vec::with_capacity(LED_PINS.len());
+    for &pin_num in LED_PINS.iter() {
+        // The bar's LEDs light when the pin is pulled low
+        leds.push(Led::new(gpio.get(pin_num)?.into_output_high(), LedPolarity::ActiveLow));
+    }
+    // Pulled up because ready lines are usually open-drain
+    let mut ready = ready_pin
+        .map(|pin| ReadyPin::new(gpio.get(pin)?.into_input_pullup()))
+        .transpose()?;
+    if let Some(pin) = ready_pin {
+        println!("Reading on conversions signalled on GPIO {}", pin);
+    }
+
+    let running = Arc::new(AtomicBool::new(true));
+    install_panic_hook(running.clone());
+    let r = running.clone();
+    shutdown::set_handler(move || {
+        r.store(false, Ordering::SeqCst);
+    })?;
+
+    let mut peak = peak_hold_ms.map(|ms| PeakHold::new(Duration::from_millis(ms), Instant::now()));
+    while running.load(Ordering::SeqCst) {
+        let reading = match ready.as_mut() {
+            Some(ready_pin) => {
+                let wait = || ready_pin.wait(ready::DEFAULT_READY_TIMEOUT);
+                match ready::read_when_ready(wait, || adc.read(channel))? {
+                    Some(reading) => reading,
+                    None => {
+                        eprintln!(
+                            "Warning: no ready signal within {} ms",
+                            ready::DEFAULT_READY_TIMEOUT.as_millis()
+                        );
+                        continue;
+                    }
+                }
+            }
+            None => adc.read(channel),
+        };
+        match reading {
+            Ok(raw) => {
+                let lit = lit_count(raw, leds.len());
+                let peak_level = peak.as_mut().map(|p| p.update(lit, Instant::now()));
+                for (i, led) in leds.iter_mut().enumerate() {
+                    // The peak LED is the last one of the held level
+                    if i < lit || peak_level.is_some_and(|p| p > 0 && i == p - 1) {
+                        led.on();
+                    } else {
+                        led.off();
+                    }
+                }
+            }
+            Err(e) => eprintln!("Error reading ADC: {}", e),
+        }
+        if ready.is_none() {
+            thread::sleep(LOOP_DELAY);
+        }
+    }
+
+    for led in leds.iter_mut() {
+        led.off();
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bar_length_rounds_to_the_nearest_led() {
+        assert_eq!(lit_count(0, 10), 0);
+        assert_eq!(lit_count(12, 10), 0);
+        assert_eq!(lit_count(13, 10), 1);
+        assert_eq!(lit_count(128, 10), 5);
+        assert_eq!(lit_count(255, 10), 10);
+    }
+
+    #[test]
+    fn peak_is_held_then_follows_the_level() {
+        let start = Instant::now();
+        let hold = Duration::from_millis(500);
+        let mut peak = PeakHold::new(hold, start);
+        assert_eq!(peak.update(8, start), 8);
+        assert_eq!(peak.update(3, start + Duration::from_millis(499)), 8);
+        assert_eq!(peak.update(3, start + hold), 3);
+        // A new high replaces the peak at once
+        assert_eq!(peak.update(9, start + hold), 9);
+    }
+
+    #[test]
+    fn bar_pins_avoid_special_functions() {
+        assert!(LED_PINS.iter().all(|&pin| common::pins::special_function(pin).is_none()));
+    }
+}
@@ -0,0 +1,264 @@
+use adc::Adc;
+use common::args::{Args, Opt};
+use common::blinker::TimedBlinker;
+use common::button::{Button, ButtonEvent, DEFAULT_DEBOUNCE, Debouncer};
+use common::buzzer::{self, Buzzer};
+use common::error::{self, AppError, open_gpio};
+use common::led::{Led, LedPolarity};
+use common::lock;
+use common::shutdown::{self, install_panic_hook};
+use common::style;
+use common::threshold::Hysteresis;
+use std::error::Error;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+// Buzzer and arm button wired as in the Alertor lesson; the silence button
+// and LED go on free pins
+const BUZZER_PIN: u8 = 17;
+const ARM_BUTTON_PIN: u8 = 18;
+const SILENCE_BUTTON_PIN: u8 = 23;
+const LED_PIN: u8 = 27;
+const DEFAULT_CHANNEL: u8 = 0;
+const DEFAULT_THRESHOLD: u8 = 128;
+const DEFAULT_HYSTERESIS: u8 = 10;
+// Siren sweep
+const SIREN_LOW: f64 = 440.0;
+const SIREN_HIGH: f64 = 880.0;
+const SIREN_PERIOD: Duration = Duration::from_millis(1000);
+// Fast blink while sounding, slow blink once silenced
+const ALARM_BLINK: Duration = Duration::from_millis(100);
+const SILENCED_BLINK: Duration = Duration::from_millis(500);
+const LOOP_DELAY: Duration = Duration::from_millis(10);
+
+/// Where the alarm is in its cycle.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum AlarmState {
+    /// Sensor ignored; LED off.
+    Disarmed,
+    /// Watching the sensor; LED steadily on.
+    Armed,
+    /// Sensor tripped while armed; siren on and LED blinking fast.
+    Triggered,
+    /// Siren silenced, waiting for the sensor to clear; LED blinking slowly.
+    Silenced,
+}
+
+/// Inputs that can move the alarm between states.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum AlarmEvent {
+    ArmPressed,
+    SilencePressed,
+    SensorTripped,
+    SensorCleared,
+}
+
+impl AlarmState {
+    /// The state after `event`. The arm button disarms from any armed state;
+    /// a silenced alarm re-arms itself once the sensor clears.
+    fn next(self, event: AlarmEvent) -> AlarmState {
+        match (self, event) {
+            (AlarmState::Disarmed, AlarmEvent::ArmPressed) => AlarmState::Armed,
+            (_, AlarmEvent::ArmPressed) => AlarmState::Disarmed,
+            (AlarmState::Armed, AlarmEvent::SensorTripped) => AlarmState::Triggered,
+            (AlarmState::Triggered, AlarmEvent::SilencePressed) => AlarmState::Silenced,
+            (AlarmState::Silenced, AlarmEvent::SensorCleared) => AlarmState::Armed,
+            (state, _) => state,
+        }
+    }
+
+    /// Like `next`, but arming while the sensor is already tripped triggers
+    /// at once instead of waiting for a fresh crossing.
+    fn step(self, event: AlarmEvent, sensor_active: bool) -> AlarmState {
+        let state = self.next(event);
+        if state == AlarmState::Armed && sensor_active {
+            state.next(AlarmEvent::SensorTripped)
+        } else {
+            state
+        }
+    }
+}
+
+/// Turns a debounced button press into `event`.
+fn press(debouncer: &mut Debouncer, button: &Button, now: Instant, event: AlarmEvent) -> Option<AlarmEvent> {
+    match debouncer.update(button.is_pressed(), now) {
+        Some(ButtonEvent::Pressed) => Some(event),
+        _ => None,
+    }
+}
+
+const OPTIONS: &[Opt] = &[
+    Opt::Value("--channel"),
+    Opt::Value("--threshold"),
+    Opt::Value("--hysteresis"),
+    Opt::Value("--min-hold-ms"),
+    Opt::Flag("--trigger-below"),
+    Opt::Value("--lock-file"),
+];
+
+fn main() {
+    error::run(try_main);
+}
+
+fn try_main() -> Result<(), Box<dyn Error>> {
+    let args = Args::from_env()?.with_version(env!("CARGO_PKG_VERSION")).with_options(OPTIONS);
+    let channel = args.value_or("--channel", DEFAULT_CHANNEL)?;
+    let threshold: u8 = args.value_or("--threshold", DEFAULT_THRESHOLD)?;
+    let hysteresis: u8 = args.value_or("--hysteresis", DEFAULT_HYSTERESIS)?;
+    // How long the reading must stay below the threshold before the sensor
+    // counts as cleared
+    let min_hold = Duration::from_millis(args.value_or("--min-hold-ms", 0)?);
+    // For sensors whose reading drops when tripped, e.g. a photoresistor
+    // covered by a passing hand
+    let trigger_below = args.flag("--trigger-below");
+    let lock_file: Option<String> = args.value("--lock-file")?;
+    args.finish();
+    args.exit_if_dry_run();
+    let _lock = lock::acquire(lock_file.as_deref())?;
+
+    style::banner("Program is starting...");
+    let mut adc = Adc::detect(&adc::DEFAULT_BUSES, adc::DEFAULT_RETRIES)
+        .map_err(|e| AppError::AdcNotFound(e.to_string()))?;
+    adc::check_channel(adc.chip(), channel)?;
+    println!("Detected I2C device: {} on bus {}", adc.chip().name(), adc.bus());
+
+    let gpio = open_gpio()?;
+    let mut buzzer = Buzzer::new(gpio.get(BUZZER_PIN)?.into_output_low());
+    let arm_button = Button::new(gpio.get(ARM_BUTTON_PIN)?.into_input_pullup());
+    let silence_button = Button::new(gpio.get(SILENCE_BUTTON_PIN)?.into_input_pullup());
+    let led = Led::new(gpio.get(LED_PIN)?.into_output_low(), LedPolarity::ActiveHigh);
+
+    let running = Arc::new(AtomicBool::new(true));
+    install_panic_hook(running.clone());
+    let r = running.clone();
+    shutdown::set_handler(move || {
+        style::banner("\nEnding program");
+        r.store(false, Ordering::SeqCst);
+    })?;
+
+    // Negating the readings turns "falls to the threshold" into the usual
+    // "rises to it"
+    let level = if trigger_below { -(threshold as f64) } else { threshold as f64 };
+    let mut sensor = Hysteresis::new(level, hysteresis as f64).with_min_hold(min_hold);
+    let mut arm_debouncer = Debouncer::new(DEFAULT_DEBOUNCE);
+    let mut silence_debouncer = Debouncer::new(DEFAULT_DEBOUNCE);
+    let mut blinker = TimedBlinker::new(led, ALARM_BLINK, Instant::now());
+    blinker.stop();
+
+    let mut state = AlarmState::Disarmed;
+    let start = Instant::now();
+    let mut triggered_at = start;
+    println!("Disarmed; press the button on GPIO {} to arm", ARM_BUTTON_PIN);
+    while running.load(Ordering::SeqCst) {
+        let now = Instant::now();
+        let mut events = Vec::new();
+        events.extend(press(&mut arm_debouncer, &arm_button, now, AlarmEvent::ArmPressed));
+        events.extend(press(&mut silence_debouncer, &silence_button, now, AlarmEvent::SilencePressed));
+        match adc.read(channel) {
+            Ok(raw) => {
+                let value = if trigger_below { -(raw as f64) } else { raw as f64 };
+                match sensor.update(value, now) {
+                    Some(true) => events.push(AlarmEvent::SensorTripped),
+                    Some(false) => events.push(AlarmEvent::SensorCleared),
+                    None => {}
+                }
+            }
+            Err(e) => eprintln!("Error reading ADC: {}", e),
+        }
+
+        for event in events {
+            let next = state.step(event, sensor.is_active());
+            if next == state {
+                continue;
+            }
+            println!(
+                "[{:>9.3}s] {:?} -> {:?} ({:?})",
+                now.saturating_duration_since(start).as_secs_f64(),
+                state,
+                next,
+                event
+            );
+            match next {
+                AlarmState::Disarmed => blinker.stop(),
+                AlarmState::Armed => blinker.restart(now),
+                AlarmState::Triggered => {
+                    triggered_at = now;
+                    blinker.set_interval(ALARM_BLINK);
+                    blinker.restart(now);
+                }
+                AlarmState::Silenced => {
+                    blinker.set_interval(SILENCED_BLINK);
+                    blinker.restart(now);
+                }
+            }
+            if state == AlarmState::Triggered {
+                buzzer.silence()?;
+            }
+            state = next;
+        }
+
+        match state {
+            AlarmState::Triggered => {
+                let frequency = buzzer::siren_frequency(now - triggered_at, SIREN_LOW, SIREN_HIGH, SIREN_PERIOD);
+                buzzer.tone(frequency)?;
+                blinker.tick(now);
+            }
+            AlarmState::Silenced => {
+                blinker.tick(now);
+            }
+            AlarmState::Disarmed | AlarmState::Armed => {}
+        }
+        thread::sleep(LOOP_DELAY);
+    }
+
+    // Cleanup
+    buzzer.silence()?;
+    blinker.into_led().off();
+    style::banner("Program is finished.");
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn full_alarm_cycle() {
+        let mut state = AlarmState::Disarmed;
+        for (event, expected) in [
+            (AlarmEvent::ArmPressed, AlarmState::Armed),
+            (AlarmEvent::SensorTripped, AlarmState::Triggered),
+            (AlarmEvent::SilencePressed, AlarmState::Silenced),
+            (AlarmEvent::SensorCleared, AlarmState::Armed),
+        ] {
+            state = state.next(event);
+            assert_eq!(state, expected, "after {:?}", event);
+        }
+    }
+
+    #[test]
+    fn arm_button_disarms_from_any_armed_state() {
+        for state in [AlarmState::Armed, AlarmState::Triggered, AlarmState::Silenced] {
+            assert_eq!(state.next(AlarmEvent::ArmPressed), AlarmState::Disarmed);
+        }
+    }
+
+    #[test]
+    fn unrelated_events_are_ignored() {
+        assert_eq!(AlarmState::Disarmed.next(AlarmEvent::SensorTripped), AlarmState::Disarmed);
+        assert_eq!(AlarmState::Armed.next(AlarmEvent::SilencePressed), AlarmState::Armed);
+        // A silenced alarm stays silenced until the sensor clears
+        assert_eq!(AlarmState::Silenced.next(AlarmEvent::SensorTripped), AlarmState::Silenced);
+        assert_eq!(AlarmState::Triggered.next(AlarmEvent::SensorCleared), AlarmState::Triggered);
+    }
+
+    #[test]
+    fn arming_with_the_sensor_tripped_triggers_at_once() {
+        assert_eq!(AlarmState::Disarmed.step(AlarmEvent::ArmPressed, true), AlarmState::Triggered);
+        assert_eq!(AlarmState::Disarmed.step(AlarmEvent::ArmPressed, false), AlarmState::Armed);
+        // Disarming is unaffected by the sensor
+        assert_eq!(AlarmState::Triggered.step(AlarmEvent::ArmPressed, true), AlarmState::Disarmed);
+    }
+}
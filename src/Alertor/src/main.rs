@@ -1,40 +1,246 @@
-use rppal::gpio::{Gpio, InputPin, OutputPin};
+use common::args::{Args, Opt};
+use common::button::{self, ButtonLogic, setup_button_interrupt, wait_for_edge};
+use common::buzzer::BeepPattern;
+use common::error::{self, open_gpio};
+use common::fade;
+use common::lock;
+use common::rate::LoopRateLimiter;
+use common::retry::{Backoff, FailureCounter};
+use common::shutdown::{self, install_panic_hook};
+use common::style;
+use common::throttle::Throttle;
+use rppal::gpio::{InputPin, OutputPin};
 use std::error::Error;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 use std::thread;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
 const BUZZER_PIN: u8 = 17;
 const BTN_PIN: u8 = 18;
 const ALERTOR_FREQUENCY: f64 = 220.0; // 220Hz
 const ALERTOR_DUTY_CYCLE: f64 = 0.5;  // 50% duty cycle
-const LOOP_DELAY_MS: u64 = 10;
-const ALERTOR_DURATION_MS: u64 = 100;
+const POLL_TIMEOUT_MS: u64 = 10;
+// Failed attempts to start the tone before giving up until the next press
+const MAX_SOUND_ATTEMPTS: u32 = 5;
+const SOUND_RETRY_INITIAL: Duration = Duration::from_millis(20);
+const SOUND_RETRY_MAX: Duration = Duration::from_millis(500);
+// Duty ramps at tone start and stop; a few ms is enough to soften the click
+const DEFAULT_ATTACK_MS: u64 = 5;
+const DEFAULT_RELEASE_MS: u64 = 5;
+const RAMP_TICK: Duration = Duration::from_millis(1);
+// Minimum interval between "turned on" lines
+const DEFAULT_LOG_EVERY_MS: u64 = 500;
+// Beep length when --off-ms turns the tone into a pattern
+const DEFAULT_ON_MS: u64 = 200;
+
+/// What is programmed into the buzzer's PWM. Log lines report the value
+/// that was last written, so they cannot drift from the output.
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Tone {
+    frequency: f64,
+    duty: f64,
+}
+
+impl Tone {
+    fn with_duty(self, duty: f64) -> Tone {
+        Tone { duty, ..self }
+    }
+}
+
+const ALERTOR_TONE: Tone = Tone {
+    frequency: ALERTOR_FREQUENCY,
+    duty: ALERTOR_DUTY_CYCLE,
+};
+
+/// Switches the tone on and off in `--on-ms`/`--off-ms` beats while the
+/// button is held; with `--off-ms 0` the tone is continuous.
+struct Beeper {
+    pattern: BeepPattern,
+    release: Duration,
+    held_since: Option<Instant>,
+    sounding: bool,
+}
+
+impl Beeper {
+    fn new(pattern: BeepPattern, release: Duration) -> Self {
+        Self {
+            pattern,
+            release,
+            held_since: None,
+            sounding: false,
+        }
+    }
+
+    fn press(&mut self, now: Instant) {
+        self.held_since = Some(now);
+        self.sounding = true;
+    }
+
+    /// Returns the release ramp to use: none if the tone is already off
+    /// between beats.
+    fn release(&mut self) -> Duration {
+        self.held_since = None;
+        let sounding = std::mem::replace(&mut self.sounding, false);
+        if sounding { self.release } else { Duration::ZERO }
+    }
+
+    /// Starts or stops the tone when the pattern moves to its next beat.
+    fn tick(
+        &mut self,
+        buzzer_pin: &mut OutputPin,
+        retry: &mut SoundRetry,
+        now: Instant,
+    ) -> Result<(), Box<dyn Error>> {
+        let Some(since) = self.held_since else {
+            return Ok(());
+        };
+        let want = self.pattern.is_on(now.saturating_duration_since(since));
+        if want && !self.sounding {
+            self.sounding = true;
+            retry.try_play(buzzer_pin);
+        } else if !want && self.sounding {
+            self.sounding = false;
+            stop_alertor_sound(buzzer_pin, self.release)?;
+        }
+        Ok(())
+    }
+}
+
+/// Retry state for starting the tone while the button is held.
+struct SoundRetry {
+    // The button is held but the tone is not playing yet
+    pending: bool,
+    failures: FailureCounter,
+    backoff: Backoff,
+    attack: Duration,
+    log_throttle: Throttle,
+}
+
+impl SoundRetry {
+    fn new(attack: Duration, log_every: Duration) -> Self {
+        Self {
+            pending: false,
+            attack,
+            log_throttle: Throttle::new(log_every),
+            failures: FailureCounter::new(MAX_SOUND_ATTEMPTS),
+            backoff: Backoff::new(SOUND_RETRY_INITIAL, SOUND_RETRY_MAX),
+        }
+    }
+
+    /// Tries to start the tone. A failure is logged and retried after a
+    /// backoff; once it has failed `MAX_SOUND_ATTEMPTS` times in a row the
+    /// buzzer is silenced and the press is abandoned.
+    fn try_play(&mut self, buzzer_pin: &mut OutputPin) {
+        match play_alertor_sound(buzzer_pin, self.attack) {
+            Ok(tone) => {
+                self.pending = false;
+                self.failures.record_success();
+                self.backoff.reset();
+                if self.log_throttle.should_emit(Instant::now()) {
+                    print_alertor_on_message(tone);
+                }
+            }
+            Err(e) => {
+                eprintln!("Failed to start alertor tone: {}", e);
+                if self.failures.record_failure() {
+                    eprintln!("Giving up after {} attempts, buzzer silenced", MAX_SOUND_ATTEMPTS);
+                    let _ = stop_alertor_sound(buzzer_pin, Duration::ZERO);
+                    self.pending = false;
+                    self.backoff.reset();
+                } else {
+                    self.pending = true;
+                    thread::sleep(self.backoff.next_delay());
+                }
+            }
+        }
+    }
+
+    /// Abandons a pending start; returns `true` if the tone never started.
+    fn cancel(&mut self) -> bool {
+        let was_pending = self.pending;
+        self.pending = false;
+        self.failures.record_success();
+        self.backoff.reset();
+        was_pending
+    }
+}
+
+const OPTIONS: &[Opt] = &[
+    Opt::Flag("--no-color"),
+    Opt::Value("--button-logic"),
+    Opt::Value("--attack-ms"),
+    Opt::Value("--release-ms"),
+    Opt::Value("--log-every"),
+    Opt::Value("--on-ms"),
+    Opt::Value("--off-ms"),
+    Opt::Value("--loop-hz"),
+    Opt::Value("--lock-file"),
+];
+
+fn main() {
+    error::run(try_main);
+}
+
+fn try_main() -> Result<(), Box<dyn Error>> {
+    let args = Args::from_env()?.with_version(env!("CARGO_PKG_VERSION")).with_options(OPTIONS);
+    let no_color = args.flag("--no-color");
+    let button_logic: ButtonLogic = args.value_or("--button-logic", ButtonLogic::default())?;
+    let attack = Duration::from_millis(args.value_or("--attack-ms", DEFAULT_ATTACK_MS)?);
+    let release = Duration::from_millis(args.value_or("--release-ms", DEFAULT_RELEASE_MS)?);
+    let log_every = Duration::from_millis(args.value_or("--log-every", DEFAULT_LOG_EVERY_MS)?);
+    // Beep pattern while the button is held; --off-ms 0 keeps the tone continuous
+    let pattern = BeepPattern {
+        on: Duration::from_millis(args.value_or("--on-ms", DEFAULT_ON_MS)?),
+        off: Duration::from_millis(args.value_or("--off-ms", 0)?),
+    };
+    // Upper bound on loop passes per second; the edge wait already bounds it
+    let loop_hz: Option<f64> = args.value("--loop-hz")?;
+    let lock_file: Option<String> = args.value("--lock-file")?;
+    args.finish();
+    let limiter = loop_hz
+        .map(LoopRateLimiter::new)
+        .transpose()
+        .map_err(|e| format!("--loop-hz {}", e))?;
+    if pattern.on.is_zero() {
+        return Err("--on-ms must be positive".into());
+    }
+    args.exit_if_dry_run();
+    let _lock = lock::acquire(lock_file.as_deref())?;
+    style::init(no_color);
 
-fn main() -> Result<(), Box<dyn Error>> {
     print_startup_message();
 
-    let (mut buzzer_pin, btn_pin) = initialize_gpio()?;
+    let (mut buzzer_pin, mut btn_pin) = initialize_gpio()?;
     initialize_buzzer(&mut buzzer_pin);
+    setup_button_interrupt(&mut btn_pin)?;
 
     let running = setup_signal_handler()?;
+    install_panic_hook(running.clone());
 
-    println!("Waiting for button press...");
+    style::banner("Waiting for button press...");
 
-    run_main_loop(&running, &mut buzzer_pin, &btn_pin)?;
+    run_interrupt_loop(
+        &running,
+        &mut buzzer_pin,
+        &mut btn_pin,
+        button_logic,
+        SoundRetry::new(attack, log_every),
+        Beeper::new(pattern, release),
+        limiter,
+    )?;
 
-    cleanup(&mut buzzer_pin)?;
+    cleanup(&mut buzzer_pin, &mut btn_pin)?;
 
     Ok(())
 }
 
 fn print_startup_message() {
-    println!("Program is starting...");
+    style::banner("Program is starting...");
 }
 
 fn initialize_gpio() -> Result<(OutputPin, InputPin), Box<dyn Error>> {
-    let gpio = Gpio::new()?;
+    let gpio = open_gpio()?;
     let buzzer_pin = gpio.get(BUZZER_PIN)?.into_output();
     let btn_pin = gpio.get(BTN_PIN)?.into_input();
     Ok((buzzer_pin, btn_pin))
@@ -44,60 +250,128 @@ fn initialize_buzzer(buzzer_pin: &mut OutputPin) {
     buzzer_pin.set_low();
 }
 
+/// Clears the returned flag on Ctrl+C, SIGTERM or SIGHUP, so a systemd
+/// stop runs the same cleanup as Ctrl+C.
 fn setup_signal_handler() -> Result<Arc<AtomicBool>, Box<dyn Error>> {
     let running = Arc::new(AtomicBool::new(true));
     let r = running.clone();
-    ctrlc::set_handler(move || {
+    shutdown::set_handler(move || {
         r.store(false, Ordering::SeqCst);
     })?;
     Ok(running)
 }
 
-fn run_main_loop(
+fn run_interrupt_loop(
     running: &Arc<AtomicBool>,
     buzzer_pin: &mut OutputPin,
-    btn_pin: &InputPin,
+    btn_pin: &mut InputPin,
+    button_logic: ButtonLogic,
+    mut retry: SoundRetry,
+    mut beeper: Beeper,
+    mut limiter: Option<LoopRateLimiter>,
 ) -> Result<(), Box<dyn Error>> {
     while running.load(Ordering::SeqCst) {
-        if is_button_pressed(btn_pin) {
-            play_alertor_sound(buzzer_pin)?;
-            print_alertor_on_message();
+        if wait_for_edge(btn_pin, Duration::from_millis(POLL_TIMEOUT_MS))? {
+            handle_button_interrupt(buzzer_pin, btn_pin, button_logic, &mut retry, &mut beeper)?;
+        } else if retry.pending {
+            retry.try_play(buzzer_pin);
         } else {
-            stop_alertor_sound(buzzer_pin)?;
-            print_alertor_off_message();
+            beeper.tick(buzzer_pin, &mut retry, Instant::now())?;
         }
+        if let Some(limiter) = limiter.as_mut() {
+            limiter.wait();
+        }
+    }
+    Ok(())
+}
 
-        thread::sleep(Duration::from_millis(LOOP_DELAY_MS));
+fn handle_button_interrupt(
+    buzzer_pin: &mut OutputPin,
+    btn_pin: &InputPin,
+    button_logic: ButtonLogic,
+    retry: &mut SoundRetry,
+    beeper: &mut Beeper,
+) -> Result<(), Box<dyn Error>> {
+    if is_button_pressed(btn_pin, button_logic) {
+        beeper.press(Instant::now());
+        retry.try_play(buzzer_pin);
+    } else {
+        // No release ramp if the tone never started, or it would blip
+        let release = beeper.release();
+        let release = if retry.cancel() { Duration::ZERO } else { release };
+        stop_alertor_sound(buzzer_pin, release)?;
+        print_alertor_off_message();
     }
     Ok(())
 }
 
-fn is_button_pressed(btn_pin: &InputPin) -> bool {
-    btn_pin.is_low()
+fn is_button_pressed(btn_pin: &InputPin, button_logic: ButtonLogic) -> bool {
+    button::is_pressed(btn_pin.read(), button_logic)
 }
 
-fn play_alertor_sound(buzzer_pin: &mut OutputPin) -> Result<(), Box<dyn Error>> {
-    buzzer_pin.set_pwm_frequency(ALERTOR_FREQUENCY, ALERTOR_DUTY_CYCLE)?;
-    thread::sleep(Duration::from_millis(ALERTOR_DURATION_MS));
-    Ok(())
+/// Programs `tone` into the buzzer's PWM.
+fn drive(buzzer_pin: &mut OutputPin, tone: Tone) -> rppal::gpio::Result<()> {
+    buzzer_pin.set_pwm_frequency(tone.frequency, tone.duty)
 }
 
-fn stop_alertor_sound(buzzer_pin: &mut OutputPin) -> Result<(), Box<dyn Error>> {
+/// Starts the tone, ramping the duty up over `attack` to avoid a click.
+/// Returns the tone left playing.
+fn play_alertor_sound(buzzer_pin: &mut OutputPin, attack: Duration) -> Result<Tone, Box<dyn Error>> {
+    let mut driven = ALERTOR_TONE.with_duty(0.0);
+    fade::ramp(0.0, ALERTOR_TONE.duty, attack, RAMP_TICK, |duty| {
+        driven = ALERTOR_TONE.with_duty(duty);
+        drive(buzzer_pin, driven)
+    })?;
+    Ok(driven)
+}
+
+/// Stops the tone, ramping the duty down over `release` first.
+fn stop_alertor_sound(buzzer_pin: &mut OutputPin, release: Duration) -> Result<(), Box<dyn Error>> {
+    fade::ramp(ALERTOR_TONE.duty, 0.0, release, RAMP_TICK, |duty| {
+        drive(buzzer_pin, ALERTOR_TONE.with_duty(duty))
+    })?;
     buzzer_pin.clear_pwm()?;
     buzzer_pin.set_low();
     Ok(())
 }
 
-fn print_alertor_on_message() {
-    println!("alertor turned on >>> ");
+fn print_alertor_on_message(tone: Tone) {
+    let line = format!(
+        "alertor turned on >>> ({:.1} Hz, {:.0}% duty)",
+        tone.frequency,
+        tone.duty * 100.0
+    );
+    println!("{}", style::on(&line));
 }
 
 fn print_alertor_off_message() {
-    println!("alertor turned off <<<");
+    println!("{}", style::off("alertor turned off <<<"));
 }
 
-fn cleanup(buzzer_pin: &mut OutputPin) -> Result<(), Box<dyn Error>> {
-    println!("Ending program");
-    stop_alertor_sound(buzzer_pin)?;
+fn cleanup(buzzer_pin: &mut OutputPin, btn_pin: &mut InputPin) -> Result<(), Box<dyn Error>> {
+    style::banner("Ending program");
+    let _ = btn_pin.clear_interrupt();
+    stop_alertor_sound(buzzer_pin, Duration::ZERO)?;
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn with_duty_keeps_the_frequency() {
+        assert_eq!(ALERTOR_TONE.with_duty(0.0), Tone { frequency: ALERTOR_FREQUENCY, duty: 0.0 });
+    }
+
+    #[test]
+    fn release_ramps_only_while_sounding() {
+        let release = Duration::from_millis(DEFAULT_RELEASE_MS);
+        let mut beeper = Beeper::new(BeepPattern { on: Duration::from_millis(DEFAULT_ON_MS), off: Duration::ZERO }, release);
+        beeper.press(Instant::now());
+        assert_eq!(beeper.release(), release);
+        // Already silent: nothing to ramp down
+        assert_eq!(beeper.release(), Duration::ZERO);
+        assert_eq!(beeper.held_since, None);
+    }
+}
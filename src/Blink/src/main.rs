@@ -1,22 +1,60 @@
-use rppal::gpio::{Gpio, Level};
+use common::args::{Args, Opt};
+use common::blinker::TimedBlinker;
+use common::error::{self, open_gpio};
+use common::led::{Led, LedPolarity};
+use common::lock;
+use common::report::Reporter;
+use common::style;
 use std::error::Error;
 use std::thread::sleep;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
 const LED_PIN: u8 = 17;
+const BLINK_INTERVAL: Duration = Duration::from_secs(1);
+// How often the loop checks whether the LED is due to toggle
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
 
-fn main() -> Result<(), Box<dyn Error>> {
-    println!("Program is starting...");
-    let gpio = Gpio::new()?;
-    let mut pin = gpio.get(LED_PIN)?.into_output();
+const OPTIONS: &[Opt] = &[
+    Opt::Flag("--no-color"),
+    Opt::Value("--report-interval"),
+    Opt::Value("--lock-file"),
+];
+
+fn main() {
+    error::run(try_main);
+}
+
+fn try_main() -> Result<(), Box<dyn Error>> {
+    let args = Args::from_env()?.with_version(env!("CARGO_PKG_VERSION")).with_options(OPTIONS);
+    let no_color = args.flag("--no-color");
+    let report_interval = Duration::from_secs(args.value_or("--report-interval", 0)?);
+    let lock_file: Option<String> = args.value("--lock-file")?;
+    args.finish();
+    args.exit_if_dry_run();
+    let _lock = lock::acquire(lock_file.as_deref())?;
+    style::init(no_color);
+
+    style::banner("Program is starting...");
+    let gpio = open_gpio()?;
+    let led = Led::new(gpio.get(LED_PIN)?.into_output(), LedPolarity::ActiveHigh);
     println!("LED will blink every 1 second.");
 
+    let mut reporter = Reporter::new(report_interval, "blinks", Instant::now());
+    let mut blinker = TimedBlinker::new(led, BLINK_INTERVAL, Instant::now());
+    println!("{}", style::on("LED turned on >>>"));
     loop {
-        pin.write(Level::High);
-        println!("LED turned on >>>");
-        sleep(Duration::from_secs(1));
-        pin.write(Level::Low);
-        println!("LED turned off <<<");
-        sleep(Duration::from_secs(1));
+        let now = Instant::now();
+        if blinker.tick(now) {
+            if blinker.is_on() {
+                println!("{}", style::on("LED turned on >>>"));
+            } else {
+                println!("{}", style::off("LED turned off <<<"));
+                reporter.record();
+                if let Some(summary) = reporter.poll(now) {
+                    println!("{}", summary);
+                }
+            }
+        }
+        sleep(POLL_INTERVAL);
     }
 }
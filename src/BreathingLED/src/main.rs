@@ -1,59 +1,345 @@
-use rppal::gpio::{Gpio, OutputPin};
+use common::args::{Args, Opt};
+use common::envelope::Envelope;
+use common::error::{self, open_gpio};
+use common::fade;
+use common::keyboard::{self, KeyAction};
+use common::lock;
+use common::pwm;
+use common::shutdown::{self, install_panic_hook};
+use common::style;
+use common::throttle::Throttle;
+use rppal::gpio::OutputPin;
 use rppal::pwm::{Channel, Polarity, Pwm};
 use std::error::Error;
-use std::sync::atomic::{AtomicBool, Ordering};
+use std::f64::consts::PI;
+use std::fmt;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, AtomicU8, Ordering};
 use std::sync::Arc;
 use std::thread;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
 const LED_PIN: u8 = 18;
 const PWM_FREQUENCY: f64 = 1000.0;
+// GPIO 18 is routed to hardware PWM channel 0
+const PWM_CHANNEL: Channel = Channel::Pwm0;
+// Brightness change per key press in --keyboard mode, in percent
+const KEY_STEP_PERCENT: u8 = 10;
 
-fn main() -> Result<(), Box<dyn Error>> {
-    println!("Starting Breathing LED...");
-    println!("Press Ctrl+C to quit");
+// Steepness of the `exp` curve; larger values linger longer near dark
+const EXP_CURVE_K: f64 = 4.0;
+
+/// Shape of one breath, selected with `--curve`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Curve {
+    /// Straight ramps up and down.
+    Linear,
+    /// Smooth `(1 - cos)/2` swell with no corners at the ends.
+    Sine,
+    /// Exponential ramps that accelerate towards the peak.
+    Exp,
+}
+
+impl FromStr for Curve {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "linear" => Ok(Curve::Linear),
+            "sine" => Ok(Curve::Sine),
+            "exp" => Ok(Curve::Exp),
+            _ => Err(format!("expected 'linear', 'sine' or 'exp', got '{}'", s)),
+        }
+    }
+}
+
+impl fmt::Display for Curve {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Curve::Linear => "linear",
+            Curve::Sine => "sine",
+            Curve::Exp => "exp",
+        })
+    }
+}
+
+impl Curve {
+    /// Maps a phase in `[0, 1)` (0 = dark, 0.5 = peak) to a brightness in `[0, 1]`.
+    fn brightness(self, phase: f64) -> f64 {
+        match self {
+            Curve::Linear => triangle(phase),
+            Curve::Sine => (1.0 - (2.0 * PI * phase).cos()) / 2.0,
+            Curve::Exp => (EXP_CURVE_K * triangle(phase)).exp_m1() / EXP_CURVE_K.exp_m1(),
+        }
+    }
+}
+
+/// Rises linearly from 0 to 1 over the first half of the phase and falls back.
+fn triangle(phase: f64) -> f64 {
+    let phase = phase.rem_euclid(1.0);
+    if phase < 0.5 { 2.0 * phase } else { 2.0 * (1.0 - phase) }
+}
+
+/// Rescales a brightness in `[0, 1]` into `[floor, 1]`, so the trough of a
+/// breath stays faintly lit instead of sitting below the LED's turn-on point.
+fn with_floor(brightness: f64, floor: f64) -> f64 {
+    fade::lerp(floor, 1.0, brightness)
+}
+
+/// Maps the `--pwm-polarity` value to a polarity.
+///
+/// With the tutorial wiring (GPIO -> resistor -> LED -> GND) the LED is lit
+/// while the pin is high, so `normal` means a higher duty cycle is brighter.
+/// Use `inverse` when the pin sinks current (LED anode to 3.3V).
+fn parse_polarity(value: &str) -> Option<Polarity> {
+    match value {
+        "normal" => Some(Polarity::Normal),
+        "inverse" => Some(Polarity::Inverse),
+        _ => None,
+    }
+}
+
+/// Duty cycle that software PWM must output to emulate `polarity`.
+fn software_duty(brightness: f64, polarity: Polarity) -> f64 {
+    match polarity {
+        Polarity::Normal => brightness,
+        Polarity::Inverse => 1.0 - brightness,
+    }
+}
+
+/// The LED driven either by the hardware PWM peripheral or by rppal's
+/// software PWM on a plain GPIO pin.
+enum LedOutput {
+    Hardware(Pwm),
+    Software(OutputPin, Polarity),
+}
+
+impl LedOutput {
+    fn set_brightness(&mut self, brightness: f64) -> Result<(), Box<dyn Error>> {
+        match self {
+            LedOutput::Hardware(pwm) => pwm.set_duty_cycle(brightness)?,
+            LedOutput::Software(pin, polarity) => {
+                pin.set_pwm_frequency(PWM_FREQUENCY, software_duty(brightness, *polarity))?
+            }
+        }
+        Ok(())
+    }
+
+    fn stop(&mut self) -> Result<(), Box<dyn Error>> {
+        match self {
+            LedOutput::Hardware(pwm) => pwm.disable()?,
+            LedOutput::Software(pin, polarity) => {
+                pin.clear_pwm()?;
+                match polarity {
+                    Polarity::Normal => pin.set_low(),
+                    Polarity::Inverse => pin.set_high(),
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+const OPTIONS: &[Opt] = &[
+    Opt::Value("--fade-in-ms"),
+    Opt::Value("--fade-out-ms"),
+    Opt::Flag("--hardware-pwm"),
+    Opt::Flag("--keyboard"),
+    Opt::Flag("--pause-key"),
+    Opt::Value("--curve"),
+    Opt::Value("--min-brightness"),
+    Opt::Value("--pwm-polarity"),
+    Opt::Value("--log-every"),
+    Opt::Value("--lock-file"),
+];
+
+fn main() {
+    error::run(try_main);
+}
+
+fn try_main() -> Result<(), Box<dyn Error>> {
+    let args = Args::from_env()?.with_version(env!("CARGO_PKG_VERSION")).with_options(OPTIONS);
+    let fade_in = Duration::from_millis(args.value_or("--fade-in-ms", 0)?);
+    let fade_out = Duration::from_millis(args.value_or("--fade-out-ms", 0)?);
+    let hardware_pwm = args.flag("--hardware-pwm");
+    let keyboard_mode = args.flag("--keyboard");
+    let pause_key = args.flag("--pause-key");
+    let curve: Curve = args.value_or("--curve", Curve::Linear)?;
+    let min_brightness: f64 = args.value_or("--min-brightness", 0.0)?;
+    let polarity_arg = args.value_or("--pwm-polarity", "normal".to_string())?;
+    // Prints the current duty this often while breathing; off by default
+    let log_every: Option<u64> = args.value("--log-every")?;
+    let lock_file: Option<String> = args.value("--lock-file")?;
+    args.finish();
+    let polarity = parse_polarity(&polarity_arg)
+        .ok_or_else(|| format!("--pwm-polarity must be 'normal' or 'inverse', got '{}'", polarity_arg))?;
+    if !(0.0..1.0).contains(&min_brightness) {
+        return Err(format!("--min-brightness must be in [0, 1), got {}", min_brightness).into());
+    }
+    // Both read stdin
+    if pause_key && keyboard_mode {
+        return Err("--pause-key cannot be combined with --keyboard".into());
+    }
+
+    args.exit_if_dry_run();
+    let _lock = lock::acquire(lock_file.as_deref())?;
+
+    style::banner("Starting Breathing LED...");
+    style::banner("Press Ctrl+C to quit");
 
     let running = Arc::new(AtomicBool::new(true));
+    install_panic_hook(running.clone());
     let r = running.clone();
 
-    ctrlc::set_handler(move || {
-        println!("\nExiting...");
+    shutdown::set_handler(move || {
+        style::banner("\nExiting...");
         r.store(false, Ordering::SeqCst);
     })?;
 
-    let gpio = Gpio::new()?;
-    let mut led = gpio.get(LED_PIN)?.into_output();
+    let mut led = if hardware_pwm {
+        style::banner(&format!("Starting hardware PWM on GPIO pin {} ({:?})", LED_PIN, polarity));
+        LedOutput::Hardware(Pwm::with_frequency(PWM_CHANNEL, PWM_FREQUENCY, 0.0, polarity, true)?)
+    } else {
+        style::banner(&format!("Starting software PWM on GPIO pin {} ({:?})", LED_PIN, polarity));
+        let gpio = open_gpio()?;
+        LedOutput::Software(gpio.get(LED_PIN)?.into_output(), polarity)
+    };
 
-    println!("Starting software PWM on GPIO pin {}", LED_PIN);
+    // In --keyboard mode the keys set the brightness instead of the breathing ramp
+    let key_level = Arc::new(AtomicU8::new(0));
+    let _keys = if keyboard_mode {
+        println!("{}", keyboard::KEY_HELP);
+        let key_level = key_level.clone();
+        Some(keyboard::spawn_key_reader(move |action| {
+            let _ = key_level.fetch_update(Ordering::SeqCst, Ordering::SeqCst, |level| match action {
+                KeyAction::Increase => Some((level + KEY_STEP_PERCENT).min(100)),
+                KeyAction::Decrease => Some(level.saturating_sub(KEY_STEP_PERCENT)),
+                KeyAction::Next | KeyAction::Previous => None,
+            });
+        }))
+    } else {
+        None
+    };
 
-    let mut brightness = 0.0;
-    let mut increasing = true;
-    let step = 0.01;
+    let paused = Arc::new(AtomicBool::new(false));
+    if pause_key {
+        println!("{}", keyboard::PAUSE_HELP);
+        keyboard::spawn_pause_toggle(paused.clone());
+    }
+
+    // `envelope` is the linear ramp position; `output` is what the LED shows
+    // after applying the curve
+    let mut envelope = Envelope::new(0.01);
+    let mut output = 0.0;
     let delay = Duration::from_millis(10);
 
+    // Ease in to the first level instead of jumping to it
+    let first = if keyboard_mode {
+        key_level.load(Ordering::SeqCst) as f64 / 100.0
+    } else {
+        with_floor(curve.brightness(envelope.level() / 2.0), min_brightness)
+    };
+    if fade::ramp_while(&running, 0.0, first, fade_in, fade::DEFAULT_TICK, |b| led.set_brightness(b))? {
+        output = first;
+    }
+
+    let mut log_throttle = log_every.map(|ms| Throttle::new(Duration::from_millis(ms)));
     while running.load(Ordering::SeqCst) {
-        led.set_pwm_frequency(PWM_FREQUENCY, brightness)?;
+        led.set_brightness(output)?;
+        if let Some(throttle) = log_throttle.as_mut()
+            && throttle.should_emit(Instant::now())
+        {
+            println!("{}", pwm::format_duty(output));
+        }
 
-        if increasing {
-            brightness += step;
-            if brightness >= 1.0 {
-                brightness = 1.0;
-                increasing = false;
-            }
-        } else {
-            brightness -= step;
-            if brightness <= 0.0 {
-                brightness = 0.0;
-                increasing = true;
-            }
+        // Hold the current brightness while paused
+        if paused.load(Ordering::SeqCst) {
+            thread::sleep(delay);
+            continue;
         }
 
+        if keyboard_mode {
+            output = key_level.load(Ordering::SeqCst) as f64 / 100.0;
+            thread::sleep(delay);
+            continue;
+        }
+
+        let brightness = envelope.step();
+        let phase = if envelope.is_rising() { brightness / 2.0 } else { 1.0 - brightness / 2.0 };
+        output = with_floor(curve.brightness(phase), min_brightness);
+
         thread::sleep(delay);
     }
 
-    led.clear_pwm()?;
-    led.set_low();
-    println!("Breathing LED stopped");
+    fade::ramp(output, 0.0, fade_out, fade::DEFAULT_TICK, |b| led.set_brightness(b))?;
+    led.stop()?;
+    style::banner("Breathing LED stopped");
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CURVES: [Curve; 3] = [Curve::Linear, Curve::Sine, Curve::Exp];
+
+    fn close(a: f64, b: f64) -> bool {
+        (a - b).abs() < 1e-9
+    }
+
+    #[test]
+    fn every_curve_is_dark_at_zero_and_full_at_the_peak() {
+        for curve in CURVES {
+            assert!(close(curve.brightness(0.0), 0.0), "{:?}", curve);
+            assert!(close(curve.brightness(0.5), 1.0), "{:?}", curve);
+        }
+    }
+
+    #[test]
+    fn curves_are_symmetric_around_the_peak() {
+        for curve in CURVES {
+            for phase in [0.1, 0.25, 0.4] {
+                assert!(close(curve.brightness(phase), curve.brightness(1.0 - phase)), "{:?} at {}", curve, phase);
+            }
+        }
+    }
+
+    #[test]
+    fn curve_shapes() {
+        assert!(close(Curve::Linear.brightness(0.25), 0.5));
+        assert!(close(Curve::Sine.brightness(0.25), 0.5));
+        // The exponential curve lingers near dark
+        assert!(Curve::Exp.brightness(0.25) < 0.2);
+    }
+
+    #[test]
+    fn triangle_wraps_the_phase() {
+        assert!(close(triangle(1.25), 0.5));
+        assert!(close(triangle(-0.25), 0.5));
+    }
+
+    #[test]
+    fn floor_raises_the_trough() {
+        assert_eq!(with_floor(0.0, 0.1), 0.1);
+        assert_eq!(with_floor(1.0, 0.1), 1.0);
+        assert!(close(with_floor(0.5, 0.2), 0.6));
+    }
+
+    #[test]
+    fn polarity_and_duty() {
+        assert_eq!(parse_polarity("normal"), Some(Polarity::Normal));
+        assert_eq!(parse_polarity("inverse"), Some(Polarity::Inverse));
+        assert_eq!(parse_polarity("inverted"), None);
+        assert_eq!(software_duty(0.25, Polarity::Normal), 0.25);
+        assert_eq!(software_duty(0.25, Polarity::Inverse), 0.75);
+    }
+
+    #[test]
+    fn curve_names_round_trip() {
+        for curve in CURVES {
+            assert_eq!(curve.to_string().parse(), Ok(curve));
+        }
+        assert!("cubic".parse::<Curve>().is_err());
+    }
+}
@@ -0,0 +1,109 @@
+use common::args::{Args, Opt};
+use common::error::{self, open_gpio};
+use common::led::{Led, LedPolarity};
+use common::lock;
+use common::pins;
+use common::shutdown::{self, install_panic_hook};
+use common::style;
+use std::error::Error;
+use std::f64::consts::PI;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+// Same LED bar as LightWater
+const LED_PINS: [u8; 10] = [17, 18, 27, 22, 23, 24, 25, 2, 3, 8];
+const BREATH_PERIOD: Duration = Duration::from_millis(2000);
+const TICK: Duration = Duration::from_millis(10);
+
+/// Phase of the LED at `index` given the shared clock phase, both in `[0, 1)`.
+///
+/// `wavelength` is how many LEDs one full breath spans along the row, so each
+/// LED lags its neighbour by `1 / wavelength` of a breath.
+fn led_phase(clock: f64, index: usize, wavelength: f64) -> f64 {
+    (clock - index as f64 / wavelength).rem_euclid(1.0)
+}
+
+/// Sine breath: dark at phase 0, fully lit at phase 0.5.
+fn breath(phase: f64) -> f64 {
+    (1.0 - (2.0 * PI * phase).cos()) / 2.0
+}
+
+const OPTIONS: &[Opt] = &[
+    Opt::Value("--wavelength"),
+    Opt::Value("--lock-file"),
+];
+
+fn main() {
+    error::run(try_main);
+}
+
+fn try_main() -> Result<(), Box<dyn Error>> {
+    let args = Args::from_env()?.with_version(env!("CARGO_PKG_VERSION")).with_options(OPTIONS);
+    let wavelength: f64 = args.value_or("--wavelength", LED_PINS.len() as f64)?;
+    let lock_file: Option<String> = args.value("--lock-file")?;
+    args.finish();
+    if wavelength.is_nan() || wavelength <= 0.0 {
+        return Err(format!("--wavelength must be positive, got {}", wavelength).into());
+    }
+    pins::warn_special_pins(&LED_PINS);
+
+    args.exit_if_dry_run();
+    let _lock = lock::acquire(lock_file.as_deref())?;
+
+    style::banner("Program is starting...");
+    let gpio = open_gpio()?;
+    let mut leds = Vec::with_capacity(LED_PINS.len());
+    for &pin_num in LED_PINS.iter() {
+        // The bar's LEDs light when the pin is pulled low
+        leds.push(Led::new(gpio.get(pin_num)?.into_output_high(), LedPolarity::ActiveLow));
+    }
+
+    let running = Arc::new(AtomicBool::new(true));
+    install_panic_hook(running.clone());
+    let r = running.clone();
+    shutdown::set_handler(move || {
+        r.store(false, Ordering::SeqCst);
+    })?;
+
+    let start = Instant::now();
+    while running.load(Ordering::SeqCst) {
+        let clock = start.elapsed().as_secs_f64() / BREATH_PERIOD.as_secs_f64();
+        for (index, led) in leds.iter_mut().enumerate() {
+            led.set_brightness(breath(led_phase(clock, index, wavelength)))?;
+        }
+        thread::sleep(TICK);
+    }
+
+    for led in leds.iter_mut() {
+        led.clear()?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn close(a: f64, b: f64) -> bool {
+        (a - b).abs() < 1e-9
+    }
+
+    #[test]
+    fn each_led_lags_its_neighbour() {
+        assert!(close(led_phase(0.5, 0, 4.0), 0.5));
+        assert!(close(led_phase(0.5, 1, 4.0), 0.25));
+        // Lagging past the start wraps to the end of the previous breath
+        assert!(close(led_phase(0.1, 1, 4.0), 0.85));
+        // One wavelength along the row is back in step
+        assert!(close(led_phase(0.3, 4, 4.0), 0.3));
+    }
+
+    #[test]
+    fn breath_is_dark_at_zero_and_full_at_half() {
+        assert!(close(breath(0.0), 0.0));
+        assert!(close(breath(0.5), 1.0));
+        assert!(close(breath(0.25), 0.5));
+    }
+}
@@ -1,23 +1,103 @@
-use rppal::gpio::{Gpio, Level};
+use common::args::{Args, Opt};
+use common::button::{Button, ButtonEvent, ButtonLogic, DEFAULT_DEBOUNCE, DebounceStrategy, Debouncer};
+use common::buzzer::{self, Buzzer};
+use common::error::{self, open_gpio};
+use common::lock;
+use common::rate::LoopRateLimiter;
+use common::shutdown::{self, install_panic_hook};
+use common::style;
+use rppal::gpio::Level;
 use std::error::Error;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Instant;
 
 const LED_PIN: u8 = 17;
 const BTN_PIN: u8 = 18;
 
-fn main() -> Result<(), Box<dyn Error>> {
-    println!("Program is starting...");
-    let gpio = Gpio::new()?;
-    let mut led_pin = gpio.get(LED_PIN)?.into_output();
-    let btn_pin = gpio.get(BTN_PIN)?.into_input();
-    loop {
-        if btn_pin.is_low() {
-            // led_pin.set_high();
-            led_pin.write(Level::High);
-            println!("Button is pressed, led turned on >>>");
-        } else {
-            // led_pin.set_low();
-            led_pin.write(Level::Low);
-            println!("Button is released, led turned off <<<");
+const OPTIONS: &[Opt] = &[
+    Opt::Flag("--no-color"),
+    Opt::Flag("--beep"),
+    Opt::Value("--buzzer-pin"),
+    Opt::Value("--button-logic"),
+    Opt::Value("--debounce-algo"),
+    Opt::Value("--loop-hz"),
+    Opt::Value("--lock-file"),
+];
+
+fn main() {
+    error::run(try_main);
+}
+
+fn try_main() -> Result<(), Box<dyn Error>> {
+    let args = Args::from_env()?.with_version(env!("CARGO_PKG_VERSION")).with_options(OPTIONS);
+    let no_color = args.flag("--no-color");
+    let beep = args.flag("--beep");
+    let buzzer_pin: Option<u8> = args.value("--buzzer-pin")?;
+    let button_logic: ButtonLogic = args.value_or("--button-logic", ButtonLogic::default())?;
+    let debounce_algo: DebounceStrategy = args.value_or("--debounce-algo", DebounceStrategy::default())?;
+    // Upper bound on polls per second; unlimited by default
+    let loop_hz: Option<f64> = args.value("--loop-hz")?;
+    let lock_file: Option<String> = args.value("--lock-file")?;
+    args.finish();
+    if beep && buzzer_pin.is_none() {
+        return Err("--beep needs --buzzer-pin".into());
+    }
+    let mut limiter = loop_hz
+        .map(LoopRateLimiter::new)
+        .transpose()
+        .map_err(|e| format!("--loop-hz {}", e))?;
+    args.exit_if_dry_run();
+    let _lock = lock::acquire(lock_file.as_deref())?;
+    style::init(no_color);
+
+    style::banner("Program is starting...");
+    let gpio = open_gpio()?;
+    let mut led_pin = gpio.get(LED_PIN)?.into_output_low();
+    let button = Button::with_logic(gpio.get(BTN_PIN)?.into_input(), button_logic);
+    let mut buzzer = match buzzer_pin {
+        Some(pin) if beep => Some(Buzzer::new(gpio.get(pin)?.into_output_low())),
+        _ => None,
+    };
+
+    let running = Arc::new(AtomicBool::new(true));
+    install_panic_hook(running.clone());
+    let r = running.clone();
+    shutdown::set_handler(move || {
+        style::banner("\nEnding program");
+        r.store(false, Ordering::SeqCst);
+    })?;
+
+    // A button already held at startup lights the LED but does not beep
+    let mut debouncer = Debouncer::with_state(DEFAULT_DEBOUNCE, button.is_pressed()).with_strategy(debounce_algo);
+    if button.is_pressed() {
+        led_pin.write(Level::High);
+    }
+    while running.load(Ordering::SeqCst) {
+        match debouncer.update(button.is_pressed(), Instant::now()) {
+            Some(ButtonEvent::Pressed) => {
+                led_pin.write(Level::High);
+                println!("{}", style::on("Button is pressed, led turned on >>>"));
+                if let Some(buzzer) = buzzer.as_mut() {
+                    buzzer.beep(buzzer::DEFAULT_BEEP);
+                }
+            }
+            Some(ButtonEvent::Released) => {
+                led_pin.write(Level::Low);
+                println!("{}", style::off("Button is released, led turned off <<<"));
+            }
+            None => {}
+        }
+        if let Some(limiter) = limiter.as_mut() {
+            limiter.wait();
         }
     }
+
+    // Cleanup
+    led_pin.write(Level::Low);
+    if let Some(buzzer) = buzzer.as_mut() {
+        buzzer.off();
+    }
+    style::banner("Program is finished.");
+    Ok(())
 }
@@ -1,55 +1,148 @@
-use rppal::gpio::{Gpio, InputPin, OutputPin, Trigger};
+use common::args::{Args, Opt};
+use common::button::{self, ButtonLogic, EdgeTrigger, setup_button_trigger, wait_for_edge};
+use common::error::{self, open_gpio};
+use common::lock;
+use common::metrics::{self, Metrics};
+use common::shutdown::{self, install_panic_hook};
+use common::stats::LatencyStats;
+use common::style;
+use rppal::gpio::{InputPin, OutputPin};
 use std::error::Error;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
 const BUZZER_PIN: u8 = 17;
 const BTN_PIN: u8 = 18;
 const POLL_TIMEOUT_MS: u64 = 10;
+const TONE_DUTY_CYCLE: f64 = 0.5;
+
+/// How the buzzer pin is driven.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Drive {
+    /// Active buzzer: sounds while the pin is high.
+    Digital,
+    /// Passive buzzer: needs a square wave at this frequency in Hz.
+    Pwm(f64),
+}
+
+impl Drive {
+    /// `--tone-hz` selects PWM for a passive module; without it the pin is
+    /// switched digitally as in the original lesson.
+    fn from_tone(tone_hz: Option<f64>) -> Drive {
+        match tone_hz {
+            Some(hz) => Drive::Pwm(hz),
+            None => Drive::Digital,
+        }
+    }
+}
+
+/// The buzzer pin plus whether it is currently sounding, which cannot be
+/// read back from the pin while software PWM is toggling it.
+struct BuzzerOutput {
+    pin: OutputPin,
+    drive: Drive,
+    on: bool,
+}
+
+const OPTIONS: &[Opt] = &[
+    Opt::Flag("--no-color"),
+    Opt::Value("--button-logic"),
+    Opt::Value("--trigger"),
+    Opt::Value("--metrics"),
+    Opt::Value("--tone-hz"),
+    Opt::Flag("--measure-latency"),
+    Opt::Value("--lock-file"),
+];
+
+fn main() {
+    error::run(try_main);
+}
+
+fn try_main() -> Result<(), Box<dyn Error>> {
+    let args = Args::from_env()?.with_version(env!("CARGO_PKG_VERSION")).with_options(OPTIONS);
+    let no_color = args.flag("--no-color");
+    let button_logic: ButtonLogic = args.value_or("--button-logic", ButtonLogic::default())?;
+    let trigger: EdgeTrigger = args.value_or("--trigger", EdgeTrigger::default())?;
+    let metrics_addr: Option<String> = args.value("--metrics")?;
+    let tone_hz: Option<f64> = args.value("--tone-hz")?;
+    // Time from each button edge to the buzzer switching, printed on exit
+    let measure_latency = args.flag("--measure-latency");
+    let lock_file: Option<String> = args.value("--lock-file")?;
+    args.finish();
+    if let Some(hz) = tone_hz
+        && (hz.is_nan() || hz <= 0.0)
+    {
+        return Err(format!("--tone-hz must be positive, got {}", hz).into());
+    }
+    if !trigger.fires_on_press(button_logic) {
+        eprintln!(
+            "Warning: --trigger {:?} with {:?} wiring fires on release, not press",
+            trigger, button_logic
+        );
+    }
+
+    args.exit_if_dry_run();
+    let _lock = lock::acquire(lock_file.as_deref())?;
+    style::init(no_color);
 
-fn main() -> Result<(), Box<dyn Error>> {
     print_startup_message();
     
-    let (mut buzzer_pin, mut btn_pin) = initialize_gpio()?;
-    initialize_buzzer(&mut buzzer_pin);
-    setup_button_interrupt(&mut btn_pin)?;
+    let (buzzer_pin, mut btn_pin) = initialize_gpio()?;
+    let mut buzzer = BuzzerOutput {
+        pin: buzzer_pin,
+        drive: Drive::from_tone(tone_hz),
+        on: false,
+    };
+    initialize_buzzer(&mut buzzer)?;
+    setup_button_trigger(&mut btn_pin, trigger)?;
     
     let running = setup_signal_handler()?;
+    install_panic_hook(running.clone());
+
+    let metrics = Arc::new(Metrics::default());
+    if let Some(addr) = &metrics_addr {
+        metrics::serve(addr.as_str(), metrics.clone())?;
+        println!("Serving metrics on http://{}/metrics", addr);
+    }
     
-    println!("Waiting for button press...");
+    style::banner("Waiting for button press...");
     
-    run_interrupt_loop(&running, &mut buzzer_pin, &mut btn_pin)?;
+    let mut latency = measure_latency.then(LatencyStats::new);
+    run_interrupt_loop(&running, &mut buzzer, &mut btn_pin, button_logic, trigger, &metrics, latency.as_mut())?;
+    if let Some(latency) = &latency {
+        println!("Edge-to-buzzer latency: {}", latency.summary());
+    }
     
-    cleanup(&mut buzzer_pin, &mut btn_pin)?;
+    cleanup(&mut buzzer, &mut btn_pin)?;
     
     Ok(())
 }
 
 fn print_startup_message() {
-    println!("Program is starting...");
+    style::banner("Program is starting...");
 }
 
 fn initialize_gpio() -> Result<(OutputPin, InputPin), Box<dyn Error>> {
-    let gpio = Gpio::new()?;
+    let gpio = open_gpio()?;
     let buzzer_pin = gpio.get(BUZZER_PIN)?.into_output();
     let btn_pin = gpio.get(BTN_PIN)?.into_input();
     Ok((buzzer_pin, btn_pin))
 }
 
-fn initialize_buzzer(buzzer_pin: &mut OutputPin) {
-    buzzer_pin.set_low();
-}
-
-fn setup_button_interrupt(btn_pin: &mut InputPin) -> Result<(), Box<dyn Error>> {
-    btn_pin.set_interrupt(Trigger::Both, None)?;
-    Ok(())
+fn initialize_buzzer(buzzer: &mut BuzzerOutput) -> Result<(), Box<dyn Error>> {
+    if let Drive::Pwm(hz) = buzzer.drive {
+        println!("Driving a passive buzzer at {} Hz", hz);
+    }
+    turn_off_buzzer(buzzer)
 }
 
+/// Clears the returned flag on Ctrl+C, SIGTERM or SIGHUP, so a systemd
+/// stop runs the same cleanup as Ctrl+C.
 fn setup_signal_handler() -> Result<Arc<AtomicBool>, Box<dyn Error>> {
     let running = Arc::new(AtomicBool::new(true));
     let r = running.clone();
-    ctrlc::set_handler(move || {
+    shutdown::set_handler(move || {
         r.store(false, Ordering::SeqCst);
     })?;
     Ok(running)
@@ -57,50 +150,111 @@ fn setup_signal_handler() -> Result<Arc<AtomicBool>, Box<dyn Error>> {
 
 fn run_interrupt_loop(
     running: &Arc<AtomicBool>,
-    buzzer_pin: &mut OutputPin,
+    buzzer: &mut BuzzerOutput,
     btn_pin: &mut InputPin,
+    button_logic: ButtonLogic,
+    trigger: EdgeTrigger,
+    metrics: &Metrics,
+    mut latency: Option<&mut LatencyStats>,
 ) -> Result<(), Box<dyn Error>> {
     while running.load(Ordering::SeqCst) {
-        if let Some(_) = btn_pin.poll_interrupt(true, Some(Duration::from_millis(POLL_TIMEOUT_MS)))? {
-            handle_button_interrupt(buzzer_pin, btn_pin);
+        if !wait_for_edge(btn_pin, Duration::from_millis(POLL_TIMEOUT_MS))? {
+            continue;
+        }
+        let edge = Instant::now();
+        let actuated = if trigger == EdgeTrigger::Both {
+            handle_button_interrupt(buzzer, btn_pin, button_logic, metrics)?
+        } else {
+            // Only one edge is reported, so each one toggles the buzzer
+            metrics.button_presses_total.inc();
+            toggle_buzzer(buzzer)?
+        };
+        if let Some(latency) = latency.as_deref_mut() {
+            latency.record(actuated - edge);
         }
     }
     Ok(())
 }
 
-fn handle_button_interrupt(buzzer_pin: &mut OutputPin, btn_pin: &InputPin) {
-    if is_button_pressed(btn_pin) {
-        turn_on_buzzer(buzzer_pin);
+fn handle_button_interrupt(
+    buzzer: &mut BuzzerOutput,
+    btn_pin: &InputPin,
+    button_logic: ButtonLogic,
+    metrics: &Metrics,
+) -> Result<Instant, Box<dyn Error>> {
+    if is_button_pressed(btn_pin, button_logic) {
+        metrics.button_presses_total.inc();
+        turn_on_buzzer(buzzer)?;
+        let actuated = Instant::now();
         print_buzzer_on_message();
+        Ok(actuated)
     } else {
-        turn_off_buzzer(buzzer_pin);
+        turn_off_buzzer(buzzer)?;
+        let actuated = Instant::now();
         print_buzzer_off_message();
+        Ok(actuated)
     }
 }
 
-fn is_button_pressed(btn_pin: &InputPin) -> bool {
-    btn_pin.is_low()
+fn is_button_pressed(btn_pin: &InputPin, button_logic: ButtonLogic) -> bool {
+    button::is_pressed(btn_pin.read(), button_logic)
 }
 
-fn turn_on_buzzer(buzzer_pin: &mut OutputPin) {
-    buzzer_pin.set_high();
+/// Returns when the buzzer was switched, before the message is printed.
+fn toggle_buzzer(buzzer: &mut BuzzerOutput) -> Result<Instant, Box<dyn Error>> {
+    if buzzer.on {
+        turn_off_buzzer(buzzer)?;
+        let actuated = Instant::now();
+        print_buzzer_off_message();
+        Ok(actuated)
+    } else {
+        turn_on_buzzer(buzzer)?;
+        let actuated = Instant::now();
+        print_buzzer_on_message();
+        Ok(actuated)
+    }
 }
 
-fn turn_off_buzzer(buzzer_pin: &mut OutputPin) {
-    buzzer_pin.set_low();
+fn turn_on_buzzer(buzzer: &mut BuzzerOutput) -> Result<(), Box<dyn Error>> {
+    match buzzer.drive {
+        Drive::Digital => buzzer.pin.set_high(),
+        Drive::Pwm(hz) => buzzer.pin.set_pwm_frequency(hz, TONE_DUTY_CYCLE)?,
+    }
+    buzzer.on = true;
+    Ok(())
+}
+
+fn turn_off_buzzer(buzzer: &mut BuzzerOutput) -> Result<(), Box<dyn Error>> {
+    if let Drive::Pwm(_) = buzzer.drive {
+        buzzer.pin.clear_pwm()?;
+    }
+    buzzer.pin.set_low();
+    buzzer.on = false;
+    Ok(())
 }
 
 fn print_buzzer_on_message() {
-    println!("Button is pressed, buzzer turned on >>>");
+    println!("{}", style::on("Button is pressed, buzzer turned on >>>"));
 }
 
 fn print_buzzer_off_message() {
-    println!("Button is released, buzzer turned off <<<");
+    println!("{}", style::off("Button is released, buzzer turned off <<<"));
 }
 
-fn cleanup(buzzer_pin: &mut OutputPin, btn_pin: &mut InputPin) -> Result<(), Box<dyn Error>> {
-    println!("Ending program");
+fn cleanup(buzzer: &mut BuzzerOutput, btn_pin: &mut InputPin) -> Result<(), Box<dyn Error>> {
+    style::banner("Ending program");
     let _ = btn_pin.clear_interrupt();
-    turn_off_buzzer(buzzer_pin);
-    Ok(())
+    // Also stops the PWM thread in the passive case
+    turn_off_buzzer(buzzer)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tone_selects_pwm() {
+        assert_eq!(Drive::from_tone(None), Drive::Digital);
+        assert_eq!(Drive::from_tone(Some(2000.0)), Drive::Pwm(2000.0));
+    }
 }
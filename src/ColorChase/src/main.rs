@@ -0,0 +1,176 @@
+use common::args::{Args, Opt};
+use common::color::{RgbCommon, hsv_to_rgb};
+use common::error::{self, open_gpio};
+use common::led::Led;
+use common::lock;
+use common::pins::warn_special_pins;
+use common::shutdown::{self, install_panic_hook};
+use common::style;
+use std::error::Error;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+// Red, green and blue pins of each RGB LED along the row (common anode by
+// default, see --rgb-common); the first one is the RGBLED lesson's wiring
+const DEFAULT_PINS: [u8; 9] = [17, 18, 27, 22, 23, 24, 25, 5, 6];
+const DEFAULT_SPEED: f64 = 4.0;
+const DEFAULT_HUE_STEP: f64 = 40.0;
+
+/// Which LED carries the highlight and what colour it is.
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Chase {
+    position: usize,
+    hue: f64,
+}
+
+impl Chase {
+    /// Moves the highlight to the next LED, wrapping at the end of the row,
+    /// and turns the hue by `hue_step` degrees.
+    fn advance(&mut self, led_count: usize, hue_step: f64) {
+        self.position = (self.position + 1) % led_count.max(1);
+        self.hue = (self.hue + hue_step).rem_euclid(360.0);
+    }
+}
+
+/// One RGB LED as three software-PWM channels.
+struct RgbLed([Led; 3]);
+
+impl RgbLed {
+    fn set(&mut self, rgb: (f64, f64, f64)) -> Result<(), Box<dyn Error>> {
+        let [r, g, b] = &mut self.0;
+        r.set_brightness(rgb.0)?;
+        g.set_brightness(rgb.1)?;
+        b.set_brightness(rgb.2)?;
+        Ok(())
+    }
+
+    fn clear(&mut self) -> Result<(), Box<dyn Error>> {
+        for led in &mut self.0 {
+            led.clear()?;
+        }
+        Ok(())
+    }
+}
+
+/// Clamps an HSV component option to `[0, 1]`, warning when it had to.
+fn unit_option(name: &str, value: f64) -> Result<f64, String> {
+    if value.is_nan() {
+        return Err(format!("{} must be between 0 and 1, got {}", name, value));
+    }
+    let clamped = value.clamp(0.0, 1.0);
+    if clamped != value {
+        eprintln!("Warning: {} {} is outside 0-1, using {}", name, value, clamped);
+    }
+    Ok(clamped)
+}
+
+const OPTIONS: &[Opt] = &[
+    Opt::Value("--pins"),
+    Opt::Value("--speed"),
+    Opt::Value("--hue-step"),
+    Opt::Value("--saturation"),
+    Opt::Value("--value"),
+    Opt::Value("--rgb-common"),
+    Opt::Value("--lock-file"),
+];
+
+fn main() {
+    error::run(try_main);
+}
+
+fn try_main() -> Result<(), Box<dyn Error>> {
+    let args = Args::from_env()?.with_version(env!("CARGO_PKG_VERSION")).with_options(OPTIONS);
+    let pins: Vec<u8> = args.list("--pins")?.unwrap_or(DEFAULT_PINS.to_vec());
+    // Steps per second
+    let speed: f64 = args.value_or("--speed", DEFAULT_SPEED)?;
+    let hue_step: f64 = args.value_or("--hue-step", DEFAULT_HUE_STEP)?;
+    // Lower saturation gives pastel colours, lower value dims them
+    let saturation: f64 = args.value_or("--saturation", 1.0)?;
+    let value: f64 = args.value_or("--value", 1.0)?;
+    let rgb_common: RgbCommon = args.value_or("--rgb-common", RgbCommon::default())?;
+    let lock_file: Option<String> = args.value("--lock-file")?;
+    args.finish();
+    let saturation = unit_option("--saturation", saturation)?;
+    let value = unit_option("--value", value)?;
+    if pins.is_empty() || !pins.len().is_multiple_of(3) {
+        return Err(format!("--pins needs r,g,b triples, got {} pins", pins.len()).into());
+    }
+    if speed.is_nan() || speed <= 0.0 {
+        return Err(format!("--speed must be positive, got {}", speed).into());
+    }
+    warn_special_pins(&pins);
+    args.exit_if_dry_run();
+    let _lock = lock::acquire(lock_file.as_deref())?;
+
+    style::banner("Program is starting...");
+    let gpio = open_gpio()?;
+    let mut leds = Vec::with_capacity(pins.len() / 3);
+    for rgb in pins.chunks(3) {
+        let channel = |pin: u8| -> Result<Led, Box<dyn Error>> {
+            let mut output = gpio.get(pin)?.into_output();
+            output.write(rgb_common.off_level());
+            Ok(Led::new(output, rgb_common.led_polarity()))
+        };
+        leds.push(RgbLed([channel(rgb[0])?, channel(rgb[1])?, channel(rgb[2])?]));
+    }
+
+    let running = Arc::new(AtomicBool::new(true));
+    install_panic_hook(running.clone());
+    let r = running.clone();
+    shutdown::set_handler(move || {
+        r.store(false, Ordering::SeqCst);
+    })?;
+
+    let delay = Duration::from_secs_f64(1.0 / speed);
+    let mut chase = Chase {
+        position: 0,
+        hue: 0.0,
+    };
+    while running.load(Ordering::SeqCst) {
+        for (i, led) in leds.iter_mut().enumerate() {
+            if i == chase.position {
+                led.set(hsv_to_rgb(chase.hue, saturation, value))?;
+            } else {
+                led.set((0.0, 0.0, 0.0))?;
+            }
+        }
+        thread::sleep(delay);
+        chase.advance(leds.len(), hue_step);
+    }
+
+    for led in leds.iter_mut() {
+        led.clear()?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn chase_wraps_position_and_hue() {
+        let mut chase = Chase { position: 2, hue: 350.0 };
+        chase.advance(3, 20.0);
+        assert_eq!(chase, Chase { position: 0, hue: 10.0 });
+        chase.advance(3, -30.0);
+        assert_eq!(chase, Chase { position: 1, hue: 340.0 });
+    }
+
+    #[test]
+    fn empty_row_does_not_panic() {
+        let mut chase = Chase { position: 0, hue: 0.0 };
+        chase.advance(0, 10.0);
+        assert_eq!(chase.position, 0);
+    }
+
+    #[test]
+    fn unit_options_are_clamped() {
+        assert_eq!(unit_option("--value", 0.5), Ok(0.5));
+        assert_eq!(unit_option("--value", 1.5), Ok(1.0));
+        assert_eq!(unit_option("--value", -0.5), Ok(0.0));
+        assert!(unit_option("--value", f64::NAN).is_err());
+    }
+}
@@ -0,0 +1,130 @@
+use std::error::Error;
+
+use adc::{Adc, AnalogReader};
+use common::args::{Args, Opt};
+use common::error::{self, AppError};
+use rppal::system::DeviceInfo;
+use serde::Serialize;
+
+// Keep in sync with the rppal version in Cargo.toml
+const RPPAL_VERSION: &str = "0.22.1";
+
+/// Default wiring of the examples in this repository.
+const PIN_ASSIGNMENTS: &[(&str, &str, u8)] = &[
+    ("Blink", "led", 17),
+    ("ButtonSwitch", "led", 17),
+    ("ButtonSwitch", "button", 18),
+    ("TableLamp", "led", 17),
+    ("TableLamp", "button", 18),
+    ("Buzzer", "buzzer", 17),
+    ("Buzzer", "button", 18),
+    ("Alertor", "buzzer", 17),
+    ("Alertor", "button", 18),
+    ("BreathingLED", "led", 18),
+    ("Softlight", "led", 17),
+    ("RGBLED", "red", 17),
+    ("RGBLED", "green", 18),
+    ("RGBLED", "blue", 27),
+    ("potentionmeter_rgb", "red", 22),
+    ("potentionmeter_rgb", "green", 27),
+    ("potentionmeter_rgb", "blue", 17),
+    ("PotServo", "servo", 18),
+];
+
+/// Everything `Info` reports, printed as one JSON object. Field names are
+/// part of the output format; rename only with care.
+#[derive(Serialize)]
+struct HardwareInfo {
+    tool_version: &'static str,
+    rppal_version: &'static str,
+    model: Option<String>,
+    i2c_buses_probed: Vec<u8>,
+    adc: Option<AdcInfo>,
+    /// Devices answering in the ADC address range; only scanned when no ADC was detected.
+    i2c_devices: Vec<I2cDevice>,
+    pins: Vec<PinAssignment>,
+}
+
+#[derive(Serialize)]
+struct AdcInfo {
+    chip: &'static str,
+    bus: u8,
+    address: u16,
+    channel_count: u8,
+    resolution_bits: u8,
+}
+
+#[derive(Serialize)]
+struct I2cDevice {
+    bus: u8,
+    address: u16,
+}
+
+#[derive(Serialize)]
+struct PinAssignment {
+    example: &'static str,
+    role: &'static str,
+    gpio: u8,
+}
+
+fn probe() -> HardwareInfo {
+    let adc = Adc::detect(&adc::DEFAULT_BUSES, adc::DEFAULT_RETRIES)
+        .ok()
+        .map(|adc| AdcInfo {
+            chip: adc.chip().name(),
+            bus: adc.bus(),
+            address: adc.chip().address(),
+            channel_count: adc.channel_count(),
+            resolution_bits: adc.resolution_bits(),
+        });
+    let i2c_devices = if adc.is_none() {
+        adc::scan(&adc::DEFAULT_BUSES)
+            .into_iter()
+            .map(|(bus, address)| I2cDevice { bus, address })
+            .collect()
+    } else {
+        Vec::new()
+    };
+
+    HardwareInfo {
+        tool_version: env!("CARGO_PKG_VERSION"),
+        rppal_version: RPPAL_VERSION,
+        model: DeviceInfo::new().ok().map(|info| info.model().to_string()),
+        i2c_buses_probed: adc::DEFAULT_BUSES.to_vec(),
+        adc,
+        i2c_devices,
+        pins: PIN_ASSIGNMENTS
+            .iter()
+            .map(|&(example, role, gpio)| PinAssignment { example, role, gpio })
+            .collect(),
+    }
+}
+
+fn main() {
+    error::run(try_main);
+}
+
+/// `--csv`: one reading of every channel of the detected ADC, with a header
+/// row, instead of the JSON report.
+fn print_csv() -> Result<(), Box<dyn Error>> {
+    let mut adc = Adc::detect(&adc::DEFAULT_BUSES, adc::DEFAULT_RETRIES)
+        .map_err(|e| AppError::AdcNotFound(e.to_string()))?;
+    let row: Vec<String> = adc.read_all()?.iter().map(u8::to_string).collect();
+    println!("{}", adc::csv_header(&adc));
+    println!("{}", row.join(","));
+    Ok(())
+}
+
+fn try_main() -> Result<(), Box<dyn Error>> {
+    let args = Args::from_env()?.with_version(env!("CARGO_PKG_VERSION")).with_options(&[Opt::Flag("--csv")]);
+    let csv = args.flag("--csv");
+    args.finish();
+    args.exit_if_dry_run();
+
+    if csv {
+        return print_csv();
+    }
+    let info = probe();
+    println!("{}", serde_json::to_string_pretty(&info)?);
+    Ok(())
+}
@@ -0,0 +1,169 @@
+use adc::Adc;
+use common::args::{Args, Opt};
+use common::color::{self, RgbCommon, hsv_to_rgb};
+use common::error::{self, AppError, open_gpio};
+use common::lock;
+use common::shutdown::{self, install_panic_hook};
+use common::style;
+use common::throttle::Throttle;
+use std::error::Error;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+// Same RGB LED wiring as the RGBLED lesson
+const RED_PIN: u8 = 17;
+const GREEN_PIN: u8 = 18;
+const BLUE_PIN: u8 = 27;
+// Joystick lesson wiring: Y on channel 0, X on channel 1
+const DEFAULT_X_CHANNEL: u8 = 1;
+const DEFAULT_Y_CHANNEL: u8 = 0;
+// Reading at rest, and how far either side of it still counts as centred
+const DEFAULT_CENTER: u8 = 128;
+const DEFAULT_DEADZONE: u8 = 8;
+const LOOP_DELAY: Duration = Duration::from_millis(20);
+const LOG_EVERY: Duration = Duration::from_millis(500);
+
+/// Calibration of one joystick axis.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct Axis {
+    center: u8,
+    deadzone: u8,
+}
+
+impl Axis {
+    /// Deflection of `raw` from the centre in `[-1, 1]`. Readings within
+    /// the deadzone are 0, and each side is scaled separately so both ends
+    /// reach full deflection even if the centre is off mid-scale.
+    fn offset(self, raw: u8) -> f64 {
+        let raw = raw as f64;
+        let low = self.center.saturating_sub(self.deadzone) as f64;
+        let high = self.center.saturating_add(self.deadzone) as f64;
+        if raw > high {
+            (raw - high) / (u8::MAX as f64 - high).max(1.0)
+        } else if raw < low {
+            -(low - raw) / low.max(1.0)
+        } else {
+            0.0
+        }
+    }
+}
+
+/// Maps the X and Y readings to a hue in `[0, 360]` degrees and a value in
+/// `[0, 1]`. A centred stick gives hue 180 at half brightness.
+fn joystick_to_hue_value(x: u8, y: u8, axis: Axis) -> (f64, f64) {
+    let hue = (axis.offset(x) + 1.0) * 180.0;
+    let value = (axis.offset(y) + 1.0) / 2.0;
+    (hue, value)
+}
+
+const OPTIONS: &[Opt] = &[
+    Opt::Value("--x-channel"),
+    Opt::Value("--y-channel"),
+    Opt::Value("--center"),
+    Opt::Value("--deadzone"),
+    Opt::Value("--rgb-common"),
+    Opt::Value("--lock-file"),
+];
+
+fn main() {
+    error::run(try_main);
+}
+
+fn try_main() -> Result<(), Box<dyn Error>> {
+    let args = Args::from_env()?.with_version(env!("CARGO_PKG_VERSION")).with_options(OPTIONS);
+    let x_channel = args.value_or("--x-channel", DEFAULT_X_CHANNEL)?;
+    let y_channel = args.value_or("--y-channel", DEFAULT_Y_CHANNEL)?;
+    let axis = Axis {
+        center: args.value_or("--center", DEFAULT_CENTER)?,
+        deadzone: args.value_or("--deadzone", DEFAULT_DEADZONE)?,
+    };
+    let rgb_common: RgbCommon = args.value_or("--rgb-common", RgbCommon::default())?;
+    let lock_file: Option<String> = args.value("--lock-file")?;
+    args.finish();
+    args.exit_if_dry_run();
+    let _lock = lock::acquire(lock_file.as_deref())?;
+
+    style::banner("Program is starting ...");
+    let mut adc = Adc::detect(&adc::DEFAULT_BUSES, adc::DEFAULT_RETRIES)
+        .map_err(|e| AppError::AdcNotFound(e.to_string()))?;
+    adc::check_channel(adc.chip(), x_channel)?;
+    adc::check_channel(adc.chip(), y_channel)?;
+    println!("Detected I2C device: {} on bus {}", adc.chip().name(), adc.bus());
+
+    let gpio = open_gpio()?;
+    let mut pins = [
+        gpio.get(RED_PIN)?.into_output(),
+        gpio.get(GREEN_PIN)?.into_output(),
+        gpio.get(BLUE_PIN)?.into_output(),
+    ];
+    for pin in pins.iter_mut() {
+        pin.write(rgb_common.off_level());
+    }
+
+    let running = Arc::new(AtomicBool::new(true));
+    install_panic_hook(running.clone());
+    let r = running.clone();
+    shutdown::set_handler(move || {
+        style::banner("\nEnding program");
+        r.store(false, Ordering::SeqCst);
+    })?;
+
+    let mut log_throttle = Throttle::new(LOG_EVERY);
+    while running.load(Ordering::SeqCst) {
+        match (adc.read(x_channel), adc.read(y_channel)) {
+            (Ok(x), Ok(y)) => {
+                let (hue, value) = joystick_to_hue_value(x, y, axis);
+                color::apply_rgb(&mut pins, hsv_to_rgb(hue, 1.0, value), rgb_common)?;
+                if log_throttle.should_emit(Instant::now()) {
+                    println!("X: {}, Y: {}, hue: {:.0}, value: {:.2}", x, y, hue, value);
+                }
+            }
+            (Err(e), _) | (_, Err(e)) => eprintln!("Error reading ADC: {}", e),
+        }
+        thread::sleep(LOOP_DELAY);
+    }
+
+    color::clear_rgb(&mut pins, rgb_common)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const AXIS: Axis = Axis { center: 128, deadzone: 10 };
+
+    #[test]
+    fn deadzone_reads_as_centred() {
+        for raw in [118, 128, 138] {
+            assert_eq!(AXIS.offset(raw), 0.0);
+        }
+    }
+
+    #[test]
+    fn both_ends_reach_full_deflection() {
+        assert_eq!(AXIS.offset(0), -1.0);
+        assert_eq!(AXIS.offset(255), 1.0);
+        // An off-centre stick still reaches both ends
+        let skewed = Axis { center: 200, deadzone: 0 };
+        assert_eq!(skewed.offset(0), -1.0);
+        assert_eq!(skewed.offset(255), 1.0);
+        assert!((skewed.offset(100) + 0.5).abs() < 1e-9);
+    }
+
+    #[test]
+    fn extreme_calibration_does_not_divide_by_zero() {
+        let axis = Axis { center: 255, deadzone: 255 };
+        assert_eq!(axis.offset(0), -0.0);
+        assert!(Axis { center: 0, deadzone: 0 }.offset(255).is_finite());
+    }
+
+    #[test]
+    fn centred_stick_is_cyan_at_half_brightness() {
+        assert_eq!(joystick_to_hue_value(128, 128, AXIS), (180.0, 0.5));
+        assert_eq!(joystick_to_hue_value(0, 255, AXIS), (0.0, 1.0));
+        assert_eq!(joystick_to_hue_value(255, 0, AXIS), (360.0, 0.0));
+    }
+}
@@ -1,50 +1,540 @@
-use rppal::gpio::{Gpio, Level};
+use common::args::{ArgError, Args, Opt};
+use common::error::{self, open_gpio};
+use common::keyboard;
+use common::led::LedPolarity;
+use common::lock;
+use common::pins;
+use common::report::Reporter;
+use common::shutdown::{self, install_panic_hook};
+use common::style;
 use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+use std::str::FromStr;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 use std::thread::sleep;
-use std::time::Duration;
+use std::time::{Duration, Instant};
+
+const LED_PINS: [u8; 10] = [17, 18, 27, 22, 23, 24, 25, 2, 3, 8];
+const DEFAULT_DELAY_MS: u64 = 100;
+// How often a paused sweep checks for resume or Ctrl+C
+const PAUSE_POLL: Duration = Duration::from_millis(50);
+// LEDs lit at once by the `knightrider` pattern
+const KNIGHT_RIDER_WIDTH: usize = 3;
+
+/// A named animation for `--playlist`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Pattern {
+    /// The lesson's single moving light (two with `--mirror`).
+    Sweep,
+    /// A short bar bouncing between the ends.
+    KnightRider,
+    /// The whole bar flashing on and off.
+    BlinkAll,
+}
+
+impl FromStr for Pattern {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "sweep" => Ok(Pattern::Sweep),
+            "knightrider" => Ok(Pattern::KnightRider),
+            "blink-all" => Ok(Pattern::BlinkAll),
+            _ => Err(format!("expected 'sweep', 'knightrider' or 'blink-all', got '{}'", s)),
+        }
+    }
+}
+
+impl fmt::Display for Pattern {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Pattern::Sweep => "sweep",
+            Pattern::KnightRider => "knightrider",
+            Pattern::BlinkAll => "blink-all",
+        })
+    }
+}
+
+/// One `pattern:seconds` entry of a `--playlist`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct PlaylistEntry {
+    pattern: Pattern,
+    duration: Duration,
+}
+
+/// Parses `sweep:5,knightrider:10,blink-all:3`. Seconds may be fractional
+/// but must be positive, and at least one entry is needed.
+fn parse_playlist(text: &str) -> Result<Vec<PlaylistEntry>, String> {
+    let mut entries = Vec::new();
+    for token in text.split(',').map(str::trim) {
+        let (name, seconds) = token
+            .split_once(':')
+            .ok_or_else(|| format!("playlist entry '{}' must be 'pattern:seconds'", token))?;
+        let pattern = name
+            .trim()
+            .parse()
+            .map_err(|e| format!("playlist entry '{}': {}", token, e))?;
+        let duration = seconds
+            .trim()
+            .parse::<f64>()
+            .ok()
+            .filter(|s| s.is_finite() && *s > 0.0)
+            .map(Duration::from_secs_f64)
+            .ok_or_else(|| format!("playlist entry '{}': seconds must be a positive number", token))?;
+        entries.push(PlaylistEntry { pattern, duration });
+    }
+    Ok(entries)
+}
+
+/// Cycles through playlist entries by wall-clock time, looping forever.
+struct Playlist {
+    entries: Vec<PlaylistEntry>,
+    index: usize,
+    // When the current entry started
+    started: Instant,
+}
+
+impl Playlist {
+    /// `entries` must not be empty, as `parse_playlist` guarantees.
+    fn new(entries: Vec<PlaylistEntry>, now: Instant) -> Self {
+        Self {
+            entries,
+            index: 0,
+            started: now,
+        }
+    }
+
+    /// Skips past every entry whose time is up and returns the pattern
+    /// playing at `now`. Entries are timed back to back, so a slow frame
+    /// does not stretch the schedule.
+    fn current(&mut self, now: Instant) -> Pattern {
+        while now.saturating_duration_since(self.started) >= self.entries[self.index].duration {
+            self.started += self.entries[self.index].duration;
+            self.index = (self.index + 1) % self.entries.len();
+        }
+        self.entries[self.index].pattern
+    }
+}
+
+/// Position of the moving light: the LED lit next and the direction of travel.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct SweepState {
+    position: usize,
+    forward: bool,
+}
+
+impl SweepState {
+    const START: SweepState = SweepState {
+        position: 0,
+        forward: true,
+    };
+
+    /// Moves to the next LED, bouncing at both ends so each end LED is lit
+    /// twice in a row, as in the original two-loop sweep. Returns `true`
+    /// when a full back-and-forth sweep has just completed.
+    fn advance(&mut self, led_count: usize) -> bool {
+        if self.forward {
+            if self.position + 1 >= led_count {
+                self.forward = false;
+            } else {
+                self.position += 1;
+            }
+            false
+        } else if self.position == 0 {
+            self.forward = true;
+            true
+        } else {
+            self.position -= 1;
+            false
+        }
+    }
+
+    /// State file format: `<position> forward|backward`.
+    fn serialize(&self) -> String {
+        let direction = if self.forward { "forward" } else { "backward" };
+        format!("{} {}\n", self.position, direction)
+    }
+
+    fn parse(text: &str) -> Option<SweepState> {
+        let mut parts = text.split_whitespace();
+        let position = parts.next()?.parse().ok()?;
+        let forward = match parts.next()? {
+            "forward" => true,
+            "backward" => false,
+            _ => return None,
+        };
+        if parts.next().is_some() {
+            return None;
+        }
+        Some(SweepState { position, forward })
+    }
+
+    /// Uses a saved state only if it fits the current LED count.
+    fn resume_or_reset(saved: Option<SweepState>, led_count: usize) -> SweepState {
+        match saved {
+            Some(state) if state.position < led_count => state,
+            _ => SweepState::START,
+        }
+    }
+}
+
+/// LEDs lit at `position` in `--mirror` mode: `position` and its mirror
+/// image from the other end. With an odd count the middle LED is its own
+/// mirror image and is returned once.
+fn mirror_indices(position: usize, led_count: usize) -> (usize, Option<usize>) {
+    let other = led_count - 1 - position;
+    (position, (other != position).then_some(other))
+}
+
+/// LEDs lit by the `knightrider` pattern: a bar centred on `position`,
+/// cut short at the ends of the strip.
+fn knight_rider_indices(position: usize, led_count: usize) -> Vec<usize> {
+    let half = KNIGHT_RIDER_WIDTH / 2;
+    (position.saturating_sub(half)..(position + half + 1).min(led_count)).collect()
+}
+
+/// When each of `count` LEDs lit in the same step is turned on, relative to
+/// the start of the step. Spacing the turn-ons by `stagger` spreads the
+/// inrush current for supplies that cannot take them all at once.
+fn stagger_schedule(count: usize, stagger: Duration) -> Vec<Duration> {
+    (0..count as u32).map(|i| stagger * i).collect()
+}
+
+fn load_state(path: &Path) -> Option<SweepState> {
+    let text = match fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(e) => {
+            eprintln!("Warning: cannot read state file {}: {}; starting at 0", path.display(), e);
+            return None;
+        }
+    };
+    let state = SweepState::parse(&text);
+    if state.is_none() {
+        eprintln!("Warning: state file {} is corrupt; starting at 0", path.display());
+    }
+    state
+}
+
+/// SIGHUPで設定ファイルから読み直せる値
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct Timing {
+    delay: Duration,
+    // 複数のLEDを同時に点灯するとき、1つずつずらして点灯する間隔
+    stagger: Duration,
+}
+
+impl Timing {
+    fn from_args(args: &Args) -> Result<Self, ArgError> {
+        Ok(Timing {
+            delay: Duration::from_millis(args.value_or("--delay-ms", DEFAULT_DELAY_MS)?),
+            stagger: Duration::from_millis(args.value_or("--stagger-ms", 0)?),
+        })
+    }
+}
+
+/// 設定ファイルを読み直して新しいタイミングを返す。--configなしで起動した場合は`None`
+fn reload_timing(args: &mut Args) -> Result<Option<Timing>, Box<dyn Error>> {
+    if !args.reload()? {
+        return Ok(None);
+    }
+    Ok(Some(Timing::from_args(args)?))
+}
+
+const OPTIONS: &[Opt] = &[
+    Opt::Value("--delay-ms"),
+    Opt::Value("--stagger-ms"),
+    Opt::Value("--report-interval"),
+    Opt::Value("--state-file"),
+    Opt::Value("--sweeps"),
+    Opt::Flag("--mirror"),
+    Opt::Flag("--pause-key"),
+    Opt::Flag("--active-high"),
+    Opt::Value("--playlist"),
+    Opt::Value("--lock-file"),
+];
+
+fn main() {
+    error::run(try_main);
+}
+
+fn try_main() -> Result<(), Box<dyn Error>> {
+    let mut args = Args::from_env()?.with_version(env!("CARGO_PKG_VERSION")).with_options(OPTIONS);
+    let report_interval = Duration::from_secs(args.value_or("--report-interval", 0)?);
+    let mut timing = Timing::from_args(&args)?;
+    let state_file: Option<String> = args.value("--state-file")?;
+    // 0 = sweep until Ctrl+C
+    let sweeps: u64 = args.value_or("--sweeps", 0)?;
+    // 両端から内側へ向かって対称に点灯する
+    let mirror = args.flag("--mirror");
+    // Enterキーで一時停止/再開する
+    let pause_key = args.flag("--pause-key");
+    // LEDバーがHIGHで点灯する配線の場合に指定する (既定はLOWで点灯)
+    let polarity = LedPolarity::from_active_high(args.flag("--active-high"));
+    // 点灯パターンを指定した秒数ずつ順番に繰り返す (例: sweep:5,knightrider:10,blink-all:3)
+    let playlist_arg: Option<String> = args.value("--playlist")?;
+    let lock_file: Option<String> = args.value("--lock-file")?;
+    args.finish();
+    let playlist = playlist_arg.as_deref().map(parse_playlist).transpose()?;
+    if playlist.is_some() && (sweeps > 0 || state_file.is_some()) {
+        return Err("--playlist loops forever and cannot be combined with --sweeps or --state-file".into());
+    }
+    pins::warn_special_pins(&LED_PINS);
+    args.exit_if_dry_run();
+    let _lock = lock::acquire(lock_file.as_deref())?;
 
-fn main() -> Result<(), Box<dyn Error>> {
     // Init
-    println!("Program is starting...");
-    let gpio = Gpio::new()?;
-    const LED_PINS: [u8; 10] = [17, 18, 27, 22, 23, 24, 25, 2, 3, 8];
+    style::banner("Program is starting...");
+    let gpio = open_gpio()?;
     let mut leds: Vec<_> = Vec::with_capacity(LED_PINS.len());
     for &pin_num in LED_PINS.iter() {
-        let pin = gpio.get(pin_num)?.into_output();
+        let mut pin = gpio.get(pin_num)?.into_output();
+        pin.write(polarity.off_level());
         leds.push(pin);
     }
 
-    // Ctrl+Cが押されたら終了
+    // Ctrl+CやSIGTERMで終了し、SIGHUPでは--delay-msと--stagger-msを設定ファイルから読み直す
     let running = Arc::new(AtomicBool::new(true));
+    install_panic_hook(running.clone());
+    let reload = Arc::new(AtomicBool::new(false));
     let r = running.clone();
-    ctrlc::set_handler(move || {
-        r.store(false, Ordering::SeqCst);
-    })?;
+    shutdown::set_handler_with_reload(
+        move || {
+            r.store(false, Ordering::SeqCst);
+        },
+        reload.clone(),
+    )?;
+
+    // In --mirror mode the light only travels over the first half; the
+    // other half follows as its mirror image
+    let steps = if mirror { leds.len().div_ceil(2) } else { leds.len() };
+    let paused = Arc::new(AtomicBool::new(false));
+    if pause_key {
+        println!("{}", keyboard::PAUSE_HELP);
+        keyboard::spawn_pause_toggle(paused.clone());
+    }
+
+    let saved = state_file.as_deref().map(Path::new).and_then(load_state);
+    let mut state = SweepState::resume_or_reset(saved, steps);
+    if saved.is_some() {
+        println!("Resuming at LED {}", state.position);
+    }
+
+    // Without --playlist the sweep plays forever
+    let mut playlist = playlist.map(|entries| Playlist::new(entries, Instant::now()));
+    let mut pattern = playlist.as_mut().map_or(Pattern::Sweep, |p| p.current(Instant::now()));
+    if playlist.is_some() {
+        println!("Pattern: {}", pattern);
+    }
+    // Phase of the blink-all pattern
+    let mut all_on = true;
 
     // Main loop
+    let mut reporter = Reporter::new(report_interval, "sweeps", Instant::now());
+    let mut sweeps_left = sweeps;
     while running.load(Ordering::SeqCst) {
-        for i in 0..leds.len() {
-            if !running.load(Ordering::SeqCst) {
-                break;
+        if reload.swap(false, Ordering::SeqCst) {
+            match reload_timing(&mut args) {
+                Ok(Some(new)) => {
+                    timing = new;
+                    println!(
+                        "Config reloaded: delay {} ms, stagger {} ms",
+                        timing.delay.as_millis(),
+                        timing.stagger.as_millis()
+                    );
+                }
+                Ok(None) => eprintln!("SIGHUP ignored: started without --config"),
+                Err(e) => eprintln!("Warning: config reload failed, keeping the current settings: {}", e),
             }
-            leds[i].write(Level::Low);
-            sleep(Duration::from_millis(100));
-            leds[i].write(Level::High);
         }
-        for i in (0..leds.len()).rev() {
-            if !running.load(Ordering::SeqCst) {
-                break;
+        if let Some(playlist) = playlist.as_mut() {
+            let next = playlist.current(Instant::now());
+            if next != pattern {
+                println!("Pattern: {}", next);
+                pattern = next;
+                state = SweepState::START;
+                all_on = true;
             }
-            leds[i].write(Level::Low);
-            sleep(Duration::from_millis(100));
-            leds[i].write(Level::High);
         }
+        let lit: Vec<usize> = match pattern {
+            Pattern::Sweep => {
+                let (first, second) = if mirror {
+                    mirror_indices(state.position, leds.len())
+                } else {
+                    (state.position, None)
+                };
+                std::iter::once(first).chain(second).collect()
+            }
+            Pattern::KnightRider => knight_rider_indices(state.position, leds.len()),
+            Pattern::BlinkAll if all_on => (0..leds.len()).collect(),
+            Pattern::BlinkAll => Vec::new(),
+        };
+        let step_start = Instant::now();
+        for (&index, offset) in lit.iter().zip(stagger_schedule(lit.len(), timing.stagger)) {
+            sleep(offset.saturating_sub(step_start.elapsed()));
+            leds[index].write(polarity.on_level());
+        }
+        sleep(timing.delay.saturating_sub(step_start.elapsed()));
+        // Hold the current frame while paused
+        while paused.load(Ordering::SeqCst) && running.load(Ordering::SeqCst) {
+            sleep(PAUSE_POLL);
+        }
+        for &index in &lit {
+            leds[index].write(polarity.off_level());
+        }
+
+        let completed = match pattern {
+            Pattern::Sweep => state.advance(steps),
+            Pattern::KnightRider => state.advance(leds.len()),
+            Pattern::BlinkAll => {
+                all_on = !all_on;
+                false
+            }
+        };
+        if completed {
+            reporter.record();
+            if let Some(summary) = reporter.poll(Instant::now()) {
+                println!("{}", summary);
+            }
+            if sweeps > 0 {
+                sweeps_left -= 1;
+                if sweeps_left == 0 {
+                    println!("Finished {} sweeps", sweeps);
+                    break;
+                }
+            }
+        }
+    }
+
+    if let Some(path) = &state_file
+        && let Err(e) = fs::write(path, state.serialize())
+    {
+        eprintln!("Warning: cannot save state file {}: {}", path, e);
     }
+
     // Cleanup
     for led in leds.iter_mut() {
-        led.set_low();
+        led.write(polarity.off_level());
     }
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn secs(s: f64) -> Duration {
+        Duration::from_secs_f64(s)
+    }
+
+    #[test]
+    fn playlist_parses_patterns_and_seconds() {
+        let entries = parse_playlist("sweep:5, knightrider : 0.5,blink-all:3").unwrap();
+        assert_eq!(
+            entries,
+            [
+                PlaylistEntry { pattern: Pattern::Sweep, duration: secs(5.0) },
+                PlaylistEntry { pattern: Pattern::KnightRider, duration: secs(0.5) },
+                PlaylistEntry { pattern: Pattern::BlinkAll, duration: secs(3.0) },
+            ]
+        );
+    }
+
+    #[test]
+    fn playlist_rejects_bad_entries() {
+        for text in ["", "sweep", "strobe:5", "sweep:0", "sweep:-1", "sweep:inf", "sweep:5,"] {
+            assert!(parse_playlist(text).is_err(), "{:?}", text);
+        }
+    }
+
+    #[test]
+    fn pattern_names_round_trip() {
+        for pattern in [Pattern::Sweep, Pattern::KnightRider, Pattern::BlinkAll] {
+            assert_eq!(pattern.to_string().parse(), Ok(pattern));
+        }
+    }
+
+    #[test]
+    fn playlist_loops_back_to_back() {
+        let start = Instant::now();
+        let entries = parse_playlist("sweep:1,blink-all:2").unwrap();
+        let mut playlist = Playlist::new(entries, start);
+        assert_eq!(playlist.current(start), Pattern::Sweep);
+        assert_eq!(playlist.current(start + secs(1.0)), Pattern::BlinkAll);
+        assert_eq!(playlist.current(start + secs(2.9)), Pattern::BlinkAll);
+        // A late frame skips straight to the entry that should be playing
+        assert_eq!(playlist.current(start + secs(7.5)), Pattern::BlinkAll);
+        assert_eq!(playlist.current(start + secs(9.0)), Pattern::Sweep);
+    }
+
+    #[test]
+    fn sweep_bounces_and_lights_each_end_twice() {
+        let mut state = SweepState::START;
+        let mut positions = vec![state.position];
+        let mut completed = false;
+        while !completed {
+            completed = state.advance(3);
+            positions.push(state.position);
+        }
+        assert_eq!(positions, [0, 1, 2, 2, 1, 0, 0]);
+        assert_eq!(state, SweepState::START);
+    }
+
+    #[test]
+    fn sweep_state_round_trips_through_the_state_file() {
+        let state = SweepState { position: 4, forward: false };
+        assert_eq!(state.serialize(), "4 backward\n");
+        assert_eq!(SweepState::parse(&state.serialize()), Some(state));
+        for corrupt in ["", "4", "x forward", "4 sideways", "4 forward extra"] {
+            assert_eq!(SweepState::parse(corrupt), None, "{:?}", corrupt);
+        }
+    }
+
+    #[test]
+    fn saved_state_beyond_the_strip_is_reset() {
+        let saved = SweepState { position: 7, forward: true };
+        assert_eq!(SweepState::resume_or_reset(Some(saved), 10), saved);
+        assert_eq!(SweepState::resume_or_reset(Some(saved), 5), SweepState::START);
+        assert_eq!(SweepState::resume_or_reset(None, 10), SweepState::START);
+    }
+
+    #[test]
+    fn mirror_lights_both_ends() {
+        assert_eq!(mirror_indices(0, 10), (0, Some(9)));
+        assert_eq!(mirror_indices(4, 10), (4, Some(5)));
+        // The middle of an odd strip is its own mirror image
+        assert_eq!(mirror_indices(2, 5), (2, None));
+    }
+
+    #[test]
+    fn knight_rider_bar_is_cut_at_the_ends() {
+        assert_eq!(knight_rider_indices(0, 10), [0, 1]);
+        assert_eq!(knight_rider_indices(5, 10), [4, 5, 6]);
+        assert_eq!(knight_rider_indices(9, 10), [8, 9]);
+    }
+
+    #[test]
+    fn stagger_spaces_turn_ons() {
+        let ms = Duration::from_millis;
+        assert_eq!(stagger_schedule(3, ms(5)), [ms(0), ms(5), ms(10)]);
+        assert_eq!(stagger_schedule(2, Duration::ZERO), [Duration::ZERO; 2]);
+        assert!(stagger_schedule(0, ms(5)).is_empty());
+    }
+
+    #[test]
+    fn timing_defaults_and_overrides() {
+        let args = |list: &[&str]| {
+            let list = list.iter().map(|s| s.to_string()).collect();
+            Args::from_vec("LightWater".to_string(), list).unwrap().with_options(OPTIONS)
+        };
+        assert_eq!(
+            Timing::from_args(&args(&[])).unwrap(),
+            Timing { delay: Duration::from_millis(DEFAULT_DELAY_MS), stagger: Duration::ZERO }
+        );
+        let timing = Timing::from_args(&args(&["--delay-ms", "40", "--stagger-ms=2"])).unwrap();
+        assert_eq!(timing, Timing { delay: Duration::from_millis(40), stagger: Duration::from_millis(2) });
+        assert!(Timing::from_args(&args(&["--delay-ms", "fast"])).is_err());
+    }
+}
@@ -0,0 +1,133 @@
+mod morse;
+
+use common::args::{Args, Opt};
+use common::buzzer;
+use common::error;
+use common::lock;
+use common::shutdown::{self, install_panic_hook};
+use common::style;
+use kit::{Buzzer, Kit, Led};
+use std::error::Error;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+
+const DEFAULT_LED_PIN: u8 = 17;
+const DEFAULT_WPM: u32 = 15;
+
+/// Where the code is keyed: an LED, an active buzzer, or both.
+struct Key {
+    led: Option<Led>,
+    buzzer: Option<Buzzer>,
+}
+
+impl Key {
+    fn down(&mut self) {
+        if let Some(led) = &mut self.led {
+            led.on();
+        }
+        if let Some(buzzer) = &mut self.buzzer {
+            buzzer.on();
+        }
+    }
+
+    fn up(&mut self) {
+        if let Some(led) = &mut self.led {
+            led.off();
+        }
+        if let Some(buzzer) = &mut self.buzzer {
+            buzzer.off();
+        }
+    }
+}
+
+const OPTIONS: &[Opt] = &[
+    Opt::Value("--text"),
+    Opt::Value("--wpm"),
+    Opt::Value("--led-pin"),
+    Opt::Value("--buzzer-pin"),
+    Opt::Flag("--repeat"),
+    Opt::Value("--articulation"),
+    Opt::Value("--lock-file"),
+];
+
+fn main() {
+    error::run(try_main);
+}
+
+fn try_main() -> Result<(), Box<dyn Error>> {
+    let args = Args::from_env()?.with_version(env!("CARGO_PKG_VERSION")).with_options(OPTIONS);
+    let text = args.value_or("--text", "SOS".to_string())?;
+    let wpm: u32 = args.value_or("--wpm", DEFAULT_WPM)?;
+    let led_pin: Option<u8> = args.value("--led-pin")?;
+    let buzzer_pin: Option<u8> = args.value("--buzzer-pin")?;
+    let repeat = args.flag("--repeat");
+    // Share of each dot and dash left silent, so repeated elements stay distinct
+    let articulation: f64 = args.value_or("--articulation", 0.0)?;
+    let lock_file: Option<String> = args.value("--lock-file")?;
+    args.finish();
+    if wpm == 0 {
+        return Err("--wpm must be at least 1".into());
+    }
+    if !(0.0..=buzzer::MAX_ARTICULATION).contains(&articulation) {
+        return Err(format!(
+            "--articulation must be between 0 and {}, got {}",
+            buzzer::MAX_ARTICULATION,
+            articulation
+        )
+        .into());
+    }
+
+    let (signals, unknown) = morse::encode(&text);
+    for c in unknown {
+        eprintln!("Warning: no Morse code for '{}', skipped", c);
+    }
+    if signals.is_empty() {
+        return Err(format!("nothing to send in '{}'", text).into());
+    }
+
+    args.exit_if_dry_run();
+    let _lock = lock::acquire(lock_file.as_deref())?;
+
+    style::banner("Program is starting...");
+    // Without any pin option, blink the tutorial LED
+    let led_pin = if buzzer_pin.is_none() { led_pin.or(Some(DEFAULT_LED_PIN)) } else { led_pin };
+    let mut key = Key {
+        led: led_pin.map(Kit::led).transpose()?,
+        buzzer: buzzer_pin.map(Kit::buzzer).transpose()?,
+    };
+
+    let running = Arc::new(AtomicBool::new(true));
+    install_panic_hook(running.clone());
+    let r = running.clone();
+    shutdown::set_handler(move || {
+        r.store(false, Ordering::SeqCst);
+    })?;
+
+    let dot = morse::dot_duration(wpm);
+    let steps = morse::timeline(&signals, dot, articulation);
+    println!("Sending '{}' at {} WPM", text, wpm);
+    'send: loop {
+        for &(down, duration) in &steps {
+            if !running.load(Ordering::SeqCst) {
+                break 'send;
+            }
+            if down {
+                key.down();
+            } else {
+                key.up();
+            }
+            thread::sleep(duration);
+        }
+        key.up();
+        if !repeat {
+            break;
+        }
+        // Word gap before starting over
+        thread::sleep(dot * morse::WORD_GAP);
+    }
+
+    key.up();
+    println!("Done");
+    Ok(())
+}
@@ -0,0 +1,203 @@
+//! ASCII to Morse code and the standard timing model.
+//!
+//! All durations are counted in dot units: a dot is 1 unit on, a dash 3, the
+//! gap between elements of a letter 1 unit off, between letters 3 and
+//! between words 7.
+
+use std::time::Duration;
+
+use common::buzzer;
+
+/// One step of the output: keyed on or off for a number of dot units.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Signal {
+    On(u32),
+    Off(u32),
+}
+
+const DOT: u32 = 1;
+const DASH: u32 = 3;
+const ELEMENT_GAP: u32 = 1;
+const LETTER_GAP: u32 = 3;
+pub const WORD_GAP: u32 = 7;
+
+/// Dot and dash pattern for a character, case-insensitive.
+pub fn pattern(c: char) -> Option<&'static str> {
+    let code = match c.to_ascii_uppercase() {
+        'A' => ".-",
+        'B' => "-...",
+        'C' => "-.-.",
+        'D' => "-..",
+        'E' => ".",
+        'F' => "..-.",
+        'G' => "--.",
+        'H' => "....",
+        'I' => "..",
+        'J' => ".---",
+        'K' => "-.-",
+        'L' => ".-..",
+        'M' => "--",
+        'N' => "-.",
+        'O' => "---",
+        'P' => ".--.",
+        'Q' => "--.-",
+        'R' => ".-.",
+        'S' => "...",
+        'T' => "-",
+        'U' => "..-",
+        'V' => "...-",
+        'W' => ".--",
+        'X' => "-..-",
+        'Y' => "-.--",
+        'Z' => "--..",
+        '0' => "-----",
+        '1' => ".----",
+        '2' => "..---",
+        '3' => "...--",
+        '4' => "....-",
+        '5' => ".....",
+        '6' => "-....",
+        '7' => "--...",
+        '8' => "---..",
+        '9' => "----.",
+        '.' => ".-.-.-",
+        ',' => "--..--",
+        '?' => "..--..",
+        '/' => "-..-.",
+        '=' => "-...-",
+        _ => return None,
+    };
+    Some(code)
+}
+
+/// Length of one dot at `wpm` words per minute ("PARIS" timing: 50 units
+/// per word).
+pub fn dot_duration(wpm: u32) -> Duration {
+    Duration::from_millis(1200 / u64::from(wpm.max(1)))
+}
+
+/// Converts `text` into timed on/off signals.
+///
+/// Characters without a Morse code are skipped and returned in the second
+/// element so the caller can warn about them. Runs of whitespace count as a
+/// single word gap; the stream never starts or ends with a gap.
+pub fn encode(text: &str) -> (Vec<Signal>, Vec<char>) {
+    let mut signals = Vec::new();
+    let mut unknown = Vec::new();
+    // Gap owed before the next element, if anything has been keyed yet
+    let mut pending_gap = None;
+
+    for c in text.chars() {
+        if c.is_whitespace() {
+            if pending_gap.is_some() {
+                pending_gap = Some(WORD_GAP);
+            }
+            continue;
+        }
+        let Some(code) = pattern(c) else {
+            unknown.push(c);
+            continue;
+        };
+        for (i, element) in code.chars().enumerate() {
+            let gap = if i == 0 { pending_gap } else { Some(ELEMENT_GAP) };
+            if let Some(gap) = gap {
+                signals.push(Signal::Off(gap));
+            }
+            signals.push(Signal::On(if element == '.' { DOT } else { DASH }));
+        }
+        pending_gap = Some(LETTER_GAP);
+    }
+    (signals, unknown)
+}
+
+/// Turns `signals` into `(key_down, duration)` steps for a dot of `dot`.
+///
+/// `articulation` (see [`buzzer::split_note`]) silences the end of each dot
+/// and dash. The silence is part of the element, so a dot still takes
+/// exactly 1 unit and a dash 3, and the gaps after them are unchanged.
+pub fn timeline(signals: &[Signal], dot: Duration, articulation: f64) -> Vec<(bool, Duration)> {
+    let mut steps = Vec::new();
+    for &signal in signals {
+        match signal {
+            Signal::On(units) => {
+                let (sounding, silence) = buzzer::split_note(dot * units, articulation);
+                steps.push((true, sounding));
+                if !silence.is_zero() {
+                    steps.push((false, silence));
+                }
+            }
+            Signal::Off(units) => steps.push((false, dot * units)),
+        }
+    }
+    steps
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use Signal::{Off, On};
+
+    #[test]
+    fn letters_use_element_and_letter_gaps() {
+        let (signals, unknown) = encode("ET");
+        assert_eq!(signals, [On(DOT), Off(LETTER_GAP), On(DASH)]);
+        assert!(unknown.is_empty());
+        assert_eq!(encode("a").0, [On(DOT), Off(ELEMENT_GAP), On(DASH)]);
+    }
+
+    #[test]
+    fn whitespace_runs_are_one_word_gap_and_never_lead_or_trail() {
+        assert_eq!(encode("  e \t e  ").0, [On(DOT), Off(WORD_GAP), On(DOT)]);
+        assert!(encode("   ").0.is_empty());
+    }
+
+    #[test]
+    fn unknown_characters_are_reported_and_skipped() {
+        let (signals, unknown) = encode("e#é");
+        assert_eq!(signals, [On(DOT)]);
+        assert_eq!(unknown, ['#', 'é']);
+    }
+
+    #[test]
+    fn every_pattern_is_dots_and_dashes() {
+        for c in ('A'..='Z').chain('0'..='9') {
+            let code = pattern(c).unwrap();
+            assert!(code.chars().all(|e| e == '.' || e == '-'), "{}", c);
+        }
+        assert_eq!(pattern('s'), Some("..."));
+    }
+
+    #[test]
+    fn paris_timing() {
+        assert_eq!(dot_duration(20), Duration::from_millis(60));
+        assert_eq!(dot_duration(15), Duration::from_millis(80));
+        assert_eq!(dot_duration(0), Duration::from_millis(1200));
+    }
+
+    fn total(steps: &[(bool, Duration)]) -> Duration {
+        steps.iter().map(|&(_, duration)| duration).sum()
+    }
+
+    #[test]
+    fn articulation_keeps_element_lengths() {
+        let dot = dot_duration(20);
+        for articulation in [0.0, 0.3, 0.5] {
+            let dot_steps = timeline(&[On(DOT)], dot, articulation);
+            assert_eq!(total(&dot_steps), dot);
+            assert_eq!(dot_steps[0], (true, dot.mul_f64(1.0 - articulation)));
+            assert_eq!(total(&timeline(&[On(DASH)], dot, articulation)), dot * 3);
+            assert_eq!(timeline(&[Off(LETTER_GAP)], dot, articulation), [(false, dot * 3)]);
+        }
+    }
+
+    #[test]
+    fn paris_takes_fifty_units_with_the_word_gap() {
+        let dot = dot_duration(20);
+        let (signals, _) = encode("PARIS");
+        for articulation in [0.0, 0.5] {
+            let word = total(&timeline(&signals, dot, articulation)) + dot * WORD_GAP;
+            assert_eq!(word, dot * 50);
+        }
+    }
+}
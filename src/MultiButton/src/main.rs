@@ -0,0 +1,165 @@
+use common::args::{Args, Opt};
+use common::button::DEFAULT_DEBOUNCE;
+use common::error::{self, open_gpio};
+use common::led::{Led, LedPolarity};
+use common::lock;
+use common::shutdown::{self, install_panic_hook};
+use common::style;
+use rppal::gpio::{InputPin, Trigger};
+use std::error::Error;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+const LED_PIN: u8 = 17;
+// The TableLamp button plus two more on free pins
+const DEFAULT_BUTTONS: [u8; 3] = [18, 23, 24];
+const DEFAULT_ACTIONS: [Action; 3] = [Action::On, Action::Off, Action::Toggle];
+// How often the loop wakes up to check for Ctrl+C
+const POLL_TIMEOUT: Duration = Duration::from_millis(10);
+
+/// What pressing a button does to the LED.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Action {
+    On,
+    Off,
+    Toggle,
+}
+
+impl FromStr for Action {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "on" => Ok(Action::On),
+            "off" => Ok(Action::Off),
+            "toggle" => Ok(Action::Toggle),
+            _ => Err(format!("expected 'on', 'off' or 'toggle', got '{}'", s)),
+        }
+    }
+}
+
+impl Action {
+    /// The LED state after this action, given the current one.
+    fn apply(self, led_on: bool) -> bool {
+        match self {
+            Action::On => true,
+            Action::Off => false,
+            Action::Toggle => !led_on,
+        }
+    }
+}
+
+/// Pairs each button pin with its action, in the order given on the
+/// command line.
+fn dispatch_table(buttons: &[u8], actions: &[Action]) -> Result<Vec<(u8, Action)>, String> {
+    if buttons.len() != actions.len() {
+        return Err(format!(
+            "got {} buttons but {} actions; give one action per button",
+            buttons.len(),
+            actions.len()
+        ));
+    }
+    Ok(buttons.iter().copied().zip(actions.iter().copied()).collect())
+}
+
+/// The action bound to `pin`, if any.
+fn action_for(table: &[(u8, Action)], pin: u8) -> Option<Action> {
+    table.iter().find(|&&(p, _)| p == pin).map(|&(_, action)| action)
+}
+
+const OPTIONS: &[Opt] = &[
+    Opt::Value("--buttons"),
+    Opt::Value("--actions"),
+    Opt::Value("--led-pin"),
+    Opt::Value("--lock-file"),
+];
+
+fn main() {
+    error::run(try_main);
+}
+
+fn try_main() -> Result<(), Box<dyn Error>> {
+    let args = Args::from_env()?.with_version(env!("CARGO_PKG_VERSION")).with_options(OPTIONS);
+    let buttons: Vec<u8> = args.list("--buttons")?.unwrap_or(DEFAULT_BUTTONS.to_vec());
+    let actions: Vec<Action> = args.list("--actions")?.unwrap_or(DEFAULT_ACTIONS.to_vec());
+    let led_pin = args.value_or("--led-pin", LED_PIN)?;
+    let lock_file: Option<String> = args.value("--lock-file")?;
+    args.finish();
+    let table = dispatch_table(&buttons, &actions)?;
+    args.exit_if_dry_run();
+    let _lock = lock::acquire(lock_file.as_deref())?;
+
+    style::banner("Program is starting...");
+    let gpio = open_gpio()?;
+    let mut led = Led::new(gpio.get(led_pin)?.into_output_low(), LedPolarity::ActiveHigh);
+    let mut pins: Vec<InputPin> = Vec::with_capacity(table.len());
+    for &(pin, action) in &table {
+        let mut input = gpio.get(pin)?.into_input_pullup();
+        // Buttons pull the pin low, so the falling edge is the press
+        input.set_interrupt(Trigger::FallingEdge, Some(DEFAULT_DEBOUNCE))?;
+        println!("GPIO {} -> {:?}", pin, action);
+        pins.push(input);
+    }
+
+    // Ctrl+Cが押されたら終了
+    let running = Arc::new(AtomicBool::new(true));
+    install_panic_hook(running.clone());
+    let r = running.clone();
+    shutdown::set_handler(move || {
+        r.store(false, Ordering::SeqCst);
+    })?;
+
+    style::banner("Waiting for button presses...");
+    let pin_refs: Vec<&InputPin> = pins.iter().collect();
+    while running.load(Ordering::SeqCst) {
+        let Some((pin, _event)) = gpio.poll_interrupts(&pin_refs, false, Some(POLL_TIMEOUT))? else {
+            continue;
+        };
+        if let Some(action) = action_for(&table, pin.pin()) {
+            if action.apply(led.is_on()) {
+                led.on();
+            } else {
+                led.off();
+            }
+            println!("GPIO {}: {:?}, LED {}", pin.pin(), action, if led.is_on() { "on" } else { "off" });
+        }
+    }
+
+    style::banner("Program is finished.");
+    for pin in pins.iter_mut() {
+        let _ = pin.clear_interrupt();
+    }
+    led.off();
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn actions_set_or_flip_the_led() {
+        for led_on in [false, true] {
+            assert!(Action::On.apply(led_on));
+            assert!(!Action::Off.apply(led_on));
+            assert_eq!(Action::Toggle.apply(led_on), !led_on);
+        }
+        assert_eq!("toggle".parse(), Ok(Action::Toggle));
+        assert!("blink".parse::<Action>().is_err());
+    }
+
+    #[test]
+    fn dispatch_pairs_buttons_with_actions() {
+        let table = dispatch_table(&[18, 23, 24], &DEFAULT_ACTIONS).unwrap();
+        assert_eq!(action_for(&table, 23), Some(Action::Off));
+        assert_eq!(action_for(&table, 24), Some(Action::Toggle));
+        assert_eq!(action_for(&table, 17), None);
+    }
+
+    #[test]
+    fn dispatch_needs_one_action_per_button() {
+        assert!(dispatch_table(&[18, 23], &DEFAULT_ACTIONS).is_err());
+    }
+}
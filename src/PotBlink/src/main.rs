@@ -0,0 +1,110 @@
+use adc::Adc;
+use common::args::{Args, Opt};
+use common::blinker::TimedBlinker;
+use common::error::{self, AppError, open_gpio};
+use common::led::{Led, LedPolarity};
+use common::lock;
+use common::shutdown::{self, install_panic_hook};
+use common::style;
+use common::throttle::Throttle;
+use std::error::Error;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+// Same LED as the Blink lesson
+const LED_PIN: u8 = 17;
+const DEFAULT_CHANNEL: u8 = 0;
+// Blink rate range covered by the knob
+const MIN_HZ: f64 = 0.5;
+const MAX_HZ: f64 = 10.0;
+// Short enough that the fastest blink still toggles on time
+const LOOP_DELAY: Duration = Duration::from_millis(10);
+const LOG_EVERY: Duration = Duration::from_millis(500);
+
+/// Maps an 8-bit ADC reading linearly to a blink rate in `[MIN_HZ, MAX_HZ]`.
+fn raw_to_hz(raw: u8) -> f64 {
+    MIN_HZ + raw as f64 / 255.0 * (MAX_HZ - MIN_HZ)
+}
+
+/// How long the LED stays on, and then off, at `hz` blinks per second.
+fn half_period(hz: f64) -> Duration {
+    Duration::from_secs_f64(0.5 / hz)
+}
+
+const OPTIONS: &[Opt] = &[
+    Opt::Value("--channel"),
+    Opt::Value("--lock-file"),
+];
+
+fn main() {
+    error::run(try_main);
+}
+
+fn try_main() -> Result<(), Box<dyn Error>> {
+    let args = Args::from_env()?.with_version(env!("CARGO_PKG_VERSION")).with_options(OPTIONS);
+    let channel = args.value_or("--channel", DEFAULT_CHANNEL)?;
+    let lock_file: Option<String> = args.value("--lock-file")?;
+    args.finish();
+    args.exit_if_dry_run();
+    let _lock = lock::acquire(lock_file.as_deref())?;
+
+    style::banner("Program is starting...");
+    let mut adc = Adc::detect(&adc::DEFAULT_BUSES, adc::DEFAULT_RETRIES)
+        .map_err(|e| AppError::AdcNotFound(e.to_string()))?;
+    adc::check_channel(adc.chip(), channel)?;
+    println!("Detected I2C device: {} on bus {}", adc.chip().name(), adc.bus());
+
+    let gpio = open_gpio()?;
+    let led = Led::new(gpio.get(LED_PIN)?.into_output_low(), LedPolarity::ActiveHigh);
+
+    let running = Arc::new(AtomicBool::new(true));
+    install_panic_hook(running.clone());
+    let r = running.clone();
+    shutdown::set_handler(move || {
+        style::banner("\nEnding program");
+        r.store(false, Ordering::SeqCst);
+    })?;
+
+    // The blinker is ticked from this loop, so the knob keeps being read
+    // while the LED blinks
+    let mut blinker = TimedBlinker::new(led, half_period(MIN_HZ), Instant::now());
+    let mut log_throttle = Throttle::new(LOG_EVERY);
+    while running.load(Ordering::SeqCst) {
+        match adc.read(channel) {
+            Ok(raw) => {
+                let hz = raw_to_hz(raw);
+                blinker.set_interval(half_period(hz));
+                if log_throttle.should_emit(Instant::now()) {
+                    println!("ADC Value : {}, Blink rate : {:.1} Hz", raw, hz);
+                }
+            }
+            Err(e) => eprintln!("Error reading ADC: {}", e),
+        }
+        blinker.tick(Instant::now());
+        thread::sleep(LOOP_DELAY);
+    }
+
+    blinker.into_led().off();
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn knob_covers_the_blink_range() {
+        assert_eq!(raw_to_hz(0), MIN_HZ);
+        assert_eq!(raw_to_hz(255), MAX_HZ);
+    }
+
+    #[test]
+    fn half_period_splits_each_blink() {
+        assert_eq!(half_period(1.0), Duration::from_millis(500));
+        assert_eq!(half_period(MAX_HZ), Duration::from_millis(50));
+        // The fastest blink still toggles no faster than the loop runs
+        assert!(half_period(MAX_HZ) >= LOOP_DELAY);
+    }
+}
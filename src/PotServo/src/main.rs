@@ -0,0 +1,127 @@
+use std::error::Error;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+use adc::Adc;
+use common::args::{Args, Opt};
+use common::error::{self, AppError, open_gpio};
+use common::lock;
+use common::shutdown::{self, install_panic_hook};
+use common::status;
+use common::style;
+use common::worker;
+
+const SERVO_PIN: u8 = 18;
+const DEFAULT_ADC_CHANNEL: u8 = 0;
+// 50 Hz servo frame
+const SERVO_PERIOD: Duration = Duration::from_millis(20);
+// Pulse widths for 0 and 180 degrees; never drive outside this range
+const MIN_PULSE: Duration = Duration::from_micros(1000);
+const MAX_PULSE: Duration = Duration::from_micros(2000);
+const MAX_ANGLE: f64 = 180.0;
+const LOOP_DELAY_MS: u64 = 100;
+
+/// Maps an 8-bit ADC reading to an angle in `[0, 180]` degrees.
+fn raw_to_angle(raw: u8) -> f64 {
+    raw as f64 / 255.0 * MAX_ANGLE
+}
+
+/// Maps an angle to the servo pulse width, clamped to the safe range.
+fn angle_to_pulse(angle: f64) -> Duration {
+    let fraction = (angle / MAX_ANGLE).clamp(0.0, 1.0);
+    MIN_PULSE + (MAX_PULSE - MIN_PULSE).mul_f64(fraction)
+}
+
+const OPTIONS: &[Opt] = &[
+    Opt::Value("--channel"),
+    Opt::Value("--heartbeat-pin"),
+    Opt::Value("--lock-file"),
+];
+
+fn main() {
+    error::run(try_main);
+}
+
+fn try_main() -> Result<(), Box<dyn Error>> {
+    let args = Args::from_env()?.with_version(env!("CARGO_PKG_VERSION")).with_options(OPTIONS);
+    let channel = args.value_or("--channel", DEFAULT_ADC_CHANNEL)?;
+    let heartbeat_pin: Option<u8> = args.value("--heartbeat-pin")?;
+    let lock_file: Option<String> = args.value("--lock-file")?;
+    args.finish();
+    args.exit_if_dry_run();
+    let _lock = lock::acquire(lock_file.as_deref())?;
+
+    style::banner("Program is starting ...");
+
+    let mut adc = Adc::detect(&adc::DEFAULT_BUSES, adc::DEFAULT_RETRIES)
+        .map_err(|e| AppError::AdcNotFound(e.to_string()))?;
+    println!("Detected I2C device: {}", adc.chip().name());
+    adc::check_channel(adc.chip(), channel)?;
+
+    let gpio = open_gpio()?;
+    let mut servo = gpio.get(SERVO_PIN)?.into_output_low();
+
+    let running = Arc::new(AtomicBool::new(true));
+    install_panic_hook(running.clone());
+
+    // Optional 1 Hz "still alive" LED for headless runs
+    let status_handle = heartbeat_pin
+        .map(|pin| status::spawn_status_led(pin, running.clone()))
+        .transpose()?;
+    let r = running.clone();
+    shutdown::set_handler(move || {
+        style::banner("\nEnding program");
+        r.store(false, Ordering::SeqCst);
+    })?;
+
+    while running.load(Ordering::SeqCst) {
+        match adc.read(channel) {
+            Ok(raw) => {
+                let angle = raw_to_angle(raw);
+                let pulse = angle_to_pulse(angle);
+                servo.set_pwm(SERVO_PERIOD, pulse)?;
+                println!(
+                    "ADC Value : {}, Angle : {:.1}, Pulse : {} us",
+                    raw,
+                    angle,
+                    pulse.as_micros()
+                );
+            }
+            Err(e) => eprintln!("Error reading I2C: {}", e),
+        }
+        thread::sleep(Duration::from_millis(LOOP_DELAY_MS));
+    }
+
+    servo.clear_pwm()?;
+    servo.set_low();
+    if let Some(handle) = status_handle {
+        worker::join_timeout("status LED", handle, worker::SHUTDOWN_TIMEOUT)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn knob_covers_the_full_sweep() {
+        assert_eq!(raw_to_angle(0), 0.0);
+        assert_eq!(raw_to_angle(255), MAX_ANGLE);
+    }
+
+    #[test]
+    fn pulse_tracks_the_angle() {
+        assert_eq!(angle_to_pulse(0.0), MIN_PULSE);
+        assert_eq!(angle_to_pulse(90.0), Duration::from_micros(1500));
+        assert_eq!(angle_to_pulse(MAX_ANGLE), MAX_PULSE);
+    }
+
+    #[test]
+    fn pulse_never_leaves_the_safe_range() {
+        assert_eq!(angle_to_pulse(-30.0), MIN_PULSE);
+        assert_eq!(angle_to_pulse(270.0), MAX_PULSE);
+    }
+}
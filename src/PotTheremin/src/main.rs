@@ -0,0 +1,187 @@
+use std::error::Error;
+use std::fmt;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use adc::Adc;
+use common::args::{Args, Opt};
+use common::buzzer::{self, Buzzer};
+use common::error::{self, AppError, open_gpio};
+use common::lock;
+use common::shutdown::{self, install_panic_hook};
+use common::smoothing::Ema;
+use common::style;
+use common::throttle::Throttle;
+
+// Passive buzzer, same pin as the Alertor lesson
+const BUZZER_PIN: u8 = 17;
+const DEFAULT_ADC_CHANNEL: u8 = 0;
+const MIN_FREQUENCY: f64 = 200.0;
+const MAX_FREQUENCY: f64 = 2000.0;
+// Weight of each new reading; lower is smoother but slower to follow the knob
+const SMOOTHING_ALPHA: f64 = 0.2;
+const LOOP_DELAY: Duration = Duration::from_millis(10);
+const LOG_EVERY: Duration = Duration::from_millis(500);
+
+/// How knob position maps to pitch, selected with `--scale`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Scale {
+    /// Equal steps in Hz.
+    Linear,
+    /// Equal steps in musical interval, which sounds even to the ear.
+    Log,
+}
+
+impl FromStr for Scale {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "linear" => Ok(Scale::Linear),
+            "log" => Ok(Scale::Log),
+            _ => Err(format!("expected 'linear' or 'log', got '{}'", s)),
+        }
+    }
+}
+
+impl fmt::Display for Scale {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Scale::Linear => "linear",
+            Scale::Log => "log",
+        })
+    }
+}
+
+/// Maps an 8-bit ADC reading to a frequency in `[MIN_FREQUENCY, MAX_FREQUENCY]`.
+fn raw_to_frequency(raw: u8, scale: Scale) -> f64 {
+    let t = raw as f64 / 255.0;
+    match scale {
+        Scale::Linear => MIN_FREQUENCY + t * (MAX_FREQUENCY - MIN_FREQUENCY),
+        Scale::Log => MIN_FREQUENCY * (MAX_FREQUENCY / MIN_FREQUENCY).powf(t),
+    }
+}
+
+const OPTIONS: &[Opt] = &[
+    Opt::Value("--channel"),
+    Opt::Value("--scale"),
+    Opt::Value("--transpose"),
+    Opt::Value("--lock-file"),
+];
+
+fn main() {
+    error::run(try_main);
+}
+
+fn try_main() -> Result<(), Box<dyn Error>> {
+    let args = Args::from_env()?.with_version(env!("CARGO_PKG_VERSION")).with_options(OPTIONS);
+    let channel = args.value_or("--channel", DEFAULT_ADC_CHANNEL)?;
+    let scale: Scale = args.value_or("--scale", Scale::Log)?;
+    // Semitones to shift the whole range by; negative goes down
+    let transpose: i32 = args.value_or("--transpose", 0)?;
+    let lock_file: Option<String> = args.value("--lock-file")?;
+    args.finish();
+    if transpose.abs() > buzzer::MAX_TRANSPOSE {
+        return Err(format!(
+            "--transpose must be within +/-{} semitones, got {}",
+            buzzer::MAX_TRANSPOSE,
+            transpose
+        )
+        .into());
+    }
+    let (low, low_clamped) = buzzer::transpose_clamped(MIN_FREQUENCY, transpose);
+    let (high, high_clamped) = buzzer::transpose_clamped(MAX_FREQUENCY, transpose);
+    if low_clamped || high_clamped {
+        eprintln!(
+            "Warning: --transpose {} leaves the buzzer's {:.0}-{:.0} Hz range, pitches are clamped to it",
+            transpose,
+            buzzer::MIN_TONE_HZ,
+            buzzer::MAX_TONE_HZ
+        );
+    }
+    args.exit_if_dry_run();
+    let _lock = lock::acquire(lock_file.as_deref())?;
+
+    style::banner("Program is starting ...");
+    if transpose != 0 {
+        style::banner(&format!(
+            "Range transposed by {} semitones: {:.0}-{:.0} Hz",
+            transpose, low, high
+        ));
+    }
+
+    let mut adc = Adc::detect(&adc::DEFAULT_BUSES, adc::DEFAULT_RETRIES)
+        .map_err(|e| AppError::AdcNotFound(e.to_string()))?;
+    println!("Detected I2C device: {}", adc.chip().name());
+    adc::check_channel(adc.chip(), channel)?;
+
+    let mut buzzer = Buzzer::new(open_gpio()?.get(BUZZER_PIN)?.into_output_low());
+
+    let running = Arc::new(AtomicBool::new(true));
+    install_panic_hook(running.clone());
+    let r = running.clone();
+    shutdown::set_handler(move || {
+        style::banner("\nEnding program");
+        r.store(false, Ordering::SeqCst);
+    })?;
+
+    let mut frequency = Ema::new(SMOOTHING_ALPHA);
+    let mut log_throttle = Throttle::new(LOG_EVERY);
+    while running.load(Ordering::SeqCst) {
+        match adc.read(channel) {
+            Ok(raw) => {
+                let (target, _) = buzzer::transpose_clamped(raw_to_frequency(raw, scale), transpose);
+                let hz = frequency.update(target);
+                buzzer.tone(hz)?;
+                if log_throttle.should_emit(Instant::now()) {
+                    println!("ADC Value : {}, Frequency : {:.0} Hz", raw, hz);
+                }
+            }
+            Err(e) => eprintln!("Error reading I2C: {}", e),
+        }
+        thread::sleep(LOOP_DELAY);
+    }
+
+    buzzer.silence()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn knob_ends_map_to_the_frequency_range() {
+        for scale in [Scale::Linear, Scale::Log] {
+            assert!((raw_to_frequency(0, scale) - MIN_FREQUENCY).abs() < 1e-9);
+            assert!((raw_to_frequency(255, scale) - MAX_FREQUENCY).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn log_scale_is_geometric() {
+        // Halfway up the knob is the geometric mean of the range
+        let expected = (MIN_FREQUENCY * MAX_FREQUENCY).sqrt();
+        let actual = raw_to_frequency(128, Scale::Log);
+        assert!((actual / expected).ln().abs() < 0.01);
+        assert!(actual < raw_to_frequency(128, Scale::Linear));
+    }
+
+    #[test]
+    fn frequency_rises_with_the_knob() {
+        for scale in [Scale::Linear, Scale::Log] {
+            assert!((0..255).all(|raw| raw_to_frequency(raw, scale) < raw_to_frequency(raw + 1, scale)));
+        }
+    }
+
+    #[test]
+    fn scale_names_round_trip() {
+        for scale in [Scale::Linear, Scale::Log] {
+            assert_eq!(scale.to_string().parse(), Ok(scale));
+        }
+        assert!("exp".parse::<Scale>().is_err());
+    }
+}
@@ -1,112 +1,474 @@
 use std::error::Error;
 use std::sync::{Arc, Mutex};
-use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicU16, AtomicU8, Ordering};
 use std::thread::{self, JoinHandle};
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
-use rppal::gpio::{Gpio, OutputPin};
-use rand::Rng;
+use common::args::{Args, Opt};
+use common::color::{Gamma, RgbCommon, hsv_to_rgb};
+use common::error::{self, AppError};
+use common::fade;
+use common::heartbeat::Heartbeat;
+use common::keyboard::{self, KeyAction};
+use common::lock;
+use common::pwm::{self, PwmResolution};
+use common::shutdown::{self, install_panic_hook};
+use common::style;
+use common::worker;
+use rppal::gpio::Gpio;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
 
 // Pythonスクリプトに合わせたGPIOピン設定 (BCM番号)
 const RED_PIN: u8 = 17;
 const GREEN_PIN: u8 = 18;
 const BLUE_PIN: u8 = 27;
 
+// --keyboardモードでのキー1回あたりの色相(度)と明るさ(%)の変化量
+const KEY_HUE_STEP: u16 = 30;
+const KEY_VALUE_STEP: u8 = 10;
+
+// デューティ比の段階数の既定値 (10ms周期では100段階程度が実用上の上限)
+const DEFAULT_PWM_STEPS: u32 = 100;
+
+// ソフトウェアPWMの周期 (100Hz相当)
+const PWM_PERIOD: Duration = Duration::from_micros(10000);
+
+// この時間PWMスレッドのハートビートが途絶えたら停止したとみなす
+const PWM_STALL_THRESHOLD: Duration = Duration::from_secs(2);
+
+// --smoothモードの更新周期
+const SMOOTH_TICK: Duration = Duration::from_millis(20);
+
+// 色を変える間隔の既定値と下限 (--interval-ms)
+// --smoothモードでは1つの目標色へ移るまでの時間になるため、
+// 少なくとも数ステップ分のフェードが入るよう下限を設ける
+const DEFAULT_INTERVAL_MS: u64 = 1000;
+const MIN_INTERVAL_MS: u64 = 100;
+
+/// PWMスレッドと共有する3色のデューティ比
+///
+/// 各色にそれぞれのガンマ補正をかけてから書き込み、フェードアウトで使う
+/// ために補正前の色も覚えておく。
+struct SharedColor {
+    duties: [Arc<Mutex<f64>>; 3],
+    gamma: Gamma,
+    color: (f64, f64, f64),
+}
+
+impl SharedColor {
+    fn new(gamma: Gamma) -> Self {
+        Self {
+            duties: std::array::from_fn(|_| Arc::new(Mutex::new(0.0))),
+            gamma,
+            color: (0.0, 0.0, 0.0),
+        }
+    }
+
+    fn set(&mut self, color: (f64, f64, f64)) {
+        self.color = color;
+        let (r, g, b) = self.gamma.apply(color);
+        *self.duties[0].lock().unwrap() = r;
+        *self.duties[1].lock().unwrap() = g;
+        *self.duties[2].lock().unwrap() = b;
+    }
+
+    /// 補正前の現在の色
+    fn color(&self) -> (f64, f64, f64) {
+        self.color
+    }
+}
+
+/// --smoothモードの状態
+///
+/// 現在の色から次のランダムな目標色へ線形補間し、目標に到達したら
+/// 新しい目標色を選ぶ。乱数生成器を引数で受け取るため、シード付きの
+/// 乱数で結果を再現できる。
+struct SmoothColor {
+    from: [f64; 3],
+    to: [f64; 3],
+    step: u32,
+    steps: u32,
+}
+
+impl SmoothColor {
+    fn new(start: [f64; 3], steps: u32) -> Self {
+        Self {
+            from: start,
+            to: start,
+            step: steps,
+            steps: steps.max(1),
+        }
+    }
+
+    /// 1ステップ進めて現在の色 (各0.0〜1.0) を返す
+    fn step(&mut self, rng: &mut impl Rng) -> [f64; 3] {
+        if self.step >= self.steps {
+            self.from = self.to;
+            self.to = [
+                rng.random_range(0..=100) as f64 / 100.0,
+                rng.random_range(0..=100) as f64 / 100.0,
+                rng.random_range(0..=100) as f64 / 100.0,
+            ];
+            self.step = 0;
+        }
+        self.step += 1;
+        let t = self.step as f64 / self.steps as f64;
+        [
+            fade::lerp(self.from[0], self.to[0], t),
+            fade::lerp(self.from[1], self.to[1], t),
+            fade::lerp(self.from[2], self.to[2], t),
+        ]
+    }
+
+    fn target(&self) -> [f64; 3] {
+        self.to
+    }
+}
+
+/// ランダムな色 (各0〜100) を1つ生成する
+///
+/// 乱数生成器を引数で受け取るため、--seedを指定すれば同じ色の並びを再現できる
+fn random_color(rng: &mut impl Rng) -> [u8; 3] {
+    [
+        rng.random_range(0..=100),
+        rng.random_range(0..=100),
+        rng.random_range(0..=100),
+    ]
+}
+
 /// ソフトウェアPWMを管理するスレッドを起動する関数
 ///
+/// ピンの初期化はスレッド内で行い、その結果をチャネルで呼び出し元に返す
+///
 /// # Arguments
 /// * `pin_num` - 制御するGPIOピン番号
 /// * `duty_cycle` - 共有されるデューティサイクル (0.0から1.0)
 /// * `running` - プログラムの実行状態を管理するフラグ
+/// * `heartbeat` - ループごとに更新する生存確認用のハートビート
+/// * `resolution` - 出力するデューティ比の段階数
+/// * `common` - RGB LEDの共通端子 (アノード/カソード) で決まる点灯レベル
+/// * `first_cycle` - 最初の周期の開始時刻。以降の周期はここから`PWM_PERIOD`
+///   ごとに始まるため、スレッド間の位相関係が保たれる
 ///
 /// # Returns
-/// * `Result<JoinHandle<()>, Box<dyn Error>>` - スレッドのJoinHandle
+/// * `Result<JoinHandle<()>, AppError>` - スレッドのJoinHandle、初期化に失敗した場合はそのエラー
 fn run_pwm_thread(
     pin_num: u8,
     duty_cycle: Arc<Mutex<f64>>,
     running: Arc<AtomicBool>,
-) -> Result<JoinHandle<()>, Box<dyn Error>> {
-    let gpio = Gpio::new()?;
-    let mut pin = gpio.get(pin_num)?.into_output();
+    heartbeat: Arc<Heartbeat>,
+    resolution: PwmResolution,
+    common: RgbCommon,
+    first_cycle: Instant,
+) -> Result<JoinHandle<()>, AppError> {
+    let init = move || {
+        let mut pin = Gpio::new()?.get(pin_num)?.into_output();
+        pin.write(common.off_level());
+        Ok(pin)
+    };
 
-    let handle = thread::spawn(move || {
-        // 100Hz相当の周期 (10,000マイクロ秒)
-        let period = Duration::from_micros(10000); 
-        
+    worker::spawn_initialized("PWM", init, move |mut pin| {
+        let mut cycle_start = first_cycle;
         while running.load(Ordering::SeqCst) {
-            let current_duty_cycle = *duty_cycle.lock().unwrap();
+            heartbeat.beat();
+            let current_duty_cycle = resolution.quantize(*duty_cycle.lock().unwrap());
 
-            // デューティサイクルに基づいてオン/オフ時間を計算
-            // Common-Anode LEDの場合、LOWで点灯、HIGHで消灯
-            let on_time = period.mul_f64(current_duty_cycle);
-            let off_time = period.saturating_sub(on_time);
+            // デューティサイクルに基づいてオン時間を計算
+            // Common-Anode LEDの場合、LOWで点灯、HIGHで消灯 (Cathodeはその逆)
+            let on_time = PWM_PERIOD.mul_f64(current_duty_cycle);
 
-            if !on_time.is_zero() {
-                pin.set_low(); // 点灯
-                thread::sleep(on_time);
-            }
-            if !off_time.is_zero() {
-                pin.set_high(); // 消灯
-                thread::sleep(off_time);
-            }
+            // 点灯・消灯の区間ごとにスリープする。大きく遅れた場合は位相を保ったまま追いつく
+            cycle_start = pwm::output_period(
+                &mut pin,
+                cycle_start,
+                PWM_PERIOD,
+                on_time,
+                common.on_level(),
+                common.off_level(),
+            );
         }
         // 終了時にピンをリセット
-        pin.set_high(); 
-    });
+        pin.write(common.off_level());
+    })
+}
+
+const OPTIONS: &[Opt] = &[
+    Opt::Value("--fade-out-ms"),
+    Opt::Flag("--keyboard"),
+    Opt::Flag("--smooth"),
+    Opt::Value("--seed"),
+    Opt::Value("--pwm-steps"),
+    Opt::Value("--interval-ms"),
+    Opt::Value("--rgb-common"),
+    Opt::Flag("--anti-phase"),
+    Opt::Value("--gamma"),
+    Opt::Value("--gamma-r"),
+    Opt::Value("--gamma-g"),
+    Opt::Value("--gamma-b"),
+    Opt::Value("--lock-file"),
+];
 
-    Ok(handle)
+fn main() {
+    error::run(try_main);
 }
 
-fn main() -> Result<(), Box<dyn Error>> {
-    println!("Program is starting...");
+fn try_main() -> Result<(), Box<dyn Error>> {
+    let args = Args::from_env()?.with_version(env!("CARGO_PKG_VERSION")).with_options(OPTIONS);
+    let fade_out = Duration::from_millis(args.value_or("--fade-out-ms", 0)?);
+    let keyboard_mode = args.flag("--keyboard");
+    let smooth_mode = args.flag("--smooth");
+    let seed: Option<u64> = args.value("--seed")?;
+    let resolution = PwmResolution::new(args.value_or("--pwm-steps", DEFAULT_PWM_STEPS)?);
+    let interval_ms = args.value_or("--interval-ms", DEFAULT_INTERVAL_MS)?;
+    let rgb_common: RgbCommon = args.value_or("--rgb-common", RgbCommon::default())?;
+    // 3色のPWM周期の位相をずらし、同時に点灯する瞬間をなくして電源の電流の山を抑える
+    let anti_phase = args.flag("--anti-phase");
+    // ガンマ補正の指数。色ごとの指定がなければ--gammaを使う (既定は1.0で補正なし)
+    let gamma_all: f64 = args.value_or("--gamma", 1.0)?;
+    let gamma = Gamma {
+        red: args.value_or("--gamma-r", gamma_all)?,
+        green: args.value_or("--gamma-g", gamma_all)?,
+        blue: args.value_or("--gamma-b", gamma_all)?,
+    };
+    let lock_file: Option<String> = args.value("--lock-file")?;
+    args.finish();
+    if interval_ms < MIN_INTERVAL_MS {
+        return Err(format!("--interval-ms must be at least {}, got {}", MIN_INTERVAL_MS, interval_ms).into());
+    }
+    let interval = Duration::from_millis(interval_ms);
+    let exponents = [
+        ("--gamma", gamma_all),
+        ("--gamma-r", gamma.red),
+        ("--gamma-g", gamma.green),
+        ("--gamma-b", gamma.blue),
+    ];
+    for (name, exponent) in exponents {
+        if !(exponent.is_finite() && exponent > 0.0) {
+            return Err(format!("{} must be a positive number, got {}", name, exponent).into());
+        }
+    }
+    args.exit_if_dry_run();
+    let _lock = lock::acquire(lock_file.as_deref())?;
+
+    style::banner("Program is starting...");
 
     // Ctrl+Cでプログラムを終了するための設定
+    // PWMスレッドはフェードアウト中も動き続けるよう別のフラグで停止する
     let running = Arc::new(AtomicBool::new(true));
+    install_panic_hook(running.clone());
+    let pwm_running = Arc::new(AtomicBool::new(true));
     let r = running.clone();
-    ctrlc::set_handler(move || {
+    shutdown::set_handler(move || {
         r.store(false, Ordering::SeqCst);
     })?;
 
     // 各色のデューティサイクルをスレッド間で共有するための変数
     // 初期値は0.0（消灯）に設定
-    let r_duty = Arc::new(Mutex::new(0.0));
-    let g_duty = Arc::new(Mutex::new(0.0));
-    let b_duty = Arc::new(Mutex::new(0.0));
+    let mut shared = SharedColor::new(gamma);
 
     // 各色を制御するPWMスレッドを起動
-    let mut threads: Vec<JoinHandle<()>> = Vec::new();
-    threads.push(run_pwm_thread(RED_PIN, r_duty.clone(), running.clone())?);
-    threads.push(run_pwm_thread(GREEN_PIN, g_duty.clone(), running.clone())?);
-    threads.push(run_pwm_thread(BLUE_PIN, b_duty.clone(), running.clone())?);
+    // 全スレッド共通の基準時刻から、--anti-phaseなら各色の位相分ずらして開始する
+    let heartbeats: [Arc<Heartbeat>; 3] = std::array::from_fn(|_| Arc::new(Heartbeat::new()));
+    let epoch = Instant::now();
+    let first_cycle = |index: usize| {
+        if anti_phase { epoch + pwm::phase_offset(index, 3, PWM_PERIOD) } else { epoch }
+    };
+    let threads: Vec<JoinHandle<()>> = vec![
+        run_pwm_thread(
+            RED_PIN,
+            shared.duties[0].clone(),
+            pwm_running.clone(),
+            heartbeats[0].clone(),
+            resolution,
+            rgb_common,
+            first_cycle(0),
+        )?,
+        run_pwm_thread(
+            GREEN_PIN,
+            shared.duties[1].clone(),
+            pwm_running.clone(),
+            heartbeats[1].clone(),
+            resolution,
+            rgb_common,
+            first_cycle(1),
+        )?,
+        run_pwm_thread(
+            BLUE_PIN,
+            shared.duties[2].clone(),
+            pwm_running.clone(),
+            heartbeats[2].clone(),
+            resolution,
+            rgb_common,
+            first_cycle(2),
+        )?,
+    ];
+
+    // --keyboardモード: 左右キーで色相、上下キーで明るさを変更する
+    let key_hue = Arc::new(AtomicU16::new(0));
+    let key_value = Arc::new(AtomicU8::new(100));
+    let _keys = if keyboard_mode {
+        println!("{}", keyboard::KEY_HELP);
+        let key_hue = key_hue.clone();
+        let key_value = key_value.clone();
+        Some(keyboard::spawn_key_reader(move |action| match action {
+            KeyAction::Next => {
+                let _ = key_hue.fetch_update(Ordering::SeqCst, Ordering::SeqCst, |h| {
+                    Some((h + KEY_HUE_STEP) % 360)
+                });
+            }
+            KeyAction::Previous => {
+                let _ = key_hue.fetch_update(Ordering::SeqCst, Ordering::SeqCst, |h| {
+                    Some((h + 360 - KEY_HUE_STEP) % 360)
+                });
+            }
+            KeyAction::Increase => {
+                let _ = key_value.fetch_update(Ordering::SeqCst, Ordering::SeqCst, |v| {
+                    Some((v + KEY_VALUE_STEP).min(100))
+                });
+            }
+            KeyAction::Decrease => {
+                let _ = key_value.fetch_update(Ordering::SeqCst, Ordering::SeqCst, |v| {
+                    Some(v.saturating_sub(KEY_VALUE_STEP))
+                });
+            }
+        }))
+    } else {
+        None
+    };
 
-    let mut rng = rand::thread_rng();
+    // --seedがあれば再現可能な乱数列を使う
+    let mut rng = match seed {
+        Some(seed) => StdRng::seed_from_u64(seed),
+        None => StdRng::from_os_rng(),
+    };
+    let mut smooth = SmoothColor::new([0.0; 3], fade::step_count(interval, SMOOTH_TICK));
 
     // メインループ：乱数を生成し、LEDの色を更新する
     while running.load(Ordering::SeqCst) {
+        // PWMスレッドが止まっていたらエラーを表示して終了する
+        if heartbeats.iter().any(|h| h.is_stalled(PWM_STALL_THRESHOLD)) {
+            eprintln!("PWMスレッドが応答しません。終了します");
+            running.store(false, Ordering::SeqCst);
+            break;
+        }
+
+        if keyboard_mode {
+            shared.set(hsv_to_rgb(
+                key_hue.load(Ordering::SeqCst) as f64,
+                1.0,
+                key_value.load(Ordering::SeqCst) as f64 / 100.0,
+            ));
+            thread::sleep(Duration::from_millis(50));
+            continue;
+        }
+
+        if smooth_mode {
+            let previous_target = smooth.target();
+            let [r, g, b] = smooth.step(&mut rng);
+            shared.set((r, g, b));
+            let target = smooth.target();
+            if target != previous_target {
+                println!(
+                    "r {}, g {}, b {}",
+                    pwm::format_duty(target[0]),
+                    pwm::format_duty(target[1]),
+                    pwm::format_duty(target[2])
+                );
+            }
+            thread::sleep(SMOOTH_TICK);
+            continue;
+        }
+
         // 0から100の範囲でランダムな値を取得
-        let r_val = rng.gen_range(0..=100);
-        let g_val = rng.gen_range(0..=100);
-        let b_val = rng.gen_range(0..=100);
+        let [r_val, g_val, b_val] = random_color(&mut rng);
         
         // 値をデューティサイクル (0.0〜1.0) に変換
         // PythonのgpiozeroのRGBLEDクラス(active_high=False)の動作に合わせる
         // 値が100のとき、デューティサイクルは1.0 (完全にオン) となる
-        *r_duty.lock().unwrap() = r_val as f64 / 100.0;
-        *g_duty.lock().unwrap() = g_val as f64 / 100.0;
-        *b_duty.lock().unwrap() = b_val as f64 / 100.0;
+        shared.set((r_val as f64 / 100.0, g_val as f64 / 100.0, b_val as f64 / 100.0));
 
-        println!("r={}, g={}, b={}", r_val, g_val, b_val);
+        println!(
+            "r {}, g {}, b {}",
+            pwm::format_duty(r_val as f64 / 100.0),
+            pwm::format_duty(g_val as f64 / 100.0),
+            pwm::format_duty(b_val as f64 / 100.0)
+        );
 
-        // 1000ms待機
-        thread::sleep(Duration::from_millis(1000));
+        // 次の色まで待機 (--interval-ms)
+        thread::sleep(interval);
     }
     
-    println!("\nEnding program...");
-    
+    style::banner("\nEnding program...");
+
+    // 現在の色を保ったまま明るさを0までフェードアウト
+    let (r, g, b) = shared.color();
+    fade::ramp(1.0, 0.0, fade_out, fade::DEFAULT_TICK, |scale| {
+        shared.set((r * scale, g * scale, b * scale));
+        Ok::<(), Box<dyn Error>>(())
+    })?;
+
     // すべてのスレッドが終了するのを待つ
+    pwm_running.store(false, Ordering::SeqCst);
     for handle in threads {
-        handle.join().unwrap();
+        worker::join_timeout("PWM", handle, worker::SHUTDOWN_TIMEOUT)?;
     }
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn random_color_is_reproducible_with_a_seed() {
+        let colors = |seed| {
+            let mut rng = StdRng::seed_from_u64(seed);
+            (0..20).map(|_| random_color(&mut rng)).collect::<Vec<_>>()
+        };
+        assert_eq!(colors(7), colors(7));
+        assert!(colors(7).iter().flatten().all(|&c| c <= 100));
+    }
+
+    #[test]
+    fn smooth_color_reaches_each_target_in_steps() {
+        let mut rng = StdRng::seed_from_u64(1);
+        let mut smooth = SmoothColor::new([0.0; 3], 4);
+        // 最初のステップで目標色を選び、4ステップ目でちょうど到達する
+        let first = smooth.step(&mut rng);
+        let target = smooth.target();
+        for (c, t) in first.iter().zip(target) {
+            assert!((c - t / 4.0).abs() < 1e-9);
+        }
+        for _ in 0..3 {
+            smooth.step(&mut rng);
+        }
+        assert_eq!(smooth.target(), target);
+        // 目標に到達した次のステップで新しい目標色を選ぶ
+        smooth.step(&mut rng);
+        assert_ne!(smooth.target(), target);
+    }
+
+    #[test]
+    fn smooth_color_lands_on_the_target() {
+        let mut rng = StdRng::seed_from_u64(2);
+        let mut smooth = SmoothColor::new([0.5; 3], 5);
+        let mut last = [0.0; 3];
+        for _ in 0..5 {
+            last = smooth.step(&mut rng);
+        }
+        for (c, t) in last.iter().zip(smooth.target()) {
+            assert!((c - t).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn shared_color_applies_gamma_but_remembers_the_input() {
+        let mut shared = SharedColor::new(Gamma::uniform(2.0));
+        shared.set((0.5, 1.0, 0.0));
+        assert_eq!(shared.color(), (0.5, 1.0, 0.0));
+        let duties: Vec<f64> = shared.duties.iter().map(|d| *d.lock().unwrap()).collect();
+        assert_eq!(duties, [0.25, 1.0, 0.0]);
+    }
+}
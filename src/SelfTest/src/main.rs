@@ -0,0 +1,235 @@
+use std::error::Error;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use adc::Adc;
+use common::args::{Args, Opt};
+use common::error;
+use common::button::{setup_button_interrupt, wait_for_edge};
+use common::lock;
+use common::style;
+use rppal::gpio::Gpio;
+
+const DEFAULT_LED_PINS: [u8; 1] = [17];
+const DEFAULT_BTN_PIN: u8 = 18;
+const DEFAULT_BUTTON_TIMEOUT_S: u64 = 10;
+const BLINK_COUNT: u32 = 3;
+const BLINK_DELAY: Duration = Duration::from_millis(200);
+
+/// Outcome of one subsystem check.
+struct CheckResult {
+    name: String,
+    passed: bool,
+    detail: String,
+}
+
+impl CheckResult {
+    fn pass(name: impl Into<String>, detail: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            passed: true,
+            detail: detail.into(),
+        }
+    }
+
+    fn fail(name: impl Into<String>, detail: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            passed: false,
+            detail: detail.into(),
+        }
+    }
+}
+
+/// The hardware operations the checks need, so the orchestration does not
+/// depend on a real Raspberry Pi.
+trait Hardware {
+    /// Returns a description of the ADC that was found.
+    fn detect_adc(&mut self) -> Result<String, Box<dyn Error>>;
+    fn blink_led(&mut self, pin: u8) -> Result<(), Box<dyn Error>>;
+    /// Returns `true` if the button was pressed before `timeout`.
+    fn wait_for_press(&mut self, pin: u8, timeout: Duration) -> Result<bool, Box<dyn Error>>;
+}
+
+struct Config {
+    led_pins: Vec<u8>,
+    btn_pin: u8,
+    button_timeout: Duration,
+}
+
+/// Runs every check in order; a failing check never stops the later ones.
+fn run_checks(hw: &mut impl Hardware, config: &Config) -> Vec<CheckResult> {
+    let mut results = Vec::new();
+
+    println!("Checking I2C ADC ...");
+    results.push(match hw.detect_adc() {
+        Ok(found) => CheckResult::pass("ADC", found),
+        Err(e) => CheckResult::fail("ADC", e.to_string()),
+    });
+
+    for &pin in &config.led_pins {
+        let name = format!("LED (GPIO {})", pin);
+        println!("Blinking LED on GPIO {} - watch for {} flashes ...", pin, BLINK_COUNT);
+        results.push(match hw.blink_led(pin) {
+            Ok(()) => CheckResult::pass(name, "blinked"),
+            Err(e) => CheckResult::fail(name, e.to_string()),
+        });
+    }
+
+    let name = format!("Button (GPIO {})", config.btn_pin);
+    println!(
+        "Press the button on GPIO {} within {} s ...",
+        config.btn_pin,
+        config.button_timeout.as_secs()
+    );
+    results.push(match hw.wait_for_press(config.btn_pin, config.button_timeout) {
+        Ok(true) => CheckResult::pass(name, "press detected"),
+        Ok(false) => CheckResult::fail(name, "no press detected"),
+        Err(e) => CheckResult::fail(name, e.to_string()),
+    });
+
+    results
+}
+
+struct PiHardware;
+
+impl Hardware for PiHardware {
+    fn detect_adc(&mut self) -> Result<String, Box<dyn Error>> {
+        let adc = Adc::detect(&adc::DEFAULT_BUSES, adc::DEFAULT_RETRIES)?;
+        Ok(format!(
+            "{} at {:#04x} on bus {}",
+            adc.chip().name(),
+            adc.chip().address(),
+            adc.bus()
+        ))
+    }
+
+    fn blink_led(&mut self, pin: u8) -> Result<(), Box<dyn Error>> {
+        let mut led = Gpio::new()?.get(pin)?.into_output_low();
+        for _ in 0..BLINK_COUNT {
+            led.set_high();
+            thread::sleep(BLINK_DELAY);
+            led.set_low();
+            thread::sleep(BLINK_DELAY);
+        }
+        Ok(())
+    }
+
+    fn wait_for_press(&mut self, pin: u8, timeout: Duration) -> Result<bool, Box<dyn Error>> {
+        let mut btn_pin = Gpio::new()?.get(pin)?.into_input();
+        setup_button_interrupt(&mut btn_pin)?;
+        let deadline = Instant::now() + timeout;
+        let mut pressed = false;
+        while let Some(remaining) = deadline.checked_duration_since(Instant::now()) {
+            if wait_for_edge(&mut btn_pin, remaining)? && btn_pin.is_low() {
+                pressed = true;
+                break;
+            }
+        }
+        let _ = btn_pin.clear_interrupt();
+        Ok(pressed)
+    }
+}
+
+const OPTIONS: &[Opt] = &[
+    Opt::Value("--led-pins"),
+    Opt::Value("--button-pin"),
+    Opt::Value("--button-timeout-s"),
+    Opt::Value("--lock-file"),
+];
+
+fn main() {
+    error::run(try_main);
+}
+
+fn try_main() -> Result<(), Box<dyn Error>> {
+    let args = Args::from_env()?.with_version(env!("CARGO_PKG_VERSION")).with_options(OPTIONS);
+    let config = Config {
+        led_pins: args.list("--led-pins")?.unwrap_or(DEFAULT_LED_PINS.to_vec()),
+        btn_pin: args.value_or("--button-pin", DEFAULT_BTN_PIN)?,
+        button_timeout: Duration::from_secs(
+            args.value_or("--button-timeout-s", DEFAULT_BUTTON_TIMEOUT_S)?,
+        ),
+    };
+    let lock_file: Option<String> = args.value("--lock-file")?;
+    args.finish();
+    args.exit_if_dry_run();
+    let _lock = lock::acquire(lock_file.as_deref())?;
+
+    style::banner("Program is starting...");
+
+    let results = run_checks(&mut PiHardware, &config);
+
+    println!();
+    for result in &results {
+        let status = if result.passed { "PASS" } else { "FAIL" };
+        println!("[{}] {}: {}", status, result.name, result.detail);
+    }
+
+    let failures = results.iter().filter(|r| !r.passed).count();
+    if failures > 0 {
+        return Err(format!("{} check(s) failed", failures).into());
+    }
+    println!("All checks passed");
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Hardware that answers from canned results.
+    struct FakeHardware {
+        adc: Result<&'static str, &'static str>,
+        broken_led: Option<u8>,
+        pressed: bool,
+        blinked: Vec<u8>,
+    }
+
+    impl Hardware for FakeHardware {
+        fn detect_adc(&mut self) -> Result<String, Box<dyn Error>> {
+            self.adc.map(str::to_string).map_err(Into::into)
+        }
+
+        fn blink_led(&mut self, pin: u8) -> Result<(), Box<dyn Error>> {
+            self.blinked.push(pin);
+            if self.broken_led == Some(pin) {
+                return Err(format!("GPIO {} is in use", pin).into());
+            }
+            Ok(())
+        }
+
+        fn wait_for_press(&mut self, _pin: u8, _timeout: Duration) -> Result<bool, Box<dyn Error>> {
+            Ok(self.pressed)
+        }
+    }
+
+    fn config() -> Config {
+        Config {
+            led_pins: vec![17, 27],
+            btn_pin: 18,
+            button_timeout: Duration::from_secs(1),
+        }
+    }
+
+    #[test]
+    fn all_checks_pass_on_working_hardware() {
+        let mut hw = FakeHardware { adc: Ok("PCF8591 on bus 1"), broken_led: None, pressed: true, blinked: Vec::new() };
+        let results = run_checks(&mut hw, &config());
+        let names: Vec<_> = results.iter().map(|r| r.name.as_str()).collect();
+        assert_eq!(names, ["ADC", "LED (GPIO 17)", "LED (GPIO 27)", "Button (GPIO 18)"]);
+        assert!(results.iter().all(|r| r.passed));
+        assert_eq!(results[0].detail, "PCF8591 on bus 1");
+    }
+
+    #[test]
+    fn a_failing_check_does_not_stop_the_rest() {
+        let mut hw = FakeHardware { adc: Err("no ADC"), broken_led: Some(17), pressed: false, blinked: Vec::new() };
+        let results = run_checks(&mut hw, &config());
+        assert_eq!(hw.blinked, [17, 27]);
+        let passed: Vec<_> = results.iter().map(|r| r.passed).collect();
+        assert_eq!(passed, [false, false, true, false]);
+        assert_eq!(results[1].detail, "GPIO 17 is in use");
+        assert_eq!(results[3].detail, "no press detected");
+    }
+}
@@ -0,0 +1,222 @@
+use common::args::{Args, Opt};
+use common::blinker::TimedBlinker;
+use common::button::{Button, DEFAULT_DEBOUNCE, Debouncer, LongPress};
+use common::envelope::Envelope;
+use common::error::{self, open_gpio};
+use common::led::{Led, LedPolarity};
+use common::lock;
+use common::shutdown::{self, install_panic_hook};
+use common::style;
+use std::error::Error;
+use std::fmt;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+// LED and button wired as in the ButtonSwitch lesson
+const LED_PIN: u8 = 17;
+const BUTTON_PIN: u8 = 18;
+const DEFAULT_HOLD_MS: u64 = 1000;
+const DEFAULT_BLINK_MS: u64 = 500;
+// Brightness change per loop pass while breathing
+const BREATH_STEP: f64 = 0.01;
+const LOOP_DELAY: Duration = Duration::from_millis(10);
+
+/// Whether the controlled activity is running.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum PowerState {
+    /// LED off, waiting for a long press.
+    Idle,
+    /// Activity running until the next long press.
+    On,
+}
+
+impl PowerState {
+    /// The state after one loop pass; only a long press changes it, short
+    /// presses are ignored so a bumped button does nothing.
+    fn next(self, long_press: bool) -> PowerState {
+        match (self, long_press) {
+            (state, false) => state,
+            (PowerState::Idle, true) => PowerState::On,
+            (PowerState::On, true) => PowerState::Idle,
+        }
+    }
+}
+
+/// What the LED does while powered on, selected with `--activity`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Activity {
+    Breathe,
+    Blink,
+}
+
+impl FromStr for Activity {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "breathe" => Ok(Activity::Breathe),
+            "blink" => Ok(Activity::Blink),
+            _ => Err(format!("expected 'breathe' or 'blink', got '{}'", s)),
+        }
+    }
+}
+
+impl fmt::Display for Activity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Activity::Breathe => "breathe",
+            Activity::Blink => "blink",
+        })
+    }
+}
+
+/// The LED together with the state of the activity driving it.
+enum Load {
+    Breathe(Led, Envelope),
+    Blink(TimedBlinker),
+}
+
+impl Load {
+    fn new(activity: Activity, led: Led, blink_interval: Duration) -> Load {
+        match activity {
+            Activity::Breathe => Load::Breathe(led, Envelope::new(BREATH_STEP)),
+            Activity::Blink => {
+                let mut blinker = TimedBlinker::new(led, blink_interval, Instant::now());
+                blinker.stop();
+                Load::Blink(blinker)
+            }
+        }
+    }
+
+    /// Starts the activity from the beginning: dark for breathing, a fresh
+    /// on phase for blinking.
+    fn start(&mut self, now: Instant) {
+        match self {
+            Load::Breathe(_, envelope) => *envelope = Envelope::new(BREATH_STEP),
+            Load::Blink(blinker) => blinker.restart(now),
+        }
+    }
+
+    fn tick(&mut self, now: Instant) -> Result<(), Box<dyn Error>> {
+        match self {
+            Load::Breathe(led, envelope) => led.set_brightness(envelope.step())?,
+            Load::Blink(blinker) => {
+                blinker.tick(now);
+            }
+        }
+        Ok(())
+    }
+
+    fn stop(&mut self) -> Result<(), Box<dyn Error>> {
+        match self {
+            Load::Breathe(led, _) => led.clear()?,
+            Load::Blink(blinker) => blinker.stop(),
+        }
+        Ok(())
+    }
+}
+
+const OPTIONS: &[Opt] = &[
+    Opt::Value("--activity"),
+    Opt::Value("--hold-ms"),
+    Opt::Value("--blink-ms"),
+    Opt::Value("--lock-file"),
+];
+
+fn main() {
+    error::run(try_main);
+}
+
+fn try_main() -> Result<(), Box<dyn Error>> {
+    let args = Args::from_env()?.with_version(env!("CARGO_PKG_VERSION")).with_options(OPTIONS);
+    let activity: Activity = args.value_or("--activity", Activity::Breathe)?;
+    // How long the button must be held to switch on or off
+    let hold = Duration::from_millis(args.value_or("--hold-ms", DEFAULT_HOLD_MS)?);
+    let blink_interval = Duration::from_millis(args.value_or("--blink-ms", DEFAULT_BLINK_MS)?);
+    let lock_file: Option<String> = args.value("--lock-file")?;
+    args.finish();
+    if hold < DEFAULT_DEBOUNCE {
+        return Err(format!("--hold-ms must be at least {} ms", DEFAULT_DEBOUNCE.as_millis()).into());
+    }
+    args.exit_if_dry_run();
+    let _lock = lock::acquire(lock_file.as_deref())?;
+
+    style::banner("Program is starting...");
+    let gpio = open_gpio()?;
+    let led = Led::new(gpio.get(LED_PIN)?.into_output_low(), LedPolarity::ActiveHigh);
+    let button = Button::new(gpio.get(BUTTON_PIN)?.into_input_pullup());
+
+    let running = Arc::new(AtomicBool::new(true));
+    install_panic_hook(running.clone());
+    let r = running.clone();
+    shutdown::set_handler(move || {
+        style::banner("\nEnding program");
+        r.store(false, Ordering::SeqCst);
+    })?;
+
+    let mut load = Load::new(activity, led, blink_interval);
+    // A button already held at startup must be released before it counts
+    let mut debouncer = Debouncer::with_state(DEFAULT_DEBOUNCE, button.is_pressed());
+    let mut long_press = LongPress::new(hold);
+    let mut state = PowerState::Idle;
+    println!(
+        "Idle; hold the button on GPIO {} for {} ms to start",
+        BUTTON_PIN,
+        hold.as_millis()
+    );
+    while running.load(Ordering::SeqCst) {
+        let now = Instant::now();
+        let event = debouncer.update(button.is_pressed(), now);
+        let next = state.next(long_press.update(event, now));
+        if next != state {
+            match next {
+                PowerState::On => {
+                    println!("{}", style::on("Power on >>>"));
+                    load.start(now);
+                }
+                PowerState::Idle => {
+                    println!("{}", style::off("Power off <<<"));
+                    load.stop()?;
+                }
+            }
+            state = next;
+        }
+        if state == PowerState::On {
+            load.tick(now)?;
+        }
+        thread::sleep(LOOP_DELAY);
+    }
+
+    // Cleanup
+    load.stop()?;
+    style::banner("Program is finished.");
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn long_press_toggles_power() {
+        assert_eq!(PowerState::Idle.next(true), PowerState::On);
+        assert_eq!(PowerState::On.next(true), PowerState::Idle);
+    }
+
+    #[test]
+    fn no_long_press_keeps_the_state() {
+        assert_eq!(PowerState::Idle.next(false), PowerState::Idle);
+        assert_eq!(PowerState::On.next(false), PowerState::On);
+    }
+
+    #[test]
+    fn activity_names_round_trip() {
+        for activity in [Activity::Breathe, Activity::Blink] {
+            assert_eq!(activity.to_string().parse(), Ok(activity));
+        }
+        assert!("strobe".parse::<Activity>().is_err());
+    }
+}
@@ -1,161 +1,584 @@
+mod scope;
+
 use std::error::Error;
+use std::fmt;
+use std::path::Path;
+use std::str::FromStr;
 use std::sync::atomic::{AtomicBool, AtomicU8, Ordering};
 use std::sync::Arc;
 use std::thread;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
-use rppal::gpio::Gpio;
-use rppal::i2c::I2c;
+use adc::lut::Lut;
+use adc::{Adc, Warmup};
+use common::args::{Args, Opt};
+use common::csvlog::CsvLogger;
+use common::error::{self, AppError};
+use common::fade;
+use common::heartbeat::Heartbeat;
+use common::keyboard::{self, KeyAction};
+use common::lock;
+use common::metrics::{self, Metrics};
+use common::pwm;
+use common::retry::{Backoff, FailureCounter};
+use common::shutdown::{self, install_panic_hook};
+use common::stats::RollingStats;
+use common::status;
+use common::style;
+use common::throttle::Throttle;
+use common::worker;
+use rppal::gpio::{Gpio, Level};
+use scope::Capture;
 
-const PCF8591_ADDR: u16 = 0x48;
-const ADS7830_ADDR: u16 = 0x4b;
+// The default I2C bus and number of detection attempts
+const I2C_BUS: u8 = 1;
+const DETECT_RETRIES: u32 = 5;
+// Consecutive read errors before the I2C handle is reopened
+const DEFAULT_RECONNECT_AFTER: u32 = 10;
 // GPIO 17 (BCM)
 const LED_PIN: u8 = 17;
+// Minimum interval between status lines
+const DEFAULT_LOG_EVERY_MS: u64 = 500;
+// Duty change per key press in --keyboard mode
+const KEY_STEP: u8 = 16;
+// The PWM worker is considered dead after this long without a heartbeat
+const PWM_STALL_THRESHOLD: Duration = Duration::from_secs(1);
+// Startup wait before the first ADC read and readings thrown away after it
+const DEFAULT_SETTLE_MS: u64 = 50;
+const DEFAULT_DISCARD_SAMPLES: u32 = 2;
+// --scope defaults: trigger half way up, a short lead-in and a longer tail
+const DEFAULT_TRIGGER_LEVEL: u8 = 128;
+const DEFAULT_PRE_SAMPLES: usize = 100;
+const DEFAULT_POST_SAMPLES: usize = 400;
+// Wait between --scope retries after a failed read, doubling while it keeps failing
+const SCOPE_RETRY_INITIAL: Duration = Duration::from_millis(10);
+const SCOPE_RETRY_MAX: Duration = Duration::from_millis(500);
+// How often the --csv file is flushed to disk
+const DEFAULT_CSV_FLUSH_MS: u64 = 1000;
+// Main loop period
+const LOOP_DELAY: Duration = Duration::from_millis(30);
+// Readings kept for --stats
+const DEFAULT_STATS_WINDOW: usize = 100;
+// Software PWM period: 100 Hz, the same as RGBLED
+const PWM_PERIOD_US: u64 = 10_000;
+// Granularity of the PWM on-time, see `--tick-us`
+const DEFAULT_TICK_US: u64 = 1000;
+// Linux sleeps overshoot by tens of microseconds, so finer on-times are noise
+const MIN_TICK_US: u64 = 100;
 
-fn main() -> Result<(), Box<dyn Error>> {
-    println!("Program is starting ...");
+/// Number of distinct duty levels the PWM worker can output when the
+/// on-time is a whole number of `tick_us` ticks within a `period_us` period.
+///
+/// A smaller tick gives finer brightness steps and smoother fades. The
+/// worker sleeps through each on and off segment, so the CPU cost stays at
+/// two wake-ups a period; what limits the tick is sleep accuracy, which is
+/// why ticks below `MIN_TICK_US` are rejected.
+fn duty_resolution(period_us: u64, tick_us: u64) -> u64 {
+    (period_us / tick_us.max(1)).max(1)
+}
 
-    // Initialize I2C
-    let mut i2c = I2c::new()?;
+/// Unit of the voltage in status lines, selected with `--voltage-unit`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum VoltageUnit {
+    Volts,
+    Millivolts,
+}
 
-    // Detect I2C device with retries
-    let mut is_pcf8591 = None;
+impl FromStr for VoltageUnit {
+    type Err = String;
 
-    for _ in 0..5 {
-        if i2c.set_slave_address(PCF8591_ADDR).is_ok() && i2c.read(&mut [0]).is_ok() {
-            is_pcf8591 = Some(true);
-            break;
-        } else if i2c.set_slave_address(ADS7830_ADDR).is_ok() && i2c.read(&mut [0]).is_ok() {
-            is_pcf8591 = Some(false);
-            break;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "v" => Ok(VoltageUnit::Volts),
+            "mv" => Ok(VoltageUnit::Millivolts),
+            _ => Err(format!("expected 'v' or 'mv', got '{}'", s)),
         }
-        thread::sleep(Duration::from_millis(100));
     }
+}
+
+impl fmt::Display for VoltageUnit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            VoltageUnit::Volts => "v",
+            VoltageUnit::Millivolts => "mv",
+        })
+    }
+}
 
-    let is_pcf8591 = match is_pcf8591 {
-        Some(v) => v,
-        None => {
-            eprintln!("No correct I2C address found after retries,");
-            eprintln!("Please use command 'i2cdetect -y 1' to check the I2C address!");
-            eprintln!("Program Exit.");
-            std::process::exit(-1);
+// Significant figures in formatted voltages, so log lines keep a fixed shape
+const VOLTAGE_SIG_FIGS: i32 = 4;
+
+/// Formats `volts` in `unit` with `VOLTAGE_SIG_FIGS` significant figures,
+/// e.g. "1.590 V" or "1590 mV".
+fn format_voltage(volts: f64, unit: VoltageUnit) -> String {
+    let (value, suffix) = match unit {
+        VoltageUnit::Volts => (volts, "V"),
+        VoltageUnit::Millivolts => (volts * 1000.0, "mV"),
+    };
+    let magnitude = if value == 0.0 { 0 } else { value.abs().log10().floor() as i32 };
+    let decimals = (VOLTAGE_SIG_FIGS - 1 - magnitude).max(0) as usize;
+    format!("{:.*} {}", decimals, value, suffix)
+}
+
+/// Duty to put on the pin for a brightness `duty`. A common-anode LED
+/// (anode to 3.3V, cathode on the pin) lights while the pin is low, so its
+/// duty is inverted.
+fn output_duty(duty: u8, invert: bool) -> u8 {
+    if invert { 255 - duty } else { duty }
+}
+
+/// Ticks per period the pin is held high for an 8-bit `duty`.
+fn on_ticks(duty: u8, steps: u64) -> u64 {
+    (duty as u64 * steps + 127) / 255
+}
+
+const OPTIONS: &[Opt] = &[
+    Opt::Value("--log-every"),
+    Opt::Value("--fade-in-ms"),
+    Opt::Value("--fade-out-ms"),
+    Opt::Value("--reconnect-after"),
+    Opt::Flag("--keyboard"),
+    Opt::Value("--tick-us"),
+    Opt::Value("--metrics"),
+    Opt::Flag("--invert-duty"),
+    Opt::Value("--vref"),
+    Opt::Value("--voltage-unit"),
+    Opt::Value("--i2c-hz"),
+    Opt::Value("--i2c-timeout-ms"),
+    Opt::Value("--pcf-dummy-reads"),
+    Opt::Value("--heartbeat-pin"),
+    Opt::Flag("--stats"),
+    Opt::Value("--stats-window"),
+    Opt::Value("--settle-ms"),
+    Opt::Value("--discard-samples"),
+    Opt::Value("--csv"),
+    Opt::Value("--csv-flush-ms"),
+    Opt::Value("--csv-max-rows"),
+    Opt::Value("--lut"),
+    Opt::Flag("--scope"),
+    Opt::Value("--trigger-level"),
+    Opt::Value("--pre"),
+    Opt::Value("--post"),
+    Opt::Value("--lock-file"),
+];
+
+fn main() {
+    error::run(try_main);
+}
+
+fn try_main() -> Result<(), Box<dyn Error>> {
+    let args = Args::from_env()?.with_version(env!("CARGO_PKG_VERSION")).with_options(OPTIONS);
+    let log_every = Duration::from_millis(args.value_or("--log-every", DEFAULT_LOG_EVERY_MS)?);
+    let fade_in = Duration::from_millis(args.value_or("--fade-in-ms", 0)?);
+    let fade_out = Duration::from_millis(args.value_or("--fade-out-ms", 0)?);
+    let reconnect_after = args.value_or("--reconnect-after", DEFAULT_RECONNECT_AFTER)?;
+    let keyboard_mode = args.flag("--keyboard");
+    let tick_us = args.value_or("--tick-us", DEFAULT_TICK_US)?;
+    let metrics_addr: Option<String> = args.value("--metrics")?;
+    let invert_duty = args.flag("--invert-duty");
+    let vref = args.value_or("--vref", adc::DEFAULT_VREF)?;
+    let voltage_unit: VoltageUnit = args.value_or("--voltage-unit", VoltageUnit::Volts)?;
+    let i2c_hz: Option<u32> = args.value("--i2c-hz")?;
+    let i2c_timeout_ms: Option<u32> = args.value("--i2c-timeout-ms")?;
+    // Raise if the PCF8591 readings lag one sample behind
+    let pcf_dummy_reads = args.value_or("--pcf-dummy-reads", adc::DEFAULT_PCF_DUMMY_READS)?;
+    let heartbeat_pin: Option<u8> = args.value("--heartbeat-pin")?;
+    let stats_mode = args.flag("--stats");
+    let stats_window = args.value_or("--stats-window", DEFAULT_STATS_WINDOW)?;
+    let settle = Duration::from_millis(args.value_or("--settle-ms", DEFAULT_SETTLE_MS)?);
+    let discard_samples = args.value_or("--discard-samples", DEFAULT_DISCARD_SAMPLES)?;
+    let csv_path: Option<String> = args.value("--csv")?;
+    let csv_flush = Duration::from_millis(args.value_or("--csv-flush-ms", DEFAULT_CSV_FLUSH_MS)?);
+    let csv_max_rows: Option<u64> = args.value("--csv-max-rows")?;
+    // Raw-to-value table for a nonlinear sensor; adds a converted value to the output
+    let lut_path: Option<String> = args.value("--lut")?;
+    let scope_mode = args.flag("--scope");
+    let trigger_level = args.value_or("--trigger-level", DEFAULT_TRIGGER_LEVEL)?;
+    let pre_samples = args.value_or("--pre", DEFAULT_PRE_SAMPLES)?;
+    let post_samples = args.value_or("--post", DEFAULT_POST_SAMPLES)?;
+    let lock_file: Option<String> = args.value("--lock-file")?;
+    args.finish();
+    if csv_max_rows == Some(0) {
+        return Err("--csv-max-rows must be positive".into());
+    }
+    let lut = lut_path.map(|path| Lut::load(Path::new(&path))).transpose()?;
+    if scope_mode && keyboard_mode {
+        return Err("--scope needs the ADC and cannot be combined with --keyboard".into());
+    }
+    if tick_us < MIN_TICK_US {
+        return Err(format!("--tick-us must be at least {}, got {}", MIN_TICK_US, tick_us).into());
+    }
+    if i2c_timeout_ms == Some(0) {
+        return Err("--i2c-timeout-ms must be positive".into());
+    }
+    let steps = duty_resolution(PWM_PERIOD_US, tick_us);
+    if !adc::PLAUSIBLE_VREF.contains(&vref) {
+        eprintln!(
+            "Warning: --vref {} V is outside the usual {}-{} V range, voltages will look wrong",
+            vref,
+            adc::PLAUSIBLE_VREF.start(),
+            adc::PLAUSIBLE_VREF.end()
+        );
+    }
+
+    args.exit_if_dry_run();
+    let _lock = lock::acquire(lock_file.as_deref())?;
+
+    style::banner("Program is starting ...");
+    if invert_duty {
+        style::banner("LED polarity: common anode (duty inverted)");
+    } else {
+        style::banner("LED polarity: common cathode");
+    }
+    style::banner(&format!(
+        "PWM tick {} us over a {} us period: {} duty steps",
+        tick_us, PWM_PERIOD_US, steps
+    ));
+
+    // Detect I2C device with retries (the keyboard replaces the potentiometer)
+    let mut adc = if keyboard_mode {
+        None
+    } else {
+        let mut adc = match Adc::detect(&[I2C_BUS], DETECT_RETRIES) {
+            Ok(adc) => adc,
+            Err(e) => {
+                eprintln!("No correct I2C address found after retries,");
+                eprintln!("I2C scan: {}", adc::describe_scan(&adc::scan(&[I2C_BUS])));
+                return Err(AppError::AdcNotFound(e.to_string()).into());
+            }
+        };
+        println!("Detected I2C device: {}", adc.chip().name());
+        adc.log_clock(i2c_hz)?;
+        if let Some(ms) = i2c_timeout_ms {
+            adc.set_timeout(ms)?;
         }
+        adc.set_pcf_dummy_reads(pcf_dummy_reads);
+        Some(adc)
     };
 
-    println!(
-        "Detected I2C device: {}",
-        if is_pcf8591 { "PCF8591" } else { "ADS7830" }
-    );
+    if scope_mode
+        && let Some(adc) = adc.as_mut()
+    {
+        let capture = Capture::new(trigger_level, pre_samples, post_samples);
+        return run_scope(adc, capture);
+    }
 
     // Shared state for SoftPWM
+    // The PWM thread has its own flag so it keeps running during the fade-out
     let running = Arc::new(AtomicBool::new(true));
+    install_panic_hook(running.clone());
+
+    // Setup CTRL-C handler
+    let running_clone = running.clone();
+    shutdown::set_handler(move || {
+        style::banner("\nEnding program");
+        running_clone.store(false, Ordering::SeqCst);
+    })?;
+
+    // Optional 1 Hz "still alive" LED for headless runs
+    let status_handle = heartbeat_pin
+        .map(|pin| status::spawn_status_led(pin, running.clone()))
+        .transpose()?;
+    let pwm_running = Arc::new(AtomicBool::new(true));
     let duty_cycle = Arc::new(AtomicU8::new(0));
+    let pwm_heartbeat = Arc::new(Heartbeat::new());
 
     // Spawn SoftPWM thread
     let pwm_handle = {
-        let running = running.clone();
+        let running = pwm_running.clone();
         let duty_cycle = duty_cycle.clone();
-        thread::spawn(move || {
-            let gpio = match Gpio::new() {
-                Ok(g) => g,
-                Err(e) => {
-                    eprintln!("Failed to access GPIO: {}", e);
-                    return;
-                }
-            };
+        let heartbeat = pwm_heartbeat.clone();
+        // Start with the LED off
+        let off_level = Level::from(invert_duty);
+        let init = move || {
+            let pin = Gpio::new()?.get(LED_PIN)?;
+            Ok(if invert_duty { pin.into_output_high() } else { pin.into_output_low() })
+        };
+        worker::spawn_initialized("PWM", init, move |mut pin| {
+            let period = Duration::from_micros(PWM_PERIOD_US);
+            let mut cycle_start = Instant::now();
+
+            while running.load(Ordering::SeqCst) {
+                // A new duty is picked up once per period
+                heartbeat.beat();
+                let duty = output_duty(duty_cycle.load(Ordering::SeqCst), invert_duty);
+                let on_time = period.mul_f64(on_ticks(duty, steps) as f64 / steps as f64);
+                cycle_start =
+                    pwm::output_period(&mut pin, cycle_start, period, on_time, Level::High, Level::Low);
+            }
+            // Turn off LED on exit
+            pin.write(off_level);
+        })?
+    };
+
+    // Everything that can fail while the PWM worker runs goes through this
+    // closure, so an error still stops the worker and leaves the LED off
+    let result = (|| -> Result<(), Box<dyn Error>> {
+        let _keys = if keyboard_mode {
+            println!("{}", keyboard::KEY_HELP);
+            let duty_cycle = duty_cycle.clone();
+            Some(keyboard::spawn_key_reader(move |action| {
+                let _ = duty_cycle.fetch_update(Ordering::SeqCst, Ordering::SeqCst, |duty| match action {
+                    KeyAction::Increase => Some(duty.saturating_add(KEY_STEP)),
+                    KeyAction::Decrease => Some(duty.saturating_sub(KEY_STEP)),
+                    KeyAction::Next | KeyAction::Previous => None,
+                });
+            }))
+        } else {
+            None
+        };
+
+        let metrics = Arc::new(Metrics::default());
+        if let Some(addr) = &metrics_addr {
+            metrics::serve(addr.as_str(), metrics.clone())?;
+            println!("Serving metrics on http://{}/metrics", addr);
+        }
+
+        // Let the supply settle so the first reading does not flash the LED
+        if adc.is_some() {
+            thread::sleep(settle);
+        }
+        let mut warmup = Warmup::new(discard_samples);
+        let mut read_failures = FailureCounter::new(reconnect_after);
+        let mut stats = RollingStats::new(stats_window);
 
-            let mut pin = match gpio.get(LED_PIN) {
-                Ok(p) => p.into_output(),
-                Err(e) => {
-                    eprintln!("Failed to get GPIO pin {}: {}", LED_PIN, e);
-                    return;
+        // Ease in to the first reading instead of jumping to it
+        if !fade_in.is_zero()
+            && let Some(adc) = adc.as_mut()
+        {
+            let first = loop {
+                if !running.load(Ordering::SeqCst) {
+                    break None;
+                }
+                if let Some(reading) = read_adc(adc, &mut read_failures, &mut warmup) {
+                    break Some(reading);
                 }
+                thread::sleep(LOOP_DELAY);
             };
+            if let Some(first) = first {
+                stats.push(first as f64);
+                fade::ramp_while(&running, 0.0, first as f64, fade_in, fade::DEFAULT_TICK, |duty| {
+                    duty_cycle.store(duty.round() as u8, Ordering::SeqCst);
+                    Ok::<(), Box<dyn Error>>(())
+                })?;
+            }
+        }
 
-            // 1 kHz frequency = 1000 us period
-            let period_micros = 1000u64;
+        // Optional log of every reading: ms since start, raw value, volts, and
+        // the --lut value if a table was given
+        let csv_header = if lut.is_some() { "t_ms,raw,voltage,value" } else { "t_ms,raw,voltage" };
+        let mut csv = csv_path
+            .map(|path| CsvLogger::create(path, csv_header, csv_max_rows, csv_flush))
+            .transpose()?;
+        let log_start = Instant::now();
 
-            while running.load(Ordering::SeqCst) {
-                let duty = duty_cycle.load(Ordering::SeqCst) as u64;
-
-                if duty == 0 {
-                    pin.set_low();
-                    thread::sleep(Duration::from_micros(period_micros));
-                } else if duty == 255 {
-                    pin.set_high();
-                    thread::sleep(Duration::from_micros(period_micros));
-                } else {
-                    // Calculate on/off times
-                    // duty is 0..255
-                    let on_time = (period_micros * duty) / 255;
-                    let off_time = period_micros - on_time;
-
-                    pin.set_high();
-                    thread::sleep(Duration::from_micros(on_time));
-                    if off_time > 0 {
-                        pin.set_low();
-                        thread::sleep(Duration::from_micros(off_time));
+        // Main loop
+        let mut log_throttle = Throttle::new(log_every);
+        while running.load(Ordering::SeqCst) {
+            if pwm_heartbeat.is_stalled(PWM_STALL_THRESHOLD) {
+                eprintln!("PWM thread stopped responding, shutting down");
+                running.store(false, Ordering::SeqCst);
+                break;
+            }
+
+            if let Some(adc) = adc.as_mut()
+                && let Some(reading) = read_adc(adc, &mut read_failures, &mut warmup)
+            {
+                // Update PWM duty cycle
+                duty_cycle.store(reading, Ordering::SeqCst);
+                stats.push(reading as f64);
+                if let Some(csv) = csv.as_mut() {
+                    let voltage = adc::to_voltage(reading as u16, adc.chip().full_scale(), vref);
+                    let mut row = format!("{},{},{:.4}", log_start.elapsed().as_millis(), reading, voltage);
+                    if let Some(lut) = &lut {
+                        row.push_str(&format!(",{:.4}", lut.interpolate(reading as f64)));
                     }
+                    csv.write_row(&row)?;
                 }
             }
-            // Turn off LED on exit
-            pin.set_low();
-        })
-    };
 
-    // Setup CTRL-C handler
-    let running_clone = running.clone();
-    ctrlc::set_handler(move || {
-        println!("\nEnding program");
-        running_clone.store(false, Ordering::SeqCst);
+            // Display info
+            // Voltage relative to the board's reference (--vref)
+            let value = duty_cycle.load(Ordering::SeqCst);
+            // The keyboard sets an 8-bit duty directly, the same range as the kit's chips
+            let full_scale = adc.as_ref().map_or(u8::MAX as u16, |adc| adc.chip().full_scale());
+            let voltage = adc::to_voltage(value as u16, full_scale, vref);
+            if adc.is_some() {
+                metrics.adc_raw.set(value as f64);
+                metrics.adc_voltage.set(voltage);
+            }
+            metrics.pwm_duty.set(value as f64 / 255.0);
+            if log_throttle.should_emit(Instant::now()) {
+                let converted = lut
+                    .as_ref()
+                    .map(|lut| format!(", Value : {:.2}", lut.interpolate(value as f64)))
+                    .unwrap_or_default();
+                if stats_mode {
+                    println!(
+                        "ADC Value : {}, Voltage : {}{}, {}, {}",
+                        value,
+                        format_voltage(voltage, voltage_unit),
+                        converted,
+                        pwm::format_duty(value as f64 / 255.0),
+                        stats.summary()
+                    );
+                } else {
+                    println!(
+                        "ADC Value : {}, Voltage : {}{}, {}",
+                        value,
+                        format_voltage(voltage, voltage_unit),
+                        converted,
+                        pwm::format_duty(value as f64 / 255.0)
+                    );
+                }
+            }
+
+            thread::sleep(LOOP_DELAY);
+        }
+
+        if let Some(csv) = csv {
+            csv.finish()?;
+        }
+        Ok(())
+    })();
+
+    // Fade out from wherever the last reading left the LED
+    let start = duty_cycle.load(Ordering::SeqCst) as f64;
+    fade::ramp(start, 0.0, fade_out, fade::DEFAULT_TICK, |duty| {
+        duty_cycle.store(duty.round() as u8, Ordering::SeqCst);
+        Ok::<(), Box<dyn Error>>(())
     })?;
 
-    // Main loop
-    while running.load(Ordering::SeqCst) {
-        let value_result: Result<u8, Box<dyn Error>> = if is_pcf8591 {
-            // PCF8591
-            i2c.set_slave_address(PCF8591_ADDR)
-                .and_then(|_| i2c.write(&[0x40]))
-                .and_then(|_| {
-                    let mut buf = [0u8; 1];
-                    i2c.read(&mut buf)?;
-                    i2c.read(&mut buf)?;
-                    Ok(buf[0])
-                })
-                .map_err(|e| e.into()) // Convert rppal::i2c::Error to Box<dyn Error>
-        } else {
-            // ADS7830
-            i2c.set_slave_address(ADS7830_ADDR)
-                .and_then(|_| i2c.write(&[0x84]))
-                .and_then(|_| {
-                    let mut buf = [0u8; 1];
-                    i2c.read(&mut buf)?;
-                    Ok(buf[0])
-                })
-                .map_err(|e| e.into()) // Convert rppal::i2c::Error to Box<dyn Error>
-        };
+    // Wait for PWM thread to finish
+    running.store(false, Ordering::SeqCst);
+    pwm_running.store(false, Ordering::SeqCst);
+    worker::join_timeout("PWM", pwm_handle, worker::SHUTDOWN_TIMEOUT)?;
+    if let Some(handle) = status_handle {
+        worker::join_timeout("status LED", handle, worker::SHUTDOWN_TIMEOUT)?;
+    }
 
-        match value_result {
-            Ok(value) => {
-                // Update PWM duty cycle
-                duty_cycle.store(value, Ordering::SeqCst);
+    result
+}
+
+/// `--scope`: reads channel 0 as fast as the bus allows until a burst has
+/// been captured, then prints it as CSV with times relative to the trigger.
+/// The LED is not driven in this mode.
+fn run_scope(adc: &mut Adc, mut capture: Capture<(Instant, u8)>) -> Result<(), Box<dyn Error>> {
+    let running = Arc::new(AtomicBool::new(true));
+    let r = running.clone();
+    shutdown::set_handler(move || {
+        r.store(false, Ordering::SeqCst);
+    })?;
 
-                // Display info
-                // Voltage reference 3.3V
-                let voltage = (value as f64 / 255.0) * 3.3;
-                println!("ADC Value : {}, Voltage : {:.2}", value, voltage);
+    eprintln!("Waiting for trigger, press Ctrl+C to stop ...");
+    let mut backoff = Backoff::new(SCOPE_RETRY_INITIAL, SCOPE_RETRY_MAX);
+    while running.load(Ordering::SeqCst) {
+        let raw = match adc.read(0) {
+            Ok(raw) => {
+                backoff.reset();
+                raw
             }
             Err(e) => {
                 eprintln!("Error reading I2C: {}", e);
-                // Optional: add a small delay or just continue to retry
+                thread::sleep(backoff.next_delay());
+                continue;
+            }
+        };
+        if let Some(burst) = capture.push(raw, (Instant::now(), raw)) {
+            let (trigger_time, _) = burst.samples[burst.trigger_index];
+            println!("t_us,raw");
+            for &(time, raw) in &burst.samples {
+                let t_us = if time >= trigger_time {
+                    time.duration_since(trigger_time).as_micros() as i64
+                } else {
+                    -(trigger_time.duration_since(time).as_micros() as i64)
+                };
+                println!("{},{}", t_us, raw);
+            }
+            return Ok(());
+        }
+    }
+    eprintln!("Stopped before the trigger fired");
+    Ok(())
+}
+
+/// Reads the potentiometer, reopening the I2C bus after a run of
+/// consecutive read errors. Readings still inside the `warmup` are dropped.
+/// Returns the reading if one was usable.
+fn read_adc(
+    adc: &mut Adc,
+    read_failures: &mut FailureCounter,
+    warmup: &mut Warmup,
+) -> Option<u8> {
+    match adc.read(0) {
+        Ok(value) => {
+            read_failures.record_success();
+            if !warmup.accept() {
+                return None;
+            }
+            Some(value)
+        }
+        Err(e) => {
+            eprintln!("Error reading I2C: {}", e);
+            if read_failures.record_failure() {
+                eprintln!("Too many consecutive read errors, reopening I2C bus {} ...", adc.bus());
+                match adc.reopen(DETECT_RETRIES) {
+                    Ok(()) => println!("Reconnected to {}", adc.chip().name()),
+                    Err(e) => eprintln!("Reconnect failed: {}", e),
+                }
             }
+            None
         }
+    }
+}
 
-        thread::sleep(Duration::from_millis(30));
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_tick_gives_ten_steps_per_period() {
+        assert_eq!(duty_resolution(PWM_PERIOD_US, DEFAULT_TICK_US), 10);
+        assert_eq!(duty_resolution(PWM_PERIOD_US, MIN_TICK_US), 100);
     }
 
-    // Wait for PWM thread to finish
-    let _ = pwm_handle.join();
+    #[test]
+    fn resolution_never_drops_below_one_step() {
+        assert_eq!(duty_resolution(1000, 5000), 1);
+        assert_eq!(duty_resolution(1000, 0), 1000);
+    }
 
-    Ok(())
+    #[test]
+    fn on_ticks_rounds_duty_to_nearest_step() {
+        assert_eq!(on_ticks(0, 10), 0);
+        assert_eq!(on_ticks(255, 10), 10);
+        assert_eq!(on_ticks(128, 10), 5);
+        assert_eq!(on_ticks(12, 10), 0);
+        assert_eq!(on_ticks(13, 10), 1);
+    }
+
+    #[test]
+    fn voltages_keep_four_significant_figures() {
+        assert_eq!(format_voltage(1.59, VoltageUnit::Volts), "1.590 V");
+        assert_eq!(format_voltage(1.59, VoltageUnit::Millivolts), "1590 mV");
+        assert_eq!(format_voltage(0.0123, VoltageUnit::Volts), "0.01230 V");
+        assert_eq!(format_voltage(0.0, VoltageUnit::Volts), "0.000 V");
+        assert_eq!(format_voltage(12.345, VoltageUnit::Millivolts), "12345 mV");
+    }
+
+    #[test]
+    fn common_anode_inverts_the_duty() {
+        assert_eq!(output_duty(0, false), 0);
+        assert_eq!(output_duty(200, false), 200);
+        assert_eq!(output_duty(0, true), 255);
+        assert_eq!(output_duty(200, true), 55);
+    }
+
+    #[test]
+    fn voltage_unit_names_round_trip() {
+        for unit in [VoltageUnit::Volts, VoltageUnit::Millivolts] {
+            assert_eq!(unit.to_string().parse(), Ok(unit));
+        }
+        assert!("V".parse::<VoltageUnit>().is_err());
+    }
 }
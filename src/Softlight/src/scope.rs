@@ -0,0 +1,129 @@
+//! `--scope`: capture a burst of ADC samples around a rising edge.
+//!
+//! Samples are fed in one at a time. The last `pre` samples are kept in a
+//! ring buffer until one crosses the trigger level from below; the capture
+//! then takes `post` more samples and hands back the whole burst.
+
+use std::collections::VecDeque;
+
+/// Fires when a sample reaches `level` after the previous one was below it.
+#[derive(Clone, Copy, Debug)]
+pub struct RisingTrigger {
+    level: u8,
+    // Whether the signal has been below the level since the last fire
+    armed: bool,
+}
+
+impl RisingTrigger {
+    /// Starts disarmed so a signal that is already high does not fire
+    /// until it has dropped below `level` once.
+    pub fn new(level: u8) -> Self {
+        Self { level, armed: false }
+    }
+
+    pub fn update(&mut self, sample: u8) -> bool {
+        if sample < self.level {
+            self.armed = true;
+            return false;
+        }
+        let fired = self.armed;
+        self.armed = false;
+        fired
+    }
+}
+
+/// One captured burst. `samples[trigger_index]` is the sample that fired.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Burst<T> {
+    pub samples: Vec<T>,
+    pub trigger_index: usize,
+}
+
+/// Pre-trigger ring buffer plus post-trigger capture.
+pub struct Capture<T> {
+    pre: VecDeque<T>,
+    pre_len: usize,
+    post_len: usize,
+    trigger: RisingTrigger,
+    // Set once triggered: the burst so far and its trigger index
+    burst: Option<Burst<T>>,
+}
+
+impl<T> Capture<T> {
+    pub fn new(level: u8, pre: usize, post: usize) -> Self {
+        Self {
+            pre: VecDeque::with_capacity(pre),
+            pre_len: pre,
+            post_len: post,
+            trigger: RisingTrigger::new(level),
+            burst: None,
+        }
+    }
+
+    /// Feeds one sample; `raw` is what the trigger looks at and `item` is
+    /// what gets stored (e.g. the raw value with a timestamp). Returns the
+    /// burst once `post` samples after the trigger have been taken.
+    pub fn push(&mut self, raw: u8, item: T) -> Option<Burst<T>> {
+        if let Some(burst) = self.burst.as_mut() {
+            burst.samples.push(item);
+            if burst.samples.len() > burst.trigger_index + self.post_len {
+                return self.burst.take();
+            }
+            return None;
+        }
+
+        if self.trigger.update(raw) {
+            let mut samples: Vec<T> = self.pre.drain(..).collect();
+            let trigger_index = samples.len();
+            samples.push(item);
+            let burst = Burst { samples, trigger_index };
+            if self.post_len == 0 {
+                return Some(burst);
+            }
+            self.burst = Some(burst);
+            return None;
+        }
+
+        if self.pre_len > 0 {
+            if self.pre.len() == self.pre_len {
+                self.pre.pop_front();
+            }
+            self.pre.push_back(item);
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn trigger_fires_only_on_a_rising_crossing() {
+        let mut trigger = RisingTrigger::new(100);
+        // Already high at startup: no edge seen yet
+        assert!(!trigger.update(150));
+        let fired: Vec<_> = [50, 120, 130, 90, 100].iter().map(|&s| trigger.update(s)).collect();
+        assert_eq!(fired, [false, true, false, false, true]);
+    }
+
+    #[test]
+    fn burst_holds_pre_and_post_samples() {
+        let mut capture = Capture::new(100, 2, 2);
+        let mut burst = None;
+        for (i, raw) in [10, 20, 30, 200, 210, 220, 5].into_iter().enumerate() {
+            if let Some(b) = capture.push(raw, i) {
+                burst = Some(b);
+                break;
+            }
+        }
+        assert_eq!(burst, Some(Burst { samples: vec![1, 2, 3, 4, 5], trigger_index: 2 }));
+    }
+
+    #[test]
+    fn zero_post_returns_at_the_trigger() {
+        let mut capture = Capture::new(100, 0, 0);
+        assert_eq!(capture.push(10, 'a'), None);
+        assert_eq!(capture.push(200, 'b'), Some(Burst { samples: vec!['b'], trigger_index: 0 }));
+    }
+}
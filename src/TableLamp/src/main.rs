@@ -1,41 +1,251 @@
-use rppal::gpio::{Gpio, Level, Trigger};
+use common::args::{Args, Opt};
+use common::button::{
+    self, AutoRepeat, Button, ButtonEvent, ButtonLogic, DEFAULT_DEBOUNCE, DebounceStrategy,
+    Debouncer, setup_button_interrupt, wait_for_edge,
+};
+use common::buzzer::{self, Buzzer};
+use common::error::{self, open_gpio};
+use common::lock;
+use common::replay::{self, Recorder};
+use common::shutdown::{self, CleanupPolicy, install_panic_hook};
+use common::stats::LatencyStats;
+use common::style;
+use rppal::gpio::{Level, OutputPin};
 use std::error::Error;
+use std::path::Path;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
 const LED_PIN: u8 = 17;
 const BTN_PIN: u8 = 18;
 
-fn main() -> Result<(), Box<dyn Error>> {
-    println!("Program is starting...");
-    let gpio = Gpio::new()?;
+// --dimmable: 1回の押下(またはリピート)で上がる明るさ(%)とPWM周波数
+const DIM_STEP_PERCENT: u8 = 20;
+const PWM_FREQUENCY: f64 = 1000.0;
+// 押し続けたときのリピート開始までの時間と間隔
+const DEFAULT_REPEAT_DELAY_MS: u64 = 500;
+const DEFAULT_REPEAT_MS: u64 = 200;
+// Ctrl+Cとリピートを確認する間隔
+const POLL_TIMEOUT: Duration = Duration::from_millis(10);
+
+/// 次の明るさ。100%の次は消灯に戻る
+fn next_level(percent: u8) -> u8 {
+    if percent >= 100 { 0 } else { (percent + DIM_STEP_PERCENT).min(100) }
+}
+
+const OPTIONS: &[Opt] = &[
+    Opt::Flag("--no-color"),
+    Opt::Flag("--beep"),
+    Opt::Value("--buzzer-pin"),
+    Opt::Flag("--keep-on-exit"),
+    Opt::Flag("--dimmable"),
+    Opt::Value("--repeat-delay-ms"),
+    Opt::Value("--repeat-ms"),
+    Opt::Flag("--measure-latency"),
+    Opt::Value("--record"),
+    Opt::Value("--replay"),
+    Opt::Value("--debounce-algo"),
+    Opt::Value("--lock-file"),
+];
+
+fn main() {
+    error::run(try_main);
+}
+
+fn try_main() -> Result<(), Box<dyn Error>> {
+    let args = Args::from_env()?.with_version(env!("CARGO_PKG_VERSION")).with_options(OPTIONS);
+    let no_color = args.flag("--no-color");
+    let beep = args.flag("--beep");
+    let buzzer_pin: Option<u8> = args.value("--buzzer-pin")?;
+    // 終了時にLEDを消さずに現在の状態のまま残す
+    let cleanup_policy = CleanupPolicy::from_keep_flag(args.flag("--keep-on-exit"));
+    // 押すたびに明るさを段階的に上げる調光モード
+    let dimmable = args.flag("--dimmable");
+    let repeat_delay = Duration::from_millis(args.value_or("--repeat-delay-ms", DEFAULT_REPEAT_DELAY_MS)?);
+    let repeat_interval = Duration::from_millis(args.value_or("--repeat-ms", DEFAULT_REPEAT_MS)?);
+    // ボタンのエッジ検出からLEDが切り替わるまでの時間を計測し、終了時に表示する
+    let measure_latency = args.flag("--measure-latency");
+    // ボタンの生のレベル変化をファイルに記録する / 記録したファイルをハードウェアなしで再生する
+    let record: Option<String> = args.value("--record")?;
+    let replay_path: Option<String> = args.value("--replay")?;
+    // チャタリングの多いスイッチには integrator を指定する
+    let debounce_algo: DebounceStrategy = args.value_or("--debounce-algo", DebounceStrategy::default())?;
+    let lock_file: Option<String> = args.value("--lock-file")?;
+    args.finish();
+    if beep && buzzer_pin.is_none() {
+        return Err("--beep needs --buzzer-pin".into());
+    }
+    if measure_latency && dimmable {
+        return Err("--measure-latency cannot be combined with --dimmable".into());
+    }
+    if dimmable && (record.is_some() || replay_path.is_some()) {
+        return Err("--record and --replay cannot be combined with --dimmable".into());
+    }
+    args.exit_if_dry_run();
+    if let Some(path) = &replay_path {
+        return run_replay(Path::new(path), debounce_algo);
+    }
+    let _lock = lock::acquire(lock_file.as_deref())?;
+    style::init(no_color);
+
+    style::banner("Program is starting...");
+    let gpio = open_gpio()?;
     let mut led_pin = gpio.get(LED_PIN)?.into_output();
-    let mut btn_pin = gpio.get(BTN_PIN)?.into_input();
+    let mut button = Button::new(gpio.get(BTN_PIN)?.into_input());
     led_pin.set_low();
-    // ボタンの割り込み設定
-    btn_pin.set_interrupt(Trigger::FallingEdge, None);
+    // 押下を確認するブザー (--beep)
+    let mut buzzer = match buzzer_pin {
+        Some(pin) if beep => Some(Buzzer::new(gpio.get(pin)?.into_output_low())),
+        _ => None,
+    };
     // Ctrl+Cが押されたら終了
     let running = Arc::new(AtomicBool::new(true));
+    install_panic_hook(running.clone());
     let r = running.clone();
-    ctrlc::set_handler(move || {
+    shutdown::set_handler(move || {
         r.store(false, Ordering::SeqCst);
     })?;
-    println!("Waiting for button press...");
-    while running.load(Ordering::SeqCst) {
-        if let Some(_) = btn_pin.poll_interrupt(true, Some(Duration::from_millis(1)))? {
+    style::banner("Waiting for button press...");
+    if dimmable {
+        let repeat = AutoRepeat::new(repeat_delay, repeat_interval);
+        let level = run_dimmer(&mut button, &mut led_pin, &running, repeat, debounce_algo, buzzer.as_mut())?;
+        // ソフトウェアPWMはプロセスと共に止まるため、状態を残す場合は点灯/消灯で残す
+        led_pin.clear_pwm()?;
+        led_pin.write(Level::from(level > 0));
+        style::banner("Program is finished.");
+        cleanup(&mut button, &mut led_pin, cleanup_policy);
+        return Ok(());
+    }
+    let mut latency = measure_latency.then(LatencyStats::new);
+    let mut events = button.events(&running)?.debounce_strategy(debounce_algo);
+    if let Some(path) = &record {
+        events = events.record_to(Recorder::create(Path::new(path), Instant::now())?);
+        println!("Recording button levels to {}", path);
+    }
+    for event in events {
+        let edge = Instant::now();
+        // 押されたときだけ反応する
+        if event? == ButtonEvent::Pressed {
             // LEDの状態をトグル
-            if led_pin.is_set_low() {
-                led_pin.set_high();
-                println!("Led turned on >>>");
+            let turn_on = led_pin.is_set_low();
+            led_pin.write(Level::from(turn_on));
+            if let Some(latency) = latency.as_mut() {
+                latency.record(edge.elapsed());
+            }
+            // ブザーは鳴っている間ブロックするため、LEDを切り替えてから鳴らす
+            if let Some(buzzer) = buzzer.as_mut() {
+                buzzer.beep(buzzer::DEFAULT_BEEP);
+            }
+            if turn_on {
+                println!("{}", style::on("Led turned on >>>"));
             } else {
-                led_pin.set_low();
-                println!("Led turned off <<<");
+                println!("{}", style::off("Led turned off <<<"));
             }
         }
     }
-    println!("Program is finished.");
-    btn_pin.clear_interrupt();
-    led_pin.set_low();
+    if let Some(latency) = &latency {
+        println!("Edge-to-LED latency: {}", latency.summary());
+    }
+    style::banner("Program is finished.");
+    cleanup(&mut button, &mut led_pin, cleanup_policy);
+    Ok(())
+}
+
+/// 記録したボタンのレベル変化をデバウンス処理に通し、実機と同じ
+/// トグル動作でLEDがどう切り替わったかを表示する
+fn run_replay(path: &Path, strategy: DebounceStrategy) -> Result<(), Box<dyn Error>> {
+    let transitions = replay::load(path)?;
+    println!("Replaying {} transitions from {}", transitions.len(), path.display());
+    let events = replay::replay(
+        &transitions,
+        ButtonLogic::default(),
+        DEFAULT_DEBOUNCE,
+        strategy,
+        button::EVENT_POLL_TIMEOUT,
+    );
+    let mut led_on = false;
+    for (at, event) in events {
+        let ms = at.as_secs_f64() * 1000.0;
+        if event == ButtonEvent::Pressed {
+            led_on = !led_on;
+            let text = if led_on { "Led turned on >>>" } else { "Led turned off <<<" };
+            println!("{:>10.3} ms {:?}: {}", ms, event, text);
+        } else {
+            println!("{:>10.3} ms {:?}", ms, event);
+        }
+    }
     Ok(())
 }
+
+/// 調光モードのループ。押した瞬間に1段階、押し続けると`repeat`の
+/// タイミングでさらに段階を進める。終了時の明るさ(%)を返す
+fn run_dimmer(
+    button: &mut Button,
+    led_pin: &mut OutputPin,
+    running: &AtomicBool,
+    mut repeat: AutoRepeat,
+    strategy: DebounceStrategy,
+    mut buzzer: Option<&mut Buzzer>,
+) -> Result<u8, Box<dyn Error>> {
+    setup_button_interrupt(button.pin_mut())?;
+    let mut debouncer = Debouncer::new(DEFAULT_DEBOUNCE).with_strategy(strategy);
+    let mut pressed_at: Option<Instant> = None;
+    let mut level = 0;
+    while running.load(Ordering::SeqCst) {
+        wait_for_edge(button.pin_mut(), POLL_TIMEOUT)?;
+        let now = Instant::now();
+        let event = debouncer.update(button.is_pressed(), now);
+        match event {
+            Some(ButtonEvent::Pressed) => pressed_at = Some(now),
+            Some(ButtonEvent::Released) => pressed_at = None,
+            None => {}
+        }
+        if repeat.update(pressed_at.map(|t| now.saturating_duration_since(t))) {
+            level = next_level(level);
+            led_pin.set_pwm_frequency(PWM_FREQUENCY, level as f64 / 100.0)?;
+            println!("Brightness {}%", level);
+        }
+        // 明るさを変えてから鳴らす
+        if event == Some(ButtonEvent::Pressed)
+            && let Some(buzzer) = buzzer.as_mut()
+        {
+            buzzer.beep(buzzer::DEFAULT_BEEP);
+        }
+    }
+    Ok(level)
+}
+
+/// 終了処理。割り込みは常に解除し、LEDは`policy`に従って消灯するか残す
+///
+/// 状態を残す場合は、ドロップ時にピンが元のモードへ戻されないようにする
+fn cleanup(button: &mut Button, led_pin: &mut OutputPin, policy: CleanupPolicy) {
+    let _ = button.pin_mut().clear_interrupt();
+    match policy {
+        CleanupPolicy::TurnOff => led_pin.set_low(),
+        CleanupPolicy::KeepState => led_pin.set_reset_on_drop(false),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn levels_step_up_then_wrap_to_off() {
+        let mut level = 0;
+        let mut seen = Vec::new();
+        for _ in 0..7 {
+            level = next_level(level);
+            seen.push(level);
+        }
+        assert_eq!(seen, [20, 40, 60, 80, 100, 0, 20]);
+    }
+
+    #[test]
+    fn odd_levels_stop_at_full() {
+        // 途中の値からでも100%を超えない
+        assert_eq!(next_level(90), 100);
+        assert_eq!(next_level(150), 0);
+    }
+}
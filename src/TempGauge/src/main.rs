@@ -0,0 +1,131 @@
+use adc::Adc;
+use adc::thermistor;
+use common::args::{Args, Opt};
+use common::color::{self, RgbCommon, hsv_to_rgb};
+use common::error::{self, AppError, open_gpio};
+use common::lock;
+use common::shutdown::{self, install_panic_hook};
+use common::style;
+use common::throttle::Throttle;
+use std::error::Error;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+// Same RGB LED wiring as the RGBLED lesson
+const RED_PIN: u8 = 17;
+const GREEN_PIN: u8 = 18;
+const BLUE_PIN: u8 = 27;
+// Thermometer lesson wiring
+const DEFAULT_CHANNEL: u8 = 0;
+// Room-temperature span so breathing on the thermistor visibly shifts the colour
+const DEFAULT_MIN_C: f64 = 15.0;
+const DEFAULT_MAX_C: f64 = 35.0;
+const COLD_HUE: f64 = 240.0;
+const HOT_HUE: f64 = 0.0;
+const LOOP_DELAY: Duration = Duration::from_millis(100);
+const LOG_EVERY: Duration = Duration::from_millis(1000);
+
+/// Maps a temperature to a hue from blue at `min_c` to red at `max_c`,
+/// passing through cyan, green and yellow. Temperatures outside the range
+/// are clamped to its ends.
+fn temperature_to_hue(celsius: f64, min_c: f64, max_c: f64) -> f64 {
+    let t = ((celsius - min_c) / (max_c - min_c)).clamp(0.0, 1.0);
+    COLD_HUE + (HOT_HUE - COLD_HUE) * t
+}
+
+const OPTIONS: &[Opt] = &[
+    Opt::Value("--channel"),
+    Opt::Value("--min-c"),
+    Opt::Value("--max-c"),
+    Opt::Value("--rgb-common"),
+    Opt::Value("--lock-file"),
+];
+
+fn main() {
+    error::run(try_main);
+}
+
+fn try_main() -> Result<(), Box<dyn Error>> {
+    let args = Args::from_env()?.with_version(env!("CARGO_PKG_VERSION")).with_options(OPTIONS);
+    let channel = args.value_or("--channel", DEFAULT_CHANNEL)?;
+    let min_c: f64 = args.value_or("--min-c", DEFAULT_MIN_C)?;
+    let max_c: f64 = args.value_or("--max-c", DEFAULT_MAX_C)?;
+    let rgb_common: RgbCommon = args.value_or("--rgb-common", RgbCommon::default())?;
+    let lock_file: Option<String> = args.value("--lock-file")?;
+    args.finish();
+    if min_c.is_nan() || max_c.is_nan() || min_c >= max_c {
+        return Err(format!("--min-c must be below --max-c, got {} and {}", min_c, max_c).into());
+    }
+    args.exit_if_dry_run();
+    let _lock = lock::acquire(lock_file.as_deref())?;
+
+    style::banner("Program is starting ...");
+    let mut adc = Adc::detect(&adc::DEFAULT_BUSES, adc::DEFAULT_RETRIES)
+        .map_err(|e| AppError::AdcNotFound(e.to_string()))?;
+    adc::check_channel(adc.chip(), channel)?;
+    println!("Detected I2C device: {} on bus {}", adc.chip().name(), adc.bus());
+    let full_scale = adc.chip().full_scale();
+
+    let gpio = open_gpio()?;
+    let mut pins = [
+        gpio.get(RED_PIN)?.into_output(),
+        gpio.get(GREEN_PIN)?.into_output(),
+        gpio.get(BLUE_PIN)?.into_output(),
+    ];
+    for pin in pins.iter_mut() {
+        pin.write(rgb_common.off_level());
+    }
+
+    let running = Arc::new(AtomicBool::new(true));
+    install_panic_hook(running.clone());
+    let r = running.clone();
+    shutdown::set_handler(move || {
+        style::banner("\nEnding program");
+        r.store(false, Ordering::SeqCst);
+    })?;
+
+    let mut log_throttle = Throttle::new(LOG_EVERY);
+    while running.load(Ordering::SeqCst) {
+        match adc.read(channel) {
+            Ok(raw) => match thermistor::raw_to_celsius(raw as u16, full_scale) {
+                Some(celsius) => {
+                    let hue = temperature_to_hue(celsius, min_c, max_c);
+                    color::apply_rgb(&mut pins, hsv_to_rgb(hue, 1.0, 1.0), rgb_common)?;
+                    if log_throttle.should_emit(Instant::now()) {
+                        println!("ADC Value: {}, Temperature: {:.2} C, hue: {:.0}", raw, celsius, hue);
+                    }
+                }
+                None => eprintln!("ADC Value {} is out of range; check the thermistor wiring", raw),
+            },
+            Err(e) => eprintln!("Error reading ADC: {}", e),
+        }
+        thread::sleep(LOOP_DELAY);
+    }
+
+    color::clear_rgb(&mut pins, rgb_common)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn range_ends_are_blue_and_red() {
+        assert_eq!(temperature_to_hue(DEFAULT_MIN_C, DEFAULT_MIN_C, DEFAULT_MAX_C), COLD_HUE);
+        assert_eq!(temperature_to_hue(DEFAULT_MAX_C, DEFAULT_MIN_C, DEFAULT_MAX_C), HOT_HUE);
+    }
+
+    #[test]
+    fn midpoint_is_green() {
+        assert_eq!(temperature_to_hue(25.0, 15.0, 35.0), 120.0);
+    }
+
+    #[test]
+    fn out_of_range_temperatures_are_clamped() {
+        assert_eq!(temperature_to_hue(-40.0, 15.0, 35.0), COLD_HUE);
+        assert_eq!(temperature_to_hue(80.0, 15.0, 35.0), HOT_HUE);
+    }
+}
@@ -0,0 +1,682 @@
+//! Detection and reading of the kit's I2C ADC module (PCF8591 or ADS7830).
+
+pub mod lut;
+pub mod ready;
+pub mod thermistor;
+
+use std::error::Error;
+use std::fmt;
+use std::thread;
+use std::time::Duration;
+
+use rppal::i2c::{self, I2c};
+
+pub const PCF8591_ADDR: u16 = 0x48;
+pub const ADS7830_ADDR: u16 = 0x4b;
+
+/// Buses checked by default: 1 on most Pis, 13/14 on the Pi 5 RP1 headers.
+pub const DEFAULT_BUSES: [u8; 3] = [1, 13, 14];
+pub const DEFAULT_RETRIES: u32 = 3;
+/// Throwaway reads before each PCF8591 sample; see [`Adc::set_pcf_dummy_reads`].
+pub const DEFAULT_PCF_DUMMY_READS: u32 = 1;
+const RETRY_DELAY: Duration = Duration::from_millis(50);
+/// Reference voltage of the kit's ADC module when powered from the Pi's 3.3V rail.
+pub const DEFAULT_VREF: f64 = 3.3;
+/// Supply range the PCF8591 and ADS7830 are specified for.
+pub const PLAUSIBLE_VREF: std::ops::RangeInclusive<f64> = 1.0..=5.5;
+/// Addresses the PCF8591 and ADS7830 can be strapped to.
+pub const SCAN_ADDRESSES: std::ops::RangeInclusive<u16> = 0x48..=0x4f;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Chip {
+    Pcf8591,
+    Ads7830,
+}
+
+impl Chip {
+    pub fn address(self) -> u16 {
+        match self {
+            Chip::Pcf8591 => PCF8591_ADDR,
+            Chip::Ads7830 => ADS7830_ADDR,
+        }
+    }
+
+    /// Number of single-ended input channels.
+    pub fn channel_count(self) -> u8 {
+        match self {
+            Chip::Pcf8591 => 4,
+            Chip::Ads7830 => 8,
+        }
+    }
+
+    /// Resolution of one conversion.
+    pub fn bits(self) -> u8 {
+        match self {
+            Chip::Pcf8591 | Chip::Ads7830 => 8,
+        }
+    }
+
+    /// Largest raw reading, i.e. the one that corresponds to `vref`.
+    pub fn full_scale(self) -> u16 {
+        (1u32 << self.bits()) as u16 - 1
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Chip::Pcf8591 => "PCF8591",
+            Chip::Ads7830 => "ADS7830",
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum AdcError {
+    /// Neither chip answered on any of the probed buses.
+    NotFound { buses: Vec<u8> },
+    /// `ch` is not an input of the detected chip; valid channels are `0..=max`.
+    InvalidChannel { ch: u8, max: u8 },
+    /// The bus clock differs from the one asked for. Linux fixes the clock
+    /// at boot, so it cannot be changed from here.
+    ClockMismatch { requested: u32, actual: u32 },
+    I2c(i2c::Error),
+}
+
+impl fmt::Display for AdcError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AdcError::NotFound { buses } => write!(
+                f,
+                "no PCF8591 or ADS7830 found on I2C buses {:?}; check the wiring and that I2C is enabled",
+                buses
+            ),
+            AdcError::InvalidChannel { ch, max } => {
+                write!(f, "ADC channel {} out of range (0..={})", ch, max)
+            }
+            AdcError::ClockMismatch { requested, actual } => write!(
+                f,
+                "I2C bus runs at {} Hz, not the requested {} Hz; the clock is set at boot, \
+                 add 'dtparam=i2c_arm_baudrate={}' to /boot/firmware/config.txt and reboot",
+                actual, requested, requested
+            ),
+            AdcError::I2c(e) => write!(f, "I2C error: {}", e),
+        }
+    }
+}
+
+impl Error for AdcError {}
+
+impl From<i2c::Error> for AdcError {
+    fn from(e: i2c::Error) -> Self {
+        AdcError::I2c(e)
+    }
+}
+
+/// A detected ADC chip and the bus it lives on.
+pub struct Adc {
+    i2c: I2c,
+    chip: Chip,
+    bus: u8,
+    // Transaction timeout in ms, reapplied when the bus is reopened
+    timeout_ms: Option<u32>,
+    // Also reapplied on reopen
+    pcf_dummy_reads: u32,
+}
+
+impl Adc {
+    /// Probes `buses` in order, retrying each up to `retries` times, and
+    /// returns the first chip that answers.
+    pub fn detect(buses: &[u8], retries: u32) -> Result<Adc, AdcError> {
+        for &bus in buses {
+            let mut i2c = match I2c::with_bus(bus) {
+                Ok(i) => i,
+                Err(_) => continue,
+            };
+            for _ in 0..retries {
+                if let Some(chip) = probe(&mut i2c) {
+                    return Ok(Adc {
+                        i2c,
+                        chip,
+                        bus,
+                        timeout_ms: None,
+                        pcf_dummy_reads: DEFAULT_PCF_DUMMY_READS,
+                    });
+                }
+                thread::sleep(RETRY_DELAY);
+            }
+        }
+        Err(AdcError::NotFound {
+            buses: buses.to_vec(),
+        })
+    }
+
+    /// Drops the current bus handle and runs detection again on the same bus,
+    /// e.g. after the module was unplugged. On failure the old handle is kept
+    /// so the caller can try again later.
+    pub fn reopen(&mut self, retries: u32) -> Result<(), AdcError> {
+        let mut adc = Adc::detect(&[self.bus], retries)?;
+        if let Some(ms) = self.timeout_ms {
+            adc.set_timeout(ms)?;
+        }
+        adc.pcf_dummy_reads = self.pcf_dummy_reads;
+        *self = adc;
+        Ok(())
+    }
+
+    /// Makes transactions that take longer than `ms` fail with a timeout
+    /// error instead of blocking, so a hung bus reaches the caller's retry
+    /// logic. The kernel rounds to 10 ms. Kept across `reopen`.
+    pub fn set_timeout(&mut self, ms: u32) -> Result<(), AdcError> {
+        self.i2c.set_timeout(ms)?;
+        self.timeout_ms = Some(ms);
+        Ok(())
+    }
+
+    /// Sets how many bytes a PCF8591 read throws away before keeping one.
+    ///
+    /// The PCF8591 starts a conversion when a byte is read and returns the
+    /// result of the previous one, so the first byte after selecting a
+    /// channel still belongs to the old channel. One dummy read is enough
+    /// on most boards; raise it if readings lag one sample behind, which
+    /// happens on some boards and bus speeds. Has no effect on the ADS7830.
+    /// Kept across `reopen`.
+    pub fn set_pcf_dummy_reads(&mut self, reads: u32) {
+        self.pcf_dummy_reads = reads;
+    }
+
+    pub fn chip(&self) -> Chip {
+        self.chip
+    }
+
+    pub fn bus(&self) -> u8 {
+        self.bus
+    }
+
+    /// The bus clock in Hz as configured by the kernel.
+    pub fn clock_speed(&self) -> Result<u32, AdcError> {
+        Ok(self.i2c.clock_speed()?)
+    }
+
+    /// Confirms the bus runs at `requested` Hz and returns the speed.
+    pub fn check_clock(&self, requested: u32) -> Result<u32, AdcError> {
+        let actual = self.clock_speed()?;
+        if actual != requested {
+            return Err(AdcError::ClockMismatch { requested, actual });
+        }
+        Ok(actual)
+    }
+
+    /// Prints the bus clock, which helps when diagnosing read errors, after
+    /// checking it against `requested` (e.g. `--i2c-hz`) if given.
+    pub fn log_clock(&self, requested: Option<u32>) -> Result<(), AdcError> {
+        match requested {
+            Some(hz) => println!("I2C clock: {} Hz", self.check_clock(hz)?),
+            None => match self.clock_speed() {
+                Ok(hz) => println!("I2C clock: {} Hz", hz),
+                Err(e) => eprintln!("Warning: cannot read I2C clock: {}", e),
+            },
+        }
+        Ok(())
+    }
+
+    /// Reads one 8-bit sample from `channel`.
+    pub fn read(&mut self, channel: u8) -> Result<u8, AdcError> {
+        check_channel(self.chip, channel)?;
+        self.i2c.set_slave_address(self.chip.address())?;
+        read_sample(&mut self.i2c, self.chip, channel, self.pcf_dummy_reads)
+    }
+}
+
+/// An ADC as seen by chip-agnostic callers, which adapt to the reader's
+/// channel count and resolution instead of assuming a particular board.
+pub trait AnalogReader {
+    /// The chip behind the reader.
+    fn chip(&self) -> Chip;
+
+    /// Reads one sample from `channel`.
+    fn read(&mut self, channel: u8) -> Result<u8, AdcError>;
+
+    /// Number of input channels, e.g. 4 on a PCF8591 and 8 on an ADS7830.
+    fn channel_count(&self) -> u8 {
+        self.chip().channel_count()
+    }
+
+    /// Resolution of one sample.
+    fn resolution_bits(&self) -> u8 {
+        self.chip().bits()
+    }
+
+    /// Reads every channel, in channel order.
+    fn read_all(&mut self) -> Result<Vec<u8>, AdcError> {
+        (0..self.channel_count()).map(|channel| self.read(channel)).collect()
+    }
+}
+
+impl AnalogReader for Adc {
+    fn chip(&self) -> Chip {
+        self.chip
+    }
+
+    fn read(&mut self, channel: u8) -> Result<u8, AdcError> {
+        Adc::read(self, channel)
+    }
+}
+
+/// CSV header for rows of [`AnalogReader::read_all`]: one `chN` column per
+/// channel, suffixed with the sample width, e.g. `ch0_8bit,ch1_8bit,...`.
+pub fn csv_header(reader: &impl AnalogReader) -> String {
+    (0..reader.channel_count())
+        .map(|channel| format!("ch{}_{}bit", channel, reader.resolution_bits()))
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+/// The bus operations one sample needs, so the transaction in
+/// [`read_sample`] can run against something other than real hardware.
+pub trait Transport {
+    fn write(&mut self, bytes: &[u8]) -> Result<(), AdcError>;
+    fn read(&mut self, buf: &mut [u8]) -> Result<(), AdcError>;
+}
+
+impl Transport for I2c {
+    fn write(&mut self, bytes: &[u8]) -> Result<(), AdcError> {
+        I2c::write(self, bytes)?;
+        Ok(())
+    }
+
+    fn read(&mut self, buf: &mut [u8]) -> Result<(), AdcError> {
+        I2c::read(self, buf)?;
+        Ok(())
+    }
+}
+
+/// Selects `channel` on `chip` and reads one sample, throwing away
+/// `pcf_dummy_reads` bytes first on a PCF8591. The chip must already be
+/// addressed.
+pub fn read_sample(
+    bus: &mut impl Transport,
+    chip: Chip,
+    channel: u8,
+    pcf_dummy_reads: u32,
+) -> Result<u8, AdcError> {
+    let mut buf = [0u8; 1];
+    match chip {
+        Chip::Pcf8591 => {
+            // The analog output stays enabled as in the Freenove code; it
+            // keeps the internal oscillator running between reads
+            let control = Pcf8591Control::new().dac_enable(true).channel(channel);
+            bus.write(&[control.byte()])?;
+            // The PCF8591 returns the previous conversion first
+            for _ in 0..pcf_dummy_reads {
+                bus.read(&mut buf)?;
+            }
+            bus.read(&mut buf)?;
+        }
+        Chip::Ads7830 => {
+            bus.write(&[ads7830_command(channel)])?;
+            bus.read(&mut buf)?;
+        }
+    }
+    Ok(buf[0])
+}
+
+fn probe(i2c: &mut I2c) -> Option<Chip> {
+    [Chip::Pcf8591, Chip::Ads7830]
+        .into_iter()
+        .find(|chip| acks(i2c, chip.address()))
+}
+
+fn acks(i2c: &mut I2c, addr: u16) -> bool {
+    i2c.set_slave_address(addr).is_ok() && i2c.read(&mut [0]).is_ok()
+}
+
+/// Lists the `(bus, address)` pairs in [`SCAN_ADDRESSES`] that acknowledge
+/// a one-byte read, like `i2cdetect` limited to the ADC's address range.
+/// Buses that cannot be opened are skipped.
+pub fn scan(buses: &[u8]) -> Vec<(u8, u16)> {
+    let mut found = Vec::new();
+    for &bus in buses {
+        if let Ok(mut i2c) = I2c::with_bus(bus) {
+            found.extend(scan_with(&[bus], |_, addr| acks(&mut i2c, addr)));
+        }
+    }
+    found
+}
+
+/// The scan loop with the bus access supplied by `ack`, so it can run
+/// against something other than real hardware.
+pub fn scan_with(buses: &[u8], mut ack: impl FnMut(u8, u16) -> bool) -> Vec<(u8, u16)> {
+    buses
+        .iter()
+        .flat_map(|&bus| SCAN_ADDRESSES.map(move |addr| (bus, addr)))
+        .filter(|&(bus, addr)| ack(bus, addr))
+        .collect()
+}
+
+/// One line for the user, e.g. `bus 1: 0x48, 0x4c` or `no devices at 0x48-0x4f`.
+pub fn describe_scan(found: &[(u8, u16)]) -> String {
+    if found.is_empty() {
+        return format!(
+            "no devices at 0x{:02x}-0x{:02x}",
+            SCAN_ADDRESSES.start(),
+            SCAN_ADDRESSES.end()
+        );
+    }
+    found
+        .iter()
+        .map(|(bus, addr)| format!("bus {}: 0x{:02x}", bus, addr))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Drops the first readings after startup, which can be garbage while the
+/// supply and the ADC's sample-and-hold settle.
+#[derive(Clone, Copy, Debug)]
+pub struct Warmup {
+    remaining: u32,
+}
+
+impl Warmup {
+    /// Discards the next `samples` readings.
+    pub fn new(samples: u32) -> Self {
+        Self { remaining: samples }
+    }
+
+    /// Call once per successful reading; returns `false` while the reading
+    /// should be discarded.
+    pub fn accept(&mut self) -> bool {
+        if self.remaining == 0 {
+            return true;
+        }
+        self.remaining -= 1;
+        false
+    }
+}
+
+/// Order of the two bytes a wider-than-8-bit ADC sends per sample.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ByteOrder {
+    /// Most significant byte first, as most I2C ADCs send it.
+    BigEndian,
+    LittleEndian,
+}
+
+/// Combines the two bytes of a sample in the order they arrived on the bus.
+/// Both chips in the kit send a single byte, so this is for readers of
+/// 10/12-bit parts; shifting right-aligned data is left to the caller.
+pub fn assemble_sample(first: u8, second: u8, order: ByteOrder) -> u16 {
+    match order {
+        ByteOrder::BigEndian => u16::from_be_bytes([first, second]),
+        ByteOrder::LittleEndian => u16::from_le_bytes([first, second]),
+    }
+}
+
+/// Converts a reading to volts for a board referenced to `vref`, where
+/// `full_scale` is the chip's largest reading (see [`Chip::full_scale`]).
+pub fn to_voltage(raw: u16, full_scale: u16, vref: f64) -> f64 {
+    raw as f64 / full_scale as f64 * vref
+}
+
+/// Rejects channels the chip does not have instead of silently reading another.
+pub fn check_channel(chip: Chip, channel: u8) -> Result<(), AdcError> {
+    let max = chip.channel_count() - 1;
+    if channel > max {
+        return Err(AdcError::InvalidChannel { ch: channel, max });
+    }
+    Ok(())
+}
+
+/// Display names for the `channels` being read, in the same order.
+///
+/// Each entry of `labels` names the channel at the same position; channels
+/// without a label, or with an empty one, fall back to `chN`. More labels
+/// than channels is an error.
+pub fn resolve_labels(labels: &[String], channels: &[u8]) -> Result<Vec<String>, String> {
+    if labels.len() > channels.len() {
+        return Err(format!(
+            "--labels has {} names but only {} channels are read",
+            labels.len(),
+            channels.len()
+        ));
+    }
+    Ok(channels
+        .iter()
+        .enumerate()
+        .map(|(i, ch)| match labels.get(i) {
+            Some(label) if !label.is_empty() => label.clone(),
+            _ => format!("ch{}", ch),
+        })
+        .collect())
+}
+
+/// PCF8591 control byte (datasheet section 8.2), with the inputs always
+/// configured as four single-ended channels.
+///
+/// ```text
+/// bit 7    0
+/// bit 6    analog output enable
+/// bit 5-4  input programming (00 = four single-ended inputs)
+/// bit 3    0
+/// bit 2    auto-increment
+/// bit 1-0  channel
+/// ```
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Pcf8591Control {
+    channel: u8,
+    auto_increment: bool,
+    dac_enable: bool,
+}
+
+impl Pcf8591Control {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Selects input `channel`; only the low two bits are used, so validate
+    /// it with [`check_channel`] first.
+    pub fn channel(self, channel: u8) -> Self {
+        Self { channel, ..self }
+    }
+
+    /// Advances the channel after every conversion.
+    pub fn auto_increment(self, auto_increment: bool) -> Self {
+        Self { auto_increment, ..self }
+    }
+
+    /// Enables the analog output (DAC).
+    pub fn dac_enable(self, dac_enable: bool) -> Self {
+        Self { dac_enable, ..self }
+    }
+
+    pub fn byte(self) -> u8 {
+        let mut byte = self.channel & 0x03;
+        if self.auto_increment {
+            byte |= 0x04;
+        }
+        if self.dac_enable {
+            byte |= 0x40;
+        }
+        byte
+    }
+}
+
+/// Single-ended command byte: SD=1, channel select, internal reference on.
+/// `channel` must already be validated.
+fn ads7830_command(channel: u8) -> u8 {
+    match channel {
+        0 => 0x84,
+        1 => 0xc4,
+        2 => 0x94,
+        3 => 0xd4,
+        4 => 0xa4,
+        5 => 0xe4,
+        6 => 0xb4,
+        _ => 0xf4,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn chip_geometry() {
+        assert_eq!(Chip::Pcf8591.channel_count(), 4);
+        assert_eq!(Chip::Ads7830.channel_count(), 8);
+        assert_eq!(Chip::Pcf8591.full_scale(), 255);
+        assert_eq!(Chip::Ads7830.full_scale(), 255);
+        assert_eq!(Chip::Pcf8591.address(), PCF8591_ADDR);
+        assert_eq!(Chip::Ads7830.address(), ADS7830_ADDR);
+    }
+
+    #[test]
+    fn channels_beyond_the_chip_are_rejected() {
+        assert!(check_channel(Chip::Pcf8591, 3).is_ok());
+        assert!(matches!(
+            check_channel(Chip::Pcf8591, 4),
+            Err(AdcError::InvalidChannel { ch: 4, max: 3 })
+        ));
+        assert!(check_channel(Chip::Ads7830, 7).is_ok());
+        assert!(check_channel(Chip::Ads7830, 8).is_err());
+    }
+
+    #[test]
+    fn voltage_scales_to_vref() {
+        assert_eq!(to_voltage(0, 255, 3.3), 0.0);
+        assert_eq!(to_voltage(255, 255, 3.3), 3.3);
+        assert!((to_voltage(51, 255, 5.0) - 1.0).abs() < 1e-12);
+        let ads = Chip::Ads7830.full_scale();
+        assert_eq!(to_voltage(ads, ads, 3.3), 3.3);
+    }
+
+    #[test]
+    fn scan_reports_acking_addresses() {
+        let found = scan_with(&[1, 13], |bus, addr| bus == 1 && (addr == 0x48 || addr == 0x4c));
+        assert_eq!(found, [(1, 0x48), (1, 0x4c)]);
+        assert_eq!(describe_scan(&found), "bus 1: 0x48, bus 1: 0x4c");
+        assert_eq!(describe_scan(&[]), "no devices at 0x48-0x4f");
+    }
+
+    #[test]
+    fn warmup_discards_the_first_readings() {
+        let mut warmup = Warmup::new(2);
+        assert_eq!([warmup.accept(), warmup.accept(), warmup.accept()], [false, false, true]);
+        assert!(Warmup::new(0).accept());
+    }
+
+    #[test]
+    fn sample_bytes_follow_the_order() {
+        assert_eq!(assemble_sample(0x12, 0x34, ByteOrder::BigEndian), 0x1234);
+        assert_eq!(assemble_sample(0x12, 0x34, ByteOrder::LittleEndian), 0x3412);
+    }
+
+    #[test]
+    fn labels_fall_back_to_channel_names() {
+        let labels = vec!["pot".to_string(), String::new()];
+        assert_eq!(resolve_labels(&labels, &[0, 1, 2]).unwrap(), ["pot", "ch1", "ch2"]);
+        assert!(resolve_labels(&labels, &[0]).is_err());
+    }
+
+    #[test]
+    fn pcf8591_control_bits() {
+        assert_eq!(Pcf8591Control::new().byte(), 0x00);
+        assert_eq!(Pcf8591Control::new().channel(2).auto_increment(true).byte(), 0x06);
+        assert_eq!(Pcf8591Control::new().channel(3).dac_enable(true).byte(), 0x43);
+        // Only the low two bits select the channel
+        assert_eq!(Pcf8591Control::new().channel(5).byte(), 0x01);
+    }
+
+    #[test]
+    fn ads7830_commands_are_single_ended_and_distinct() {
+        let commands: Vec<u8> = (0..8).map(ads7830_command).collect();
+        assert_eq!(commands[0], 0x84);
+        assert_eq!(commands[1], 0xc4);
+        assert!(commands.iter().all(|c| c & 0x80 != 0));
+        let mut unique = commands.clone();
+        unique.sort();
+        unique.dedup();
+        assert_eq!(unique.len(), 8);
+    }
+
+    /// Answers each read with the number of reads before it.
+    #[derive(Default)]
+    struct CountingBus {
+        written: Vec<u8>,
+        reads: u8,
+    }
+
+    impl Transport for CountingBus {
+        fn write(&mut self, bytes: &[u8]) -> Result<(), AdcError> {
+            self.written.extend_from_slice(bytes);
+            Ok(())
+        }
+
+        fn read(&mut self, buf: &mut [u8]) -> Result<(), AdcError> {
+            buf[0] = self.reads;
+            self.reads += 1;
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn pcf8591_discards_the_configured_reads() {
+        for dummies in [0, 1, 3] {
+            let mut bus = CountingBus::default();
+            let sample = read_sample(&mut bus, Chip::Pcf8591, 2, dummies).unwrap();
+            assert_eq!(bus.reads as u32, dummies + 1);
+            assert_eq!(sample as u32, dummies);
+            assert_eq!(bus.written, [Pcf8591Control::new().dac_enable(true).channel(2).byte()]);
+        }
+    }
+
+    #[test]
+    fn ads7830_ignores_the_dummy_reads() {
+        let mut bus = CountingBus::default();
+        assert_eq!(read_sample(&mut bus, Chip::Ads7830, 5, 3).unwrap(), 0);
+        assert_eq!(bus.reads, 1);
+        assert_eq!(bus.written, [ads7830_command(5)]);
+    }
+
+    /// A reader over [`CountingBus`], so the trait's provided methods run
+    /// against the same transaction as a real [`Adc`].
+    struct FakeReader {
+        chip: Chip,
+        bus: CountingBus,
+    }
+
+    impl AnalogReader for FakeReader {
+        fn chip(&self) -> Chip {
+            self.chip
+        }
+
+        fn read(&mut self, channel: u8) -> Result<u8, AdcError> {
+            check_channel(self.chip, channel)?;
+            read_sample(&mut self.bus, self.chip, channel, 0)
+        }
+    }
+
+    fn reader(chip: Chip) -> FakeReader {
+        FakeReader { chip, bus: CountingBus::default() }
+    }
+
+    #[test]
+    fn readers_describe_their_chip() {
+        let pcf = reader(Chip::Pcf8591);
+        assert_eq!((pcf.channel_count(), pcf.resolution_bits()), (4, 8));
+        let ads = reader(Chip::Ads7830);
+        assert_eq!((ads.channel_count(), ads.resolution_bits()), (8, 8));
+    }
+
+    #[test]
+    fn read_all_covers_every_channel_in_order() {
+        let mut ads = reader(Chip::Ads7830);
+        assert_eq!(ads.read_all().unwrap(), [0, 1, 2, 3, 4, 5, 6, 7]);
+        let commands: Vec<u8> = (0..8).map(ads7830_command).collect();
+        assert_eq!(ads.bus.written, commands);
+    }
+
+    #[test]
+    fn csv_header_names_each_channel() {
+        assert_eq!(csv_header(&reader(Chip::Pcf8591)), "ch0_8bit,ch1_8bit,ch2_8bit,ch3_8bit");
+        assert_eq!(csv_header(&reader(Chip::Ads7830)).split(',').count(), 8);
+    }
+}
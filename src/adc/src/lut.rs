@@ -0,0 +1,112 @@
+//! Piecewise-linear lookup tables for sensors with a nonlinear response.
+
+use std::fs;
+use std::path::Path;
+
+/// A sorted table of `(raw, value)` points. Readings between two points are
+/// interpolated linearly; readings outside the table are clamped to its
+/// first or last value.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Lut {
+    points: Vec<(f64, f64)>,
+}
+
+impl Lut {
+    /// Builds a table from points with strictly increasing raw values; at
+    /// least two are needed.
+    pub fn new(points: Vec<(f64, f64)>) -> Result<Self, String> {
+        if points.len() < 2 {
+            return Err(format!("a lookup table needs at least 2 points, got {}", points.len()));
+        }
+        if let Some(pair) = points.windows(2).find(|pair| pair[1].0 <= pair[0].0) {
+            return Err(format!(
+                "lookup table raw values must increase, but {} is followed by {}",
+                pair[0].0, pair[1].0
+            ));
+        }
+        Ok(Self { points })
+    }
+
+    /// Parses one `raw value` pair per line, separated by whitespace or a
+    /// comma. Blank lines and `#` comments are skipped.
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut points = Vec::new();
+        for (number, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let point = parse_point(line)
+                .ok_or_else(|| format!("line {}: expected 'raw value', got '{}'", number + 1, line))?;
+            points.push(point);
+        }
+        Self::new(points)
+    }
+
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let text = fs::read_to_string(path).map_err(|e| format!("cannot read {}: {}", path.display(), e))?;
+        Self::parse(&text).map_err(|e| format!("{}: {}", path.display(), e))
+    }
+
+    /// The value for `raw`: exact on a table point, linear in between, and
+    /// clamped beyond either end.
+    pub fn interpolate(&self, raw: f64) -> f64 {
+        let (first, last) = (self.points[0], self.points[self.points.len() - 1]);
+        if raw <= first.0 {
+            return first.1;
+        }
+        if raw >= last.0 {
+            return last.1;
+        }
+        // First point strictly above `raw`; the loop above guarantees one
+        // exists and that it is not the first
+        let upper = self.points.partition_point(|&(x, _)| x <= raw);
+        let (x0, y0) = self.points[upper - 1];
+        let (x1, y1) = self.points[upper];
+        y0 + (raw - x0) / (x1 - x0) * (y1 - y0)
+    }
+}
+
+fn parse_point(line: &str) -> Option<(f64, f64)> {
+    let mut parts = line.split(|c: char| c == ',' || c.is_whitespace()).filter(|p| !p.is_empty());
+    let raw = parts.next()?.parse().ok()?;
+    let value = parts.next()?.parse().ok()?;
+    if parts.next().is_some() {
+        return None;
+    }
+    Some((raw, value))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn interpolates_between_points_and_clamps_outside() {
+        let lut = Lut::new(vec![(0.0, 10.0), (100.0, 20.0), (200.0, 60.0)]).unwrap();
+        assert_eq!(lut.interpolate(100.0), 20.0);
+        assert_eq!(lut.interpolate(50.0), 15.0);
+        assert_eq!(lut.interpolate(150.0), 40.0);
+        assert_eq!(lut.interpolate(-5.0), 10.0);
+        assert_eq!(lut.interpolate(255.0), 60.0);
+    }
+
+    #[test]
+    fn needs_two_increasing_points() {
+        assert!(Lut::new(vec![(0.0, 1.0)]).is_err());
+        assert!(Lut::new(vec![(0.0, 1.0), (0.0, 2.0)]).is_err());
+        assert!(Lut::new(vec![(10.0, 1.0), (5.0, 2.0)]).is_err());
+    }
+
+    #[test]
+    fn parses_commas_whitespace_and_comments() {
+        let lut = Lut::parse("# raw value\n0, 1.5\n\n255 3.0\n").unwrap();
+        assert_eq!(lut, Lut::new(vec![(0.0, 1.5), (255.0, 3.0)]).unwrap());
+    }
+
+    #[test]
+    fn parse_errors_name_the_line() {
+        assert_eq!(Lut::parse("0 1\nabc\n").unwrap_err(), "line 2: expected 'raw value', got 'abc'");
+        assert!(Lut::parse("0 1 2\n255 3").is_err());
+    }
+}
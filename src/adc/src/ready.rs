@@ -0,0 +1,73 @@
+//! Waiting on an ADC board's end-of-conversion ("ready") line instead of
+//! polling on a fixed period.
+
+use std::time::Duration;
+
+use rppal::gpio::{self, InputPin, Trigger};
+
+/// How long to wait for the ready line before reporting a timeout.
+pub const DEFAULT_READY_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Result of one wait on the ready line.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ReadyWait {
+    /// The board signalled a new conversion.
+    Ready,
+    /// Nothing arrived within the timeout, e.g. because the line is not
+    /// wired or the board stopped converting.
+    TimedOut,
+}
+
+/// A GPIO input wired to the board's ready line. The line is taken to go
+/// low when a conversion completes, as the usual open-drain /DRDY outputs do.
+pub struct ReadyPin {
+    pin: InputPin,
+}
+
+impl ReadyPin {
+    /// Enables falling-edge interrupts on `pin`.
+    pub fn new(mut pin: InputPin) -> gpio::Result<Self> {
+        pin.set_interrupt(Trigger::FallingEdge, None)?;
+        Ok(Self { pin })
+    }
+
+    /// Blocks until the next conversion is signalled or `timeout` passes.
+    pub fn wait(&mut self, timeout: Duration) -> gpio::Result<ReadyWait> {
+        Ok(match self.pin.poll_interrupt(true, Some(timeout))? {
+            Some(_) => ReadyWait::Ready,
+            None => ReadyWait::TimedOut,
+        })
+    }
+}
+
+/// Runs `read` only if `wait` reported a new conversion, so a timeout never
+/// costs a bus transaction that would just return the previous sample.
+pub fn read_when_ready<T, E>(
+    wait: impl FnOnce() -> Result<ReadyWait, E>,
+    read: impl FnOnce() -> T,
+) -> Result<Option<T>, E> {
+    Ok(match wait()? {
+        ReadyWait::Ready => Some(read()),
+        ReadyWait::TimedOut => None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_only_after_a_conversion() {
+        assert_eq!(read_when_ready(|| Ok::<_, ()>(ReadyWait::Ready), || 42), Ok(Some(42)));
+
+        let mut read = false;
+        let result = read_when_ready(|| Ok::<_, ()>(ReadyWait::TimedOut), || read = true);
+        assert_eq!(result, Ok(None));
+        assert!(!read);
+    }
+
+    #[test]
+    fn wait_errors_are_passed_on() {
+        assert_eq!(read_when_ready(|| Err("no pin"), || 42), Err("no pin"));
+    }
+}
@@ -0,0 +1,54 @@
+//! Temperature from the kit's NTC thermistor, wired as in the Thermometer
+//! lesson: a 10k resistor from 3.3V to the ADC input and the thermistor from
+//! the input to GND.
+
+/// Thermistor resistance at 25 °C, in ohms, equal to the fixed resistor.
+pub const NOMINAL_OHMS: f64 = 10_000.0;
+/// Temperature at which the thermistor measures `NOMINAL_OHMS`.
+pub const NOMINAL_CELSIUS: f64 = 25.0;
+/// B coefficient of the kit's thermistor.
+pub const B_COEFFICIENT: f64 = 3950.0;
+
+const KELVIN_OFFSET: f64 = 273.15;
+
+/// Converts a raw reading on a `full_scale` ADC to degrees Celsius with the
+/// B-parameter equation used by the Freenove lesson.
+///
+/// The supply voltage cancels out of the divider, so no `--vref` is needed.
+/// Returns `None` for a reading at either end of the scale, where the
+/// thermistor is shorted or disconnected and no temperature can be derived.
+pub fn raw_to_celsius(raw: u16, full_scale: u16) -> Option<f64> {
+    if raw == 0 || raw >= full_scale {
+        return None;
+    }
+    let ohms = NOMINAL_OHMS * raw as f64 / (full_scale - raw) as f64;
+    let inverse_kelvin =
+        1.0 / (NOMINAL_CELSIUS + KELVIN_OFFSET) + (ohms / NOMINAL_OHMS).ln() / B_COEFFICIENT;
+    Some(1.0 / inverse_kelvin - KELVIN_OFFSET)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn midscale_is_the_nominal_temperature() {
+        let celsius = raw_to_celsius(500, 1000).unwrap();
+        assert!((celsius - NOMINAL_CELSIUS).abs() < 1e-9);
+    }
+
+    #[test]
+    fn higher_readings_are_colder() {
+        // The thermistor is on the low side of the divider, so a larger
+        // reading means more resistance, which for an NTC means colder
+        let warm = raw_to_celsius(100, 255).unwrap();
+        let cold = raw_to_celsius(200, 255).unwrap();
+        assert!(warm > NOMINAL_CELSIUS && cold < NOMINAL_CELSIUS);
+    }
+
+    #[test]
+    fn ends_of_the_scale_have_no_temperature() {
+        assert_eq!(raw_to_celsius(0, 255), None);
+        assert_eq!(raw_to_celsius(255, 255), None);
+    }
+}
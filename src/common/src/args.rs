@@ -0,0 +1,474 @@
+use std::cell::RefCell;
+use std::env;
+use std::error::Error;
+use std::fmt;
+use std::path::Path;
+use std::process;
+use std::str::FromStr;
+
+use crate::config::{self, AppConfig, ConfigError};
+use crate::error::EXIT_CONFIG;
+use crate::style;
+
+/// Minimal command line option lookup.
+///
+/// Options are written as `--name value` or `--name=value`; flags are a bare
+/// `--name`. Values missing from the command line are taken from the
+/// `--config` file, if one was given.
+///
+/// Programs declare the options they accept with [`Args::with_options`],
+/// so [`Args::finish`] can print a usage line for `--help` and reject
+/// anything else, including options a run happens not to read. Every
+/// option actually looked up is also remembered, to echo the resolved
+/// values for `--dry-run` and `--dump-config`.
+pub struct Args {
+    program: String,
+    version: &'static str,
+    args: Vec<String>,
+    config: AppConfig,
+    // Declared options; without a declaration the looked-up ones count
+    options: Option<&'static [Opt]>,
+    // Options the program asked about, in lookup order, and whether each
+    // takes a value; `--dry-run` is handled here for every program
+    seen: RefCell<Vec<(String, bool)>>,
+    // Defaults passed to `value_or`, rendered with `Display`
+    defaults: RefCell<Vec<(String, String)>>,
+}
+
+/// An option a program accepts, see [`Args::with_options`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Opt {
+    /// A bare `--name`.
+    Flag(&'static str),
+    /// `--name value` or `--name=value`, including comma-separated lists.
+    Value(&'static str),
+}
+
+impl Opt {
+    pub fn name(self) -> &'static str {
+        match self {
+            Opt::Flag(name) | Opt::Value(name) => name,
+        }
+    }
+
+    pub fn takes_value(self) -> bool {
+        matches!(self, Opt::Value(_))
+    }
+}
+
+// Options every program accepts, handled by `Args` itself
+const BUILTIN_OPTIONS: &[Opt] = &[
+    Opt::Flag("--help"),
+    Opt::Flag("--version"),
+    Opt::Flag("--dry-run"),
+    Opt::Flag("--dump-config"),
+    Opt::Flag("--quiet"),
+    Opt::Value("--config"),
+];
+
+#[derive(Debug)]
+pub enum ArgError {
+    MissingValue(String),
+    InvalidValue { name: String, value: String },
+    Unknown { name: String, usage: String },
+}
+
+impl fmt::Display for ArgError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ArgError::MissingValue(name) => write!(f, "option {} requires a value", name),
+            ArgError::InvalidValue { name, value } => {
+                write!(f, "invalid value '{}' for option {}", value, name)
+            }
+            ArgError::Unknown { name, usage } => write!(f, "unknown option {}\n\n{}", name, usage),
+        }
+    }
+}
+
+impl Error for ArgError {}
+
+impl Args {
+    /// Reads the process arguments and loads `--config <path.toml>` if given.
+    pub fn from_env() -> Result<Self, ConfigError> {
+        let mut args = env::args();
+        let program = args
+            .next()
+            .as_deref()
+            .and_then(|p| Path::new(p).file_name())
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        Self::from_vec(program, args.collect())
+    }
+
+    pub fn from_vec(program: String, args: Vec<String>) -> Result<Self, ConfigError> {
+        let mut parsed = Self {
+            program,
+            version: "unknown",
+            args,
+            config: AppConfig::default(),
+            options: None,
+            seen: RefCell::new(Vec::new()),
+            defaults: RefCell::new(Vec::new()),
+        };
+        if let Ok(Some(path)) = parsed.cli_raw("--config") {
+            parsed.config = AppConfig::load(Path::new(&path))?;
+        }
+        Ok(parsed)
+    }
+
+    /// Re-reads the `--config` file, e.g. on SIGHUP. Options looked up
+    /// afterwards see the new values, though the command line still wins.
+    /// Returns `false` if the program was started without `--config`.
+    pub fn reload(&mut self) -> Result<bool, ConfigError> {
+        match self.cli_raw("--config") {
+            Ok(Some(path)) => {
+                self.config = AppConfig::load(Path::new(&path))?;
+                Ok(true)
+            }
+            _ => Ok(false),
+        }
+    }
+
+    /// Declares every option the program accepts. Command line options and
+    /// config file keys outside this list are reported by [`Args::finish`],
+    /// and looking up an undeclared option is a bug caught in debug builds.
+    pub fn with_options(mut self, options: &'static [Opt]) -> Self {
+        self.options = Some(options);
+        self
+    }
+
+    /// Sets the version printed by `--version`; pass
+    /// `env!("CARGO_PKG_VERSION")` so it is the example's own version.
+    pub fn with_version(mut self, version: &'static str) -> Self {
+        self.version = version;
+        self
+    }
+
+    /// Returns `true` if the bare flag `name` is present, or set to `true`
+    /// in the config file.
+    pub fn flag(&self, name: &str) -> bool {
+        self.mark_seen(name, false);
+        self.flag_set(name)
+    }
+
+    /// Returns the raw string given for option `name`, if present.
+    pub fn raw(&self, name: &str) -> Result<Option<String>, ArgError> {
+        self.mark_seen(name, true);
+        Ok(config::resolve(self.cli_raw(name)?, self.file_raw(name)))
+    }
+
+    /// Parses option `name` as `T`, if present.
+    pub fn value<T: FromStr>(&self, name: &str) -> Result<Option<T>, ArgError> {
+        match self.raw(name)? {
+            Some(v) => v.parse().map(Some).map_err(|_| ArgError::InvalidValue {
+                name: name.to_string(),
+                value: v,
+            }),
+            None => Ok(None),
+        }
+    }
+
+    /// Parses option `name` as a comma-separated list of `T`, if present.
+    pub fn list<T: FromStr>(&self, name: &str) -> Result<Option<Vec<T>>, ArgError> {
+        match self.raw(name)? {
+            Some(v) => v
+                .split(',')
+                .map(|item| {
+                    item.trim().parse().map_err(|_| ArgError::InvalidValue {
+                        name: name.to_string(),
+                        value: v.clone(),
+                    })
+                })
+                .collect::<Result<Vec<T>, ArgError>>()
+                .map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// Parses option `name` as `T`, falling back to `default` when absent.
+    ///
+    /// `T` must print the way it parses, so `--dump-config` can show the
+    /// default as a value that could be passed back in.
+    pub fn value_or<T: FromStr + fmt::Display>(&self, name: &str, default: T) -> Result<T, ArgError> {
+        let mut defaults = self.defaults.borrow_mut();
+        if !defaults.iter().any(|(seen, _)| seen == name) {
+            defaults.push((name.to_string(), default.to_string()));
+        }
+        drop(defaults);
+        Ok(self.value(name)?.unwrap_or(default))
+    }
+
+    /// Call once all options have been read.
+    ///
+    /// Handles `--help` and `--version` by printing and exiting, exits with
+    /// the usage text on command line options the program never looked up,
+    /// and warns about config file keys it does not use, which are usually
+    /// typos. `--quiet`, or `quiet = true` in the config file, turns off the
+    /// startup and shutdown banners (see [`style::banner`]).
+    pub fn finish(&self) {
+        if self.args.iter().any(|a| a == "--help" || a == "-h") {
+            println!("{}", self.usage());
+            process::exit(0);
+        }
+        if self.args.iter().any(|a| a == "--version") {
+            println!("{} {}", self.program, self.version);
+            process::exit(0);
+        }
+
+        if let Err(e) = self.check_unknown() {
+            eprintln!("Error: {}", e);
+            process::exit(EXIT_CONFIG);
+        }
+        style::set_quiet(self.flag_set("--quiet"));
+
+        let known = self.known_options();
+        for key in self.config.keys() {
+            let name = format!("--{}", key);
+            let builtin = BUILTIN_OPTIONS.iter().any(|opt| opt.name() == name);
+            if !builtin && !known.iter().any(|(known, _)| *known == name) {
+                eprintln!("Warning: unknown option '{}' in config file ignored", key);
+            }
+        }
+    }
+
+    /// The program's options as `(name, takes_value)`: the declared list,
+    /// or for a program that declared none, the options it looked up.
+    fn known_options(&self) -> Vec<(String, bool)> {
+        match self.options {
+            Some(options) => options
+                .iter()
+                .map(|opt| (opt.name().to_string(), opt.takes_value()))
+                .collect(),
+            None => self.seen.borrow().clone(),
+        }
+    }
+
+    /// Rejects any `-x` or `--name` token that is neither a builtin nor a
+    /// known option. Values of known options are skipped, so
+    /// `--transpose -5` is fine.
+    fn check_unknown(&self) -> Result<(), ArgError> {
+        let known = self.known_options();
+        let mut args = self.args.iter();
+        while let Some(arg) = args.next() {
+            if !arg.starts_with('-') || arg == "-h" {
+                continue;
+            }
+            let name = arg.split('=').next().unwrap_or(arg);
+            let takes_value = BUILTIN_OPTIONS
+                .iter()
+                .find(|opt| opt.name() == name)
+                .map(|opt| opt.takes_value())
+                .or_else(|| known.iter().find(|(known, _)| known == name).map(|&(_, value)| value));
+            let Some(takes_value) = takes_value else {
+                return Err(ArgError::Unknown {
+                    name: name.to_string(),
+                    usage: self.usage(),
+                });
+            };
+            // Skip the value of `--name value`
+            if takes_value && !arg.contains('=') {
+                args.next();
+            }
+        }
+        Ok(())
+    }
+
+    /// With `--dry-run`, prints the options as resolved from the command line
+    /// and config file and exits 0. Call after validating the options and
+    /// before touching any hardware.
+    ///
+    /// `--dump-config` prints [`Args::effective_config`] here first and, unlike
+    /// `--dry-run`, lets the program carry on.
+    pub fn exit_if_dry_run(&self) {
+        if self.args.iter().any(|a| a == "--dump-config") {
+            println!("{}", self.effective_config());
+        }
+        if !self.args.iter().any(|a| a == "--dry-run") {
+            return;
+        }
+        println!("Configuration OK (dry run, no hardware accessed):");
+        for (name, takes_value) in self.seen.borrow().iter() {
+            let value = if *takes_value {
+                config::resolve(self.cli_raw(name).ok().flatten(), self.file_raw(name))
+                    .unwrap_or_else(|| "(default)".to_string())
+            } else {
+                self.flag_set(name).to_string()
+            };
+            println!("  {} = {}", name, value);
+        }
+        process::exit(0);
+    }
+
+    /// Every option the program looked up with the value it ended up using,
+    /// as pretty-printed JSON with the keys sorted so two runs with the same
+    /// settings print the same text.
+    ///
+    /// Flags are booleans. Other options are strings written the way they
+    /// would be on the command line: the command line value, else the config
+    /// file value, else the `value_or` default, or `null` for an optional
+    /// option that was left unset.
+    pub fn effective_config(&self) -> String {
+        let defaults = self.defaults.borrow();
+        let mut resolved = serde_json::Map::new();
+        for (name, takes_value) in self.seen.borrow().iter() {
+            let value = if *takes_value {
+                config::resolve(self.cli_raw(name).ok().flatten(), self.file_raw(name))
+                    .or_else(|| defaults.iter().find(|(seen, _)| seen == name).map(|(_, v)| v.clone()))
+                    .map_or(serde_json::Value::Null, serde_json::Value::String)
+            } else {
+                serde_json::Value::Bool(self.flag_set(name))
+            };
+            resolved.insert(name.trim_start_matches("--").to_string(), value);
+        }
+        serde_json::to_string_pretty(&resolved).unwrap_or_default()
+    }
+
+    /// One-line usage listing every option the program accepts.
+    pub fn usage(&self) -> String {
+        let mut usage = format!(
+            "Usage: {} [--help] [--version] [--dry-run] [--dump-config] [--quiet] [--config <file.toml>]",
+            self.program
+        );
+        for (name, takes_value) in self.known_options() {
+            if takes_value {
+                usage.push_str(&format!(" [{} <value>]", name));
+            } else {
+                usage.push_str(&format!(" [{}]", name));
+            }
+        }
+        usage
+    }
+
+    fn cli_raw(&self, name: &str) -> Result<Option<String>, ArgError> {
+        let prefix = format!("{}=", name);
+        for (i, arg) in self.args.iter().enumerate() {
+            if arg == name {
+                return match self.args.get(i + 1) {
+                    Some(v) => Ok(Some(v.clone())),
+                    None => Err(ArgError::MissingValue(name.to_string())),
+                };
+            }
+            if let Some(v) = arg.strip_prefix(&prefix) {
+                return Ok(Some(v.to_string()));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Whether flag `name` is on: given on the command line, or set to
+    /// `true` in the config file.
+    fn flag_set(&self, name: &str) -> bool {
+        self.args.iter().any(|a| a == name) || self.file_raw(name).as_deref() == Some("true")
+    }
+
+    fn file_raw(&self, name: &str) -> Option<String> {
+        self.config.get(name.strip_prefix("--").unwrap_or(name))
+    }
+
+    fn mark_seen(&self, name: &str, takes_value: bool) {
+        debug_assert!(
+            self.options.is_none_or(|options| options.iter().any(|opt| opt.name() == name)),
+            "option {} is looked up but not declared in with_options",
+            name
+        );
+        let mut seen = self.seen.borrow_mut();
+        if !seen.iter().any(|(seen, _)| seen == name) {
+            seen.push((name.to_string(), takes_value));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const OPTIONS: &[Opt] = &[Opt::Value("--delay-ms"), Opt::Flag("--mirror"), Opt::Value("--transpose")];
+
+    fn args(list: &[&str]) -> Args {
+        let list = list.iter().map(|s| s.to_string()).collect();
+        Args::from_vec("test".to_string(), list).unwrap().with_options(OPTIONS)
+    }
+
+    fn unknown_name(args: &Args) -> Option<String> {
+        match args.check_unknown() {
+            Err(ArgError::Unknown { name, .. }) => Some(name),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn declared_options_pass_even_if_never_read() {
+        let args = args(&["--delay-ms", "50", "--mirror"]);
+        assert!(args.check_unknown().is_ok());
+    }
+
+    #[test]
+    fn unknown_long_option_is_rejected() {
+        assert_eq!(unknown_name(&args(&["--mirorr"])).as_deref(), Some("--mirorr"));
+        assert_eq!(unknown_name(&args(&["--bogus=3"])).as_deref(), Some("--bogus"));
+    }
+
+    #[test]
+    fn single_dash_typo_is_rejected() {
+        assert_eq!(unknown_name(&args(&["-x"])).as_deref(), Some("-x"));
+        assert_eq!(unknown_name(&args(&["-delay-ms", "50"])).as_deref(), Some("-delay-ms"));
+    }
+
+    #[test]
+    fn negative_value_is_not_taken_for_an_option() {
+        assert!(args(&["--transpose", "-5"]).check_unknown().is_ok());
+        assert!(args(&["--transpose=-5"]).check_unknown().is_ok());
+    }
+
+    #[test]
+    fn flag_does_not_swallow_the_next_token() {
+        assert_eq!(unknown_name(&args(&["--mirror", "-x"])).as_deref(), Some("-x"));
+    }
+
+    #[test]
+    fn builtins_are_always_accepted() {
+        let args = args(&["--dry-run", "--quiet", "--dump-config", "-h"]);
+        assert!(args.check_unknown().is_ok());
+    }
+
+    #[test]
+    fn without_a_declaration_looked_up_options_are_known() {
+        let list = vec!["--wpm".to_string(), "15".to_string()];
+        let args = Args::from_vec("test".to_string(), list).unwrap();
+        assert_eq!(unknown_name(&args).as_deref(), Some("--wpm"));
+        assert_eq!(args.value::<u32>("--wpm").unwrap(), Some(15));
+        assert!(args.check_unknown().is_ok());
+    }
+
+    #[test]
+    fn usage_lists_declared_options() {
+        let usage = args(&[]).usage();
+        assert!(usage.contains("[--delay-ms <value>]"));
+        assert!(usage.contains("[--mirror]"));
+    }
+
+    #[test]
+    fn value_or_falls_back_to_the_default() {
+        let args = args(&["--delay-ms=20"]);
+        assert_eq!(args.value_or("--delay-ms", 100u64).unwrap(), 20);
+        assert_eq!(args.value_or("--transpose", 0i32).unwrap(), 0);
+    }
+
+    #[test]
+    fn invalid_value_is_reported_with_its_option() {
+        let args = args(&["--delay-ms", "soon"]);
+        assert!(matches!(
+            args.value::<u64>("--delay-ms"),
+            Err(ArgError::InvalidValue { name, value }) if name == "--delay-ms" && value == "soon"
+        ));
+    }
+
+    #[test]
+    fn quiet_can_come_from_the_config_file() {
+        assert!(!args(&[]).flag_set("--quiet"));
+        assert!(args(&["--quiet"]).flag_set("--quiet"));
+        let mut args = args(&[]);
+        args.config = AppConfig::parse("quiet = true").unwrap();
+        assert!(args.flag_set("--quiet"));
+    }
+}
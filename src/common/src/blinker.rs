@@ -0,0 +1,83 @@
+use std::time::{Duration, Instant};
+
+use crate::led::Led;
+
+/// Returns `true` once `interval` has passed since the `last` toggle.
+pub fn should_toggle(last: Instant, now: Instant, interval: Duration) -> bool {
+    now.saturating_duration_since(last) >= interval
+}
+
+/// Blinks an LED from an existing loop instead of a dedicated thread.
+///
+/// Call `tick` as often as convenient; the LED toggles whenever `interval`
+/// has elapsed, so the blink rate is only as precise as the loop's period.
+pub struct TimedBlinker {
+    led: Led,
+    interval: Duration,
+    last_toggle: Instant,
+}
+
+impl TimedBlinker {
+    /// Turns the LED on and starts timing from `now`.
+    pub fn new(mut led: Led, interval: Duration, now: Instant) -> Self {
+        led.on();
+        Self {
+            led,
+            interval,
+            last_toggle: now,
+        }
+    }
+
+    /// Toggles the LED if the interval has elapsed; returns `true` if it did.
+    pub fn tick(&mut self, now: Instant) -> bool {
+        if !should_toggle(self.last_toggle, now, self.interval) {
+            return false;
+        }
+        self.led.toggle();
+        self.last_toggle = now;
+        true
+    }
+
+    /// Turns the LED on and starts a fresh on phase from `now`, e.g. when
+    /// blinking resumes after `stop`.
+    pub fn restart(&mut self, now: Instant) {
+        self.led.on();
+        self.last_toggle = now;
+    }
+
+    /// Turns the LED off; `tick` would start blinking again, so callers
+    /// stop ticking until `restart`.
+    pub fn stop(&mut self) {
+        self.led.off();
+    }
+
+    /// Changes the blink rate. Takes effect from the current on/off phase,
+    /// so a knob can retune it smoothly mid-cycle.
+    pub fn set_interval(&mut self, interval: Duration) {
+        self.interval = interval;
+    }
+
+    pub fn is_on(&self) -> bool {
+        self.led.is_on()
+    }
+
+    /// Gives the LED back, e.g. to turn it off on exit.
+    pub fn into_led(self) -> Led {
+        self.led
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn toggles_once_the_interval_has_passed() {
+        let last = Instant::now();
+        let interval = Duration::from_millis(500);
+        assert!(!should_toggle(last, last + Duration::from_millis(499), interval));
+        assert!(should_toggle(last, last + interval, interval));
+        // A `now` before `last` never toggles
+        assert!(!should_toggle(last + interval, last, interval));
+    }
+}
@@ -0,0 +1,617 @@
+use std::fmt;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+use rppal::gpio::{InputPin, Level, Result, Trigger};
+
+use crate::replay::Recorder;
+
+/// Which pin level means "pressed".
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ButtonLogic {
+    /// Button to ground with a pull-up (the tutorial wiring): low = pressed.
+    #[default]
+    ActiveLow,
+    /// Button to 3.3V with a pull-down: high = pressed.
+    ActiveHigh,
+}
+
+impl FromStr for ButtonLogic {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "active-low" => Ok(ButtonLogic::ActiveLow),
+            "active-high" => Ok(ButtonLogic::ActiveHigh),
+            _ => Err(format!("expected 'active-low' or 'active-high', got '{}'", s)),
+        }
+    }
+}
+
+impl fmt::Display for ButtonLogic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            ButtonLogic::ActiveLow => "active-low",
+            ButtonLogic::ActiveHigh => "active-high",
+        })
+    }
+}
+
+/// Which pin transitions raise a button interrupt.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum EdgeTrigger {
+    Rising,
+    Falling,
+    /// Both transitions; the level is read to tell press from release.
+    #[default]
+    Both,
+}
+
+impl FromStr for EdgeTrigger {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "rising" => Ok(EdgeTrigger::Rising),
+            "falling" => Ok(EdgeTrigger::Falling),
+            "both" => Ok(EdgeTrigger::Both),
+            _ => Err(format!("expected 'rising', 'falling' or 'both', got '{}'", s)),
+        }
+    }
+}
+
+impl fmt::Display for EdgeTrigger {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            EdgeTrigger::Rising => "rising",
+            EdgeTrigger::Falling => "falling",
+            EdgeTrigger::Both => "both",
+        })
+    }
+}
+
+impl EdgeTrigger {
+    pub fn trigger(self) -> Trigger {
+        match self {
+            EdgeTrigger::Rising => Trigger::RisingEdge,
+            EdgeTrigger::Falling => Trigger::FallingEdge,
+            EdgeTrigger::Both => Trigger::Both,
+        }
+    }
+
+    /// Whether a single-edge trigger fires on the press for this wiring.
+    /// A mismatch (e.g. `rising` with an active-low button) fires on the
+    /// release instead, which is usually a misconfiguration.
+    pub fn fires_on_press(self, logic: ButtonLogic) -> bool {
+        matches!(
+            (self, logic),
+            (EdgeTrigger::Both, _)
+                | (EdgeTrigger::Falling, ButtonLogic::ActiveLow)
+                | (EdgeTrigger::Rising, ButtonLogic::ActiveHigh)
+        )
+    }
+}
+
+/// Interprets a raw pin level according to the wiring.
+pub fn is_pressed(level: Level, logic: ButtonLogic) -> bool {
+    match logic {
+        ButtonLogic::ActiveLow => level == Level::Low,
+        ButtonLogic::ActiveHigh => level == Level::High,
+    }
+}
+
+/// Edges ignored for this long after an accepted press or release.
+pub const DEFAULT_DEBOUNCE: Duration = Duration::from_millis(20);
+/// How often `ButtonEvents` wakes up to check the `running` flag and
+/// re-sample the level.
+pub const EVENT_POLL_TIMEOUT: Duration = Duration::from_millis(10);
+
+/// A debounced change of button state.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ButtonEvent {
+    Pressed,
+    Released,
+}
+
+/// How a [`Debouncer`] decides that the button really changed state.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum DebounceStrategy {
+    /// Accepts a change at once, then ignores further changes for one
+    /// window. Reacts fastest, but a burst of noise longer than the window
+    /// can get through.
+    #[default]
+    Window,
+    /// Integrates how long the input reads pressed versus released and only
+    /// changes state once a full window's worth has accumulated one way.
+    /// Adds up to one window of latency but rides out periodic noise.
+    Integrator,
+}
+
+impl FromStr for DebounceStrategy {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "window" => Ok(DebounceStrategy::Window),
+            "integrator" => Ok(DebounceStrategy::Integrator),
+            _ => Err(format!("expected 'window' or 'integrator', got '{}'", s)),
+        }
+    }
+}
+
+impl fmt::Display for DebounceStrategy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            DebounceStrategy::Window => "window",
+            DebounceStrategy::Integrator => "integrator",
+        })
+    }
+}
+
+/// Turns raw pressed/released samples into debounced events.
+///
+/// Pure state machine so it can be fed samples without a real pin.
+#[derive(Clone, Debug)]
+pub struct Debouncer {
+    strategy: DebounceStrategy,
+    pressed: bool,
+    last_change: Option<Instant>,
+    window: Duration,
+    // Integrator only: time credited towards "pressed", kept in
+    // `[0, window]`, and the previous raw sample
+    integral: Duration,
+    last_sample: Option<(bool, Instant)>,
+}
+
+impl Debouncer {
+    pub fn new(window: Duration) -> Self {
+        Self::with_state(window, false)
+    }
+
+    /// Starts from a known state, so a button already held at startup is
+    /// not reported as a press.
+    pub fn with_state(window: Duration, pressed: bool) -> Self {
+        Self {
+            strategy: DebounceStrategy::default(),
+            pressed,
+            last_change: None,
+            window,
+            integral: if pressed { window } else { Duration::ZERO },
+            last_sample: None,
+        }
+    }
+
+    pub fn with_strategy(mut self, strategy: DebounceStrategy) -> Self {
+        self.strategy = strategy;
+        self
+    }
+
+    /// Feeds one sample; returns an event when the debounced state changes.
+    pub fn update(&mut self, pressed: bool, now: Instant) -> Option<ButtonEvent> {
+        let settled = match self.strategy {
+            DebounceStrategy::Window => self.settle_window(pressed, now),
+            DebounceStrategy::Integrator => self.settle_integrator(pressed, now),
+        }?;
+        if settled == self.pressed {
+            return None;
+        }
+        self.pressed = settled;
+        self.last_change = Some(now);
+        Some(if settled { ButtonEvent::Pressed } else { ButtonEvent::Released })
+    }
+
+    /// Takes the sample as is unless the last accepted change is less than
+    /// one window old.
+    fn settle_window(&self, pressed: bool, now: Instant) -> Option<bool> {
+        match self.last_change {
+            Some(last) if now.saturating_duration_since(last) < self.window => None,
+            _ => Some(pressed),
+        }
+    }
+
+    /// Credits the time since the previous sample to that sample's level,
+    /// then settles only at either end of the range.
+    fn settle_integrator(&mut self, pressed: bool, now: Instant) -> Option<bool> {
+        if let Some((was_pressed, at)) = self.last_sample {
+            let elapsed = now.saturating_duration_since(at);
+            self.integral = if was_pressed {
+                (self.integral + elapsed).min(self.window)
+            } else {
+                self.integral.saturating_sub(elapsed)
+            };
+        }
+        self.last_sample = Some((pressed, now));
+        if self.window.is_zero() {
+            Some(pressed)
+        } else if self.integral >= self.window {
+            Some(true)
+        } else if self.integral.is_zero() {
+            Some(false)
+        } else {
+            None
+        }
+    }
+}
+
+/// Keyboard-style auto-repeat for a held button: one step on the press,
+/// another after `delay`, then one every `interval` until release.
+#[derive(Clone, Debug)]
+pub struct AutoRepeat {
+    delay: Duration,
+    interval: Duration,
+    // Steps fired during the current hold
+    fired: u32,
+}
+
+impl AutoRepeat {
+    pub fn new(delay: Duration, interval: Duration) -> Self {
+        Self {
+            delay,
+            interval,
+            fired: 0,
+        }
+    }
+
+    /// Feeds how long the button has been held, or `None` once released.
+    /// Returns `true` when a step is due. Releasing stops the repeat at
+    /// once and the next press starts over.
+    pub fn update(&mut self, held_for: Option<Duration>) -> bool {
+        let Some(held_for) = held_for else {
+            self.fired = 0;
+            return false;
+        };
+        let due = match self.fired {
+            0 => true,
+            n => held_for >= self.delay + self.interval * (n - 1),
+        };
+        if due {
+            self.fired += 1;
+        }
+        due
+    }
+}
+
+/// Fires once per press when the button has been held for `hold`, so a
+/// deliberate long press can be told apart from a tap.
+#[derive(Clone, Debug)]
+pub struct LongPress {
+    hold: Duration,
+    pressed_since: Option<Instant>,
+    fired: bool,
+}
+
+impl LongPress {
+    pub fn new(hold: Duration) -> Self {
+        Self {
+            hold,
+            pressed_since: None,
+            fired: false,
+        }
+    }
+
+    /// Feeds the debounced event of this loop pass, if any. Returns `true`
+    /// on the pass where the current press first reaches `hold`; holding
+    /// longer does not fire again until the button is released.
+    pub fn update(&mut self, event: Option<ButtonEvent>, now: Instant) -> bool {
+        match event {
+            Some(ButtonEvent::Pressed) => {
+                self.pressed_since = Some(now);
+                self.fired = false;
+            }
+            Some(ButtonEvent::Released) => self.pressed_since = None,
+            None => {}
+        }
+        match self.pressed_since {
+            Some(since) if !self.fired && now.saturating_duration_since(since) >= self.hold => {
+                self.fired = true;
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+/// Iterator over the debounced events of a [`Button`], see [`Button::events`].
+pub struct ButtonEvents<'a> {
+    button: &'a mut Button,
+    running: &'a AtomicBool,
+    debouncer: Debouncer,
+    recorder: Option<Recorder>,
+}
+
+impl ButtonEvents<'_> {
+    /// Also writes every raw level change to `recorder`, for replaying
+    /// with [`replay::replay`](crate::replay::replay).
+    pub fn record_to(mut self, recorder: Recorder) -> Self {
+        self.recorder = Some(recorder);
+        self
+    }
+
+    /// Debounces with `strategy` instead of the default window.
+    pub fn debounce_strategy(mut self, strategy: DebounceStrategy) -> Self {
+        self.debouncer = self.debouncer.with_strategy(strategy);
+        self
+    }
+}
+
+impl Iterator for ButtonEvents<'_> {
+    type Item = Result<ButtonEvent>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.running.load(Ordering::SeqCst) {
+            // The level is sampled on timeouts too, so a change whose edge
+            // fell inside the debounce window is still picked up later
+            if let Err(e) = self.button.pin.poll_interrupt(true, Some(EVENT_POLL_TIMEOUT)) {
+                return Some(Err(e));
+            }
+            let level = self.button.pin.read();
+            let now = Instant::now();
+            if let Some(recorder) = self.recorder.as_mut()
+                && let Err(e) = recorder.sample(level, now)
+            {
+                return Some(Err(e.into()));
+            }
+            let pressed = is_pressed(level, self.button.logic);
+            if let Some(event) = self.debouncer.update(pressed, now) {
+                return Some(Ok(event));
+            }
+        }
+        None
+    }
+}
+
+/// A push button on an input pin.
+pub struct Button {
+    pin: InputPin,
+    logic: ButtonLogic,
+}
+
+impl Button {
+    /// A button with the tutorial's active-low wiring.
+    pub fn new(pin: InputPin) -> Self {
+        Self::with_logic(pin, ButtonLogic::ActiveLow)
+    }
+
+    pub fn with_logic(pin: InputPin, logic: ButtonLogic) -> Self {
+        Self { pin, logic }
+    }
+
+    pub fn is_pressed(&self) -> bool {
+        is_pressed(self.pin.read(), self.logic)
+    }
+
+    /// Enables edge interrupts and yields debounced presses and releases
+    /// until `running` is cleared.
+    pub fn events<'a>(&'a mut self, running: &'a AtomicBool) -> Result<ButtonEvents<'a>> {
+        setup_button_interrupt(&mut self.pin)?;
+        // Start from the current level so a held button is not reported
+        let debouncer = Debouncer::with_state(DEFAULT_DEBOUNCE, self.is_pressed());
+        Ok(ButtonEvents {
+            button: self,
+            running,
+            debouncer,
+            recorder: None,
+        })
+    }
+
+    /// Gives access to the pin, e.g. to configure interrupts.
+    pub fn pin_mut(&mut self) -> &mut InputPin {
+        &mut self.pin
+    }
+}
+
+/// Enables edge interrupts on both press and release.
+pub fn setup_button_interrupt(btn_pin: &mut InputPin) -> Result<()> {
+    setup_button_trigger(btn_pin, EdgeTrigger::Both)
+}
+
+/// Enables edge interrupts on the transitions selected by `trigger`.
+pub fn setup_button_trigger(btn_pin: &mut InputPin, trigger: EdgeTrigger) -> Result<()> {
+    btn_pin.set_interrupt(trigger.trigger(), None)
+}
+
+/// Blocks for up to `timeout` waiting for a button edge.
+///
+/// Returns `true` if an edge arrived; the caller reads the pin level to find
+/// out whether it was a press or a release.
+pub fn wait_for_edge(btn_pin: &mut InputPin, timeout: Duration) -> Result<bool> {
+    Ok(btn_pin.poll_interrupt(true, Some(timeout))?.is_some())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WINDOW: Duration = Duration::from_millis(20);
+
+    fn ms(start: Instant, ms: u64) -> Instant {
+        start + Duration::from_millis(ms)
+    }
+
+    #[test]
+    fn button_logic_parses_what_it_prints() {
+        for logic in [ButtonLogic::ActiveLow, ButtonLogic::ActiveHigh] {
+            assert_eq!(logic.to_string().parse::<ButtonLogic>(), Ok(logic));
+        }
+        assert!("low".parse::<ButtonLogic>().is_err());
+    }
+
+    #[test]
+    fn edge_trigger_parses_what_it_prints() {
+        for trigger in [EdgeTrigger::Rising, EdgeTrigger::Falling, EdgeTrigger::Both] {
+            assert_eq!(trigger.to_string().parse::<EdgeTrigger>(), Ok(trigger));
+        }
+    }
+
+    #[test]
+    fn debounce_strategy_parses_what_it_prints() {
+        for strategy in [DebounceStrategy::Window, DebounceStrategy::Integrator] {
+            assert_eq!(strategy.to_string().parse::<DebounceStrategy>(), Ok(strategy));
+        }
+        assert!("median".parse::<DebounceStrategy>().is_err());
+    }
+
+    #[test]
+    fn is_pressed_follows_the_wiring() {
+        assert!(is_pressed(Level::Low, ButtonLogic::ActiveLow));
+        assert!(!is_pressed(Level::High, ButtonLogic::ActiveLow));
+        assert!(is_pressed(Level::High, ButtonLogic::ActiveHigh));
+    }
+
+    #[test]
+    fn single_edge_must_match_the_wiring_to_fire_on_press() {
+        assert!(EdgeTrigger::Falling.fires_on_press(ButtonLogic::ActiveLow));
+        assert!(!EdgeTrigger::Rising.fires_on_press(ButtonLogic::ActiveLow));
+        assert!(EdgeTrigger::Rising.fires_on_press(ButtonLogic::ActiveHigh));
+        assert!(EdgeTrigger::Both.fires_on_press(ButtonLogic::ActiveHigh));
+    }
+
+    #[test]
+    fn window_reports_a_press_at_once_and_ignores_bounce() {
+        let start = Instant::now();
+        let mut debouncer = Debouncer::new(WINDOW);
+        assert_eq!(debouncer.update(true, start), Some(ButtonEvent::Pressed));
+        assert_eq!(debouncer.update(false, ms(start, 2)), None);
+        assert_eq!(debouncer.update(true, ms(start, 4)), None);
+        assert_eq!(debouncer.update(false, ms(start, 25)), Some(ButtonEvent::Released));
+    }
+
+    #[test]
+    fn window_picks_up_a_change_made_during_the_window() {
+        let start = Instant::now();
+        let mut debouncer = Debouncer::new(WINDOW);
+        debouncer.update(true, start);
+        // Released inside the window, still released once it has passed
+        assert_eq!(debouncer.update(false, ms(start, 10)), None);
+        assert_eq!(debouncer.update(false, ms(start, 30)), Some(ButtonEvent::Released));
+    }
+
+    #[test]
+    fn held_at_startup_is_not_reported() {
+        let start = Instant::now();
+        let mut debouncer = Debouncer::with_state(WINDOW, true);
+        assert_eq!(debouncer.update(true, start), None);
+        assert_eq!(debouncer.update(false, ms(start, 1)), Some(ButtonEvent::Released));
+    }
+
+    #[test]
+    fn integrator_waits_a_full_window_before_pressing() {
+        let start = Instant::now();
+        let mut debouncer = Debouncer::new(WINDOW).with_strategy(DebounceStrategy::Integrator);
+        assert_eq!(debouncer.update(true, start), None);
+        assert_eq!(debouncer.update(true, ms(start, 10)), None);
+        assert_eq!(debouncer.update(true, ms(start, 20)), Some(ButtonEvent::Pressed));
+    }
+
+    #[test]
+    fn integrator_rides_out_a_short_glitch() {
+        let start = Instant::now();
+        let mut debouncer = Debouncer::new(WINDOW).with_strategy(DebounceStrategy::Integrator);
+        let samples = [(0, true), (5, false), (10, false), (15, true), (18, false), (40, false)];
+        for (t, pressed) in samples {
+            assert_eq!(debouncer.update(pressed, ms(start, t)), None, "at {} ms", t);
+        }
+    }
+
+    #[test]
+    fn integrator_releases_after_a_full_window_released() {
+        let start = Instant::now();
+        let mut debouncer = Debouncer::with_state(WINDOW, true).with_strategy(DebounceStrategy::Integrator);
+        assert_eq!(debouncer.update(false, start), None);
+        assert_eq!(debouncer.update(false, ms(start, 10)), None);
+        assert_eq!(debouncer.update(false, ms(start, 20)), Some(ButtonEvent::Released));
+    }
+
+    /// Raw samples as `(ms, pressed)`, then the events the window and the
+    /// integrator report for them as `(ms, event)`.
+    type Bounce = (
+        &'static str,
+        &'static [(u64, bool)],
+        &'static [(u64, ButtonEvent)],
+        &'static [(u64, ButtonEvent)],
+    );
+
+    const BOUNCES: &[Bounce] = {
+        use ButtonEvent::{Pressed, Released};
+        &[
+            (
+                "clean press",
+                &[(0, true), (50, true), (100, false), (150, false)],
+                &[(0, Pressed), (100, Released)],
+                &[(50, Pressed), (150, Released)],
+            ),
+            (
+                "bouncy press",
+                &[(0, true), (2, false), (4, true), (6, false), (8, true), (40, true)],
+                &[(0, Pressed)],
+                &[(40, Pressed)],
+            ),
+            (
+                "glitch while released",
+                &[(0, false), (10, true), (12, false), (40, false)],
+                &[(10, Pressed), (40, Released)],
+                &[],
+            ),
+            (
+                "bouncy release",
+                &[(0, true), (30, true), (60, false), (62, true), (64, false), (100, false)],
+                &[(0, Pressed), (60, Released)],
+                &[(30, Pressed), (100, Released)],
+            ),
+        ]
+    };
+
+    fn debounce(strategy: DebounceStrategy, samples: &[(u64, bool)]) -> Vec<(u64, ButtonEvent)> {
+        let start = Instant::now();
+        let mut debouncer = Debouncer::new(WINDOW).with_strategy(strategy);
+        samples
+            .iter()
+            .filter_map(|&(t, pressed)| debouncer.update(pressed, ms(start, t)).map(|event| (t, event)))
+            .collect()
+    }
+
+    #[test]
+    fn strategies_on_the_same_bounce_patterns() {
+        for &(name, samples, window, integrator) in BOUNCES {
+            assert_eq!(debounce(DebounceStrategy::Window, samples), window, "window, {}", name);
+            assert_eq!(debounce(DebounceStrategy::Integrator, samples), integrator, "integrator, {}", name);
+        }
+    }
+
+    #[test]
+    fn auto_repeat_fires_on_press_after_delay_then_every_interval() {
+        let mut repeat = AutoRepeat::new(Duration::from_millis(500), Duration::from_millis(100));
+        let held = |t| Some(Duration::from_millis(t));
+        assert!(repeat.update(held(0)));
+        assert!(!repeat.update(held(499)));
+        assert!(repeat.update(held(500)));
+        assert!(!repeat.update(held(599)));
+        assert!(repeat.update(held(600)));
+        assert!(!repeat.update(None));
+        // A new press starts over with an immediate step
+        assert!(repeat.update(held(0)));
+    }
+
+    #[test]
+    fn long_press_fires_once_per_hold() {
+        let start = Instant::now();
+        let mut long_press = LongPress::new(Duration::from_millis(1000));
+        assert!(!long_press.update(Some(ButtonEvent::Pressed), start));
+        assert!(!long_press.update(None, ms(start, 999)));
+        assert!(long_press.update(None, ms(start, 1000)));
+        assert!(!long_press.update(None, ms(start, 3000)));
+        assert!(!long_press.update(Some(ButtonEvent::Released), ms(start, 3100)));
+        assert!(!long_press.update(Some(ButtonEvent::Pressed), ms(start, 4000)));
+        assert!(long_press.update(None, ms(start, 5000)));
+    }
+
+    #[test]
+    fn short_press_is_not_a_long_press() {
+        let start = Instant::now();
+        let mut long_press = LongPress::new(Duration::from_millis(1000));
+        long_press.update(Some(ButtonEvent::Pressed), start);
+        assert!(!long_press.update(Some(ButtonEvent::Released), ms(start, 300)));
+        assert!(!long_press.update(None, ms(start, 2000)));
+    }
+}
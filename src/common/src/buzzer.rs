@@ -0,0 +1,272 @@
+use std::thread;
+use std::time::Duration;
+
+use rppal::gpio::{OutputPin, Result};
+
+const TONE_DUTY_CYCLE: f64 = 0.5;
+/// Long enough to hear, short enough not to delay the next button event.
+pub const DEFAULT_BEEP: Duration = Duration::from_millis(30);
+/// Largest share of a note that `split_note` will turn into silence.
+pub const MAX_ARTICULATION: f64 = 0.5;
+
+/// Splits a note of `duration` into the time it sounds and the silence
+/// after it, so repeated notes get distinct onsets.
+///
+/// `articulation` is the silent fraction: 0 is legato (no gap), 0.5 is
+/// staccato. Values outside `0..=MAX_ARTICULATION` are clamped and NaN is
+/// treated as legato.
+pub fn split_note(duration: Duration, articulation: f64) -> (Duration, Duration) {
+    let articulation = if articulation.is_nan() { 0.0 } else { articulation };
+    let gap = duration.mul_f64(articulation.clamp(0.0, MAX_ARTICULATION));
+    (duration - gap, gap)
+}
+
+/// Lowest and highest tones worth asking the kit's passive buzzer for, B0
+/// and D#8 at the ends of the usual note table. Below it the buzzer only
+/// clicks; above it software PWM cannot hold the frequency.
+pub const MIN_TONE_HZ: f64 = 31.0;
+pub const MAX_TONE_HZ: f64 = 4978.0;
+/// Largest `--transpose` the examples accept, four octaves either way.
+pub const MAX_TRANSPOSE: i32 = 48;
+
+/// Shifts `frequency` by `semitones` in equal temperament; negative values
+/// go down, and 12 semitones is one octave.
+pub fn transpose(frequency: f64, semitones: i32) -> f64 {
+    frequency * 2f64.powf(semitones as f64 / 12.0)
+}
+
+/// [`transpose`], with the result clamped to
+/// `MIN_TONE_HZ..=MAX_TONE_HZ`. The flag tells whether it was clamped, so
+/// the caller can warn that the tone is not the one asked for.
+pub fn transpose_clamped(frequency: f64, semitones: i32) -> (f64, bool) {
+    let shifted = transpose(frequency, semitones);
+    let clamped = shifted.clamp(MIN_TONE_HZ, MAX_TONE_HZ);
+    (clamped, clamped != shifted)
+}
+
+/// Frequency of a siren sweeping from `low` up to `high` and back over each
+/// `period`, `elapsed` after it started.
+pub fn siren_frequency(elapsed: Duration, low: f64, high: f64, period: Duration) -> f64 {
+    if period.is_zero() {
+        return low;
+    }
+    let phase = (elapsed.as_secs_f64() / period.as_secs_f64()).fract();
+    let rise = if phase < 0.5 { 2.0 * phase } else { 2.0 * (1.0 - phase) };
+    low + (high - low) * rise
+}
+
+/// A repeating beep-beep pattern: `on` of tone, then `off` of silence.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BeepPattern {
+    pub on: Duration,
+    pub off: Duration,
+}
+
+impl BeepPattern {
+    /// Whether the tone should sound `elapsed` after the pattern started.
+    /// A zero `on` is silence; otherwise a zero `off` is a continuous tone.
+    pub fn is_on(self, elapsed: Duration) -> bool {
+        if self.on.is_zero() {
+            return false;
+        }
+        if self.off.is_zero() {
+            return true;
+        }
+        let period = self.on + self.off;
+        Duration::from_nanos((elapsed.as_nanos() % period.as_nanos()) as u64) < self.on
+    }
+}
+
+/// The output a [`Buzzer`] drives. Implemented for rppal's `OutputPin`;
+/// tests substitute a pin that records what it was told.
+pub trait BuzzerPin {
+    fn set_high(&mut self);
+    fn set_low(&mut self);
+    fn set_pwm_frequency(&mut self, frequency: f64, duty_cycle: f64) -> Result<()>;
+    fn clear_pwm(&mut self) -> Result<()>;
+}
+
+impl BuzzerPin for OutputPin {
+    fn set_high(&mut self) {
+        OutputPin::set_high(self);
+    }
+
+    fn set_low(&mut self) {
+        OutputPin::set_low(self);
+    }
+
+    fn set_pwm_frequency(&mut self, frequency: f64, duty_cycle: f64) -> Result<()> {
+        OutputPin::set_pwm_frequency(self, frequency, duty_cycle)
+    }
+
+    fn clear_pwm(&mut self) -> Result<()> {
+        OutputPin::clear_pwm(self)
+    }
+}
+
+/// A buzzer on an output pin.
+///
+/// Active buzzers sound with `on`; passive buzzers need a square wave from
+/// `tone`.
+pub struct Buzzer<P = OutputPin> {
+    pin: P,
+}
+
+impl<P: BuzzerPin> Buzzer<P> {
+    pub fn new(pin: P) -> Self {
+        Self { pin }
+    }
+
+    pub fn on(&mut self) {
+        self.pin.set_high();
+    }
+
+    pub fn off(&mut self) {
+        self.pin.set_low();
+    }
+
+    /// Sounds an active buzzer for `duration`, blocking meanwhile; keep it
+    /// short when called from an input loop.
+    pub fn beep(&mut self, duration: Duration) {
+        self.on();
+        thread::sleep(duration);
+        self.off();
+    }
+
+    /// Plays `frequency` Hz at 50% duty with software PWM.
+    pub fn tone(&mut self, frequency: f64) -> Result<()> {
+        self.pin.set_pwm_frequency(frequency, TONE_DUTY_CYCLE)
+    }
+
+    /// Stops any tone and drives the pin low.
+    pub fn silence(&mut self) -> Result<()> {
+        self.pin.clear_pwm()?;
+        self.off();
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::time::Instant;
+
+    fn close(a: f64, b: f64) -> bool {
+        (a - b).abs() < 0.01
+    }
+
+    #[test]
+    fn split_note_legato_has_no_gap() {
+        let note = Duration::from_millis(400);
+        assert_eq!(split_note(note, 0.0), (note, Duration::ZERO));
+        assert_eq!(split_note(note, f64::NAN), (note, Duration::ZERO));
+    }
+
+    #[test]
+    fn split_note_staccato_silences_the_tail() {
+        let note = Duration::from_millis(400);
+        assert_eq!(split_note(note, 0.25), (Duration::from_millis(300), Duration::from_millis(100)));
+        assert_eq!(split_note(note, 0.5), (Duration::from_millis(200), Duration::from_millis(200)));
+    }
+
+    #[test]
+    fn split_note_clamps_articulation() {
+        let note = Duration::from_millis(400);
+        assert_eq!(split_note(note, 0.9), split_note(note, MAX_ARTICULATION));
+        assert_eq!(split_note(note, -1.0), (note, Duration::ZERO));
+    }
+
+    #[test]
+    fn transpose_by_an_octave_doubles_or_halves() {
+        assert!(close(transpose(440.0, 12), 880.0));
+        assert!(close(transpose(440.0, -12), 220.0));
+        assert!(close(transpose(440.0, 0), 440.0));
+    }
+
+    #[test]
+    fn transpose_crosses_octaves_in_semitones() {
+        // A4 up a minor third is C5, and down fifteen semitones is F#3
+        assert!(close(transpose(440.0, 3), 523.25));
+        assert!(close(transpose(440.0, -15), 185.0));
+    }
+
+    #[test]
+    fn transpose_clamped_passes_through_in_range_tones() {
+        assert_eq!(transpose_clamped(440.0, 0), (440.0, false));
+        let (hz, clamped) = transpose_clamped(440.0, 12);
+        assert!(close(hz, 880.0));
+        assert!(!clamped);
+    }
+
+    #[test]
+    fn transpose_clamped_stops_at_the_buzzer_range() {
+        assert_eq!(transpose_clamped(2000.0, 24), (MAX_TONE_HZ, true));
+        assert_eq!(transpose_clamped(200.0, -48), (MIN_TONE_HZ, true));
+    }
+
+    #[test]
+    fn siren_sweeps_up_and_back_each_period() {
+        let period = Duration::from_secs(1);
+        let at = |ms| siren_frequency(Duration::from_millis(ms), 440.0, 880.0, period);
+        assert!(close(at(0), 440.0));
+        assert!(close(at(250), 660.0));
+        assert!(close(at(500), 880.0));
+        assert!(close(at(750), 660.0));
+        assert!(close(at(1250), 660.0));
+        assert_eq!(siren_frequency(Duration::from_secs(3), 440.0, 880.0, Duration::ZERO), 440.0);
+    }
+
+    #[test]
+    fn beep_pattern_alternates() {
+        let pattern = BeepPattern { on: Duration::from_millis(200), off: Duration::from_millis(100) };
+        let on = |ms| pattern.is_on(Duration::from_millis(ms));
+        assert_eq!([on(0), on(199), on(200), on(299), on(300)], [true, true, false, false, true]);
+    }
+
+    #[test]
+    fn zero_off_is_continuous() {
+        let continuous = BeepPattern { on: Duration::from_millis(200), off: Duration::ZERO };
+        assert!(continuous.is_on(Duration::ZERO));
+        assert!(continuous.is_on(Duration::from_millis(250)));
+    }
+
+    #[test]
+    fn zero_on_is_silence() {
+        let silent = BeepPattern { on: Duration::ZERO, off: Duration::from_millis(100) };
+        assert!(!silent.is_on(Duration::ZERO));
+        assert!(!silent.is_on(Duration::from_millis(150)));
+        let both_zero = BeepPattern { on: Duration::ZERO, off: Duration::ZERO };
+        assert!(!both_zero.is_on(Duration::from_millis(50)));
+    }
+
+    /// Records each level change and when it happened.
+    struct RecordingPin(Vec<(bool, Instant)>);
+
+    impl BuzzerPin for RecordingPin {
+        fn set_high(&mut self) {
+            self.0.push((true, Instant::now()));
+        }
+
+        fn set_low(&mut self) {
+            self.0.push((false, Instant::now()));
+        }
+
+        fn set_pwm_frequency(&mut self, _frequency: f64, _duty_cycle: f64) -> Result<()> {
+            Ok(())
+        }
+
+        fn clear_pwm(&mut self) -> Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn beep_sounds_for_the_duration_then_goes_quiet() {
+        let mut buzzer = Buzzer::new(RecordingPin(Vec::new()));
+        buzzer.beep(Duration::from_millis(20));
+        let changes = &buzzer.pin.0;
+        assert_eq!(changes.iter().map(|&(high, _)| high).collect::<Vec<_>>(), [true, false]);
+        assert!(changes[1].1 - changes[0].1 >= Duration::from_millis(20));
+    }
+}
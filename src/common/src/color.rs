@@ -0,0 +1,207 @@
+use std::fmt;
+use std::str::FromStr;
+
+use rppal::gpio::{Level, OutputPin, Result};
+
+use crate::led::LedPolarity;
+
+// Software PWM frequency for `apply_rgb`
+const RGB_PWM_FREQUENCY: f64 = 1000.0;
+
+/// Converts HSV to RGB.
+///
+/// `hue` is in degrees (wrapped into `[0, 360)`); `saturation` and `value`
+/// are clamped to `[0, 1]`. Returns `(r, g, b)` each in `[0, 1]`.
+pub fn hsv_to_rgb(hue: f64, saturation: f64, value: f64) -> (f64, f64, f64) {
+    let h = hue.rem_euclid(360.0) / 60.0;
+    let s = saturation.clamp(0.0, 1.0);
+    let v = value.clamp(0.0, 1.0);
+
+    let c = v * s;
+    let x = c * (1.0 - (h % 2.0 - 1.0).abs());
+    let m = v - c;
+
+    let (r, g, b) = match h as u32 {
+        0 => (c, x, 0.0),
+        1 => (x, c, 0.0),
+        2 => (0.0, c, x),
+        3 => (0.0, x, c),
+        4 => (x, 0.0, c),
+        _ => (c, 0.0, x),
+    };
+    (r + m, g + m, b + m)
+}
+
+/// Gamma exponents for the red, green and blue channels of an RGB LED.
+///
+/// The three dies in one package differ in efficiency, so one exponent
+/// for all of them still leaves white tinted; each channel gets its own.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Gamma {
+    pub red: f64,
+    pub green: f64,
+    pub blue: f64,
+}
+
+impl Gamma {
+    /// Leaves brightness unchanged.
+    pub const LINEAR: Gamma = Gamma::uniform(1.0);
+
+    pub const fn uniform(exponent: f64) -> Self {
+        Self {
+            red: exponent,
+            green: exponent,
+            blue: exponent,
+        }
+    }
+
+    /// Raises each channel of `rgb`, clamped to `[0, 1]`, to that channel's
+    /// exponent.
+    pub fn apply(self, rgb: (f64, f64, f64)) -> (f64, f64, f64) {
+        (
+            rgb.0.clamp(0.0, 1.0).powf(self.red),
+            rgb.1.clamp(0.0, 1.0).powf(self.green),
+            rgb.2.clamp(0.0, 1.0).powf(self.blue),
+        )
+    }
+}
+
+/// Which pin the three colours of an RGB LED share.
+///
+/// The RGB LED in the Freenove kit is common anode: the long leg goes to
+/// 3.3V and each colour lights while its pin is low. Common-cathode modules
+/// (long leg to GND) light while the pin is high.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum RgbCommon {
+    #[default]
+    Anode,
+    Cathode,
+}
+
+impl FromStr for RgbCommon {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "anode" => Ok(RgbCommon::Anode),
+            "cathode" => Ok(RgbCommon::Cathode),
+            _ => Err(format!("expected 'anode' or 'cathode', got '{}'", s)),
+        }
+    }
+}
+
+impl fmt::Display for RgbCommon {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            RgbCommon::Anode => "anode",
+            RgbCommon::Cathode => "cathode",
+        })
+    }
+}
+
+impl RgbCommon {
+    /// Duty to put on a colour's pin for `brightness` in `[0, 1]`.
+    pub fn pin_duty(self, brightness: f64) -> f64 {
+        let brightness = brightness.clamp(0.0, 1.0);
+        match self {
+            RgbCommon::Anode => 1.0 - brightness,
+            RgbCommon::Cathode => brightness,
+        }
+    }
+
+    /// Pin level that lights a colour.
+    pub fn on_level(self) -> Level {
+        match self {
+            RgbCommon::Anode => Level::Low,
+            RgbCommon::Cathode => Level::High,
+        }
+    }
+
+    /// Pin level that turns a colour off.
+    pub fn off_level(self) -> Level {
+        !self.on_level()
+    }
+
+    /// Polarity for driving one colour as a [`crate::led::Led`].
+    pub fn led_polarity(self) -> LedPolarity {
+        match self {
+            RgbCommon::Anode => LedPolarity::ActiveLow,
+            RgbCommon::Cathode => LedPolarity::ActiveHigh,
+        }
+    }
+}
+
+/// Shows `rgb` (each in `[0, 1]`) on the red, green and blue `pins` with
+/// software PWM, inverting the duty for a common-anode LED.
+pub fn apply_rgb(pins: &mut [OutputPin; 3], rgb: (f64, f64, f64), common: RgbCommon) -> Result<()> {
+    let [r, g, b] = pins;
+    r.set_pwm_frequency(RGB_PWM_FREQUENCY, common.pin_duty(rgb.0))?;
+    g.set_pwm_frequency(RGB_PWM_FREQUENCY, common.pin_duty(rgb.1))?;
+    b.set_pwm_frequency(RGB_PWM_FREQUENCY, common.pin_duty(rgb.2))?;
+    Ok(())
+}
+
+/// Stops PWM on the RGB `pins` and leaves all three colours off.
+pub fn clear_rgb(pins: &mut [OutputPin; 3], common: RgbCommon) -> Result<()> {
+    for pin in pins.iter_mut() {
+        pin.clear_pwm()?;
+        pin.write(common.off_level());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn close(a: (f64, f64, f64), b: (f64, f64, f64)) -> bool {
+        (a.0 - b.0).abs() < 1e-9 && (a.1 - b.1).abs() < 1e-9 && (a.2 - b.2).abs() < 1e-9
+    }
+
+    #[test]
+    fn primary_and_secondary_hues() {
+        assert!(close(hsv_to_rgb(0.0, 1.0, 1.0), (1.0, 0.0, 0.0)));
+        assert!(close(hsv_to_rgb(120.0, 1.0, 1.0), (0.0, 1.0, 0.0)));
+        assert!(close(hsv_to_rgb(240.0, 1.0, 1.0), (0.0, 0.0, 1.0)));
+        assert!(close(hsv_to_rgb(60.0, 1.0, 1.0), (1.0, 1.0, 0.0)));
+        assert!(close(hsv_to_rgb(180.0, 1.0, 0.5), (0.0, 0.5, 0.5)));
+    }
+
+    #[test]
+    fn hue_wraps_around() {
+        assert!(close(hsv_to_rgb(360.0, 1.0, 1.0), hsv_to_rgb(0.0, 1.0, 1.0)));
+        assert!(close(hsv_to_rgb(-120.0, 1.0, 1.0), hsv_to_rgb(240.0, 1.0, 1.0)));
+    }
+
+    #[test]
+    fn zero_saturation_is_grey() {
+        assert!(close(hsv_to_rgb(200.0, 0.0, 0.3), (0.3, 0.3, 0.3)));
+        // Out-of-range inputs are clamped
+        assert!(close(hsv_to_rgb(0.0, -1.0, 2.0), (1.0, 1.0, 1.0)));
+    }
+
+    #[test]
+    fn gamma_applies_per_channel() {
+        let gamma = Gamma { red: 1.0, green: 2.0, blue: 3.0 };
+        assert!(close(gamma.apply((0.5, 0.5, 0.5)), (0.5, 0.25, 0.125)));
+        assert!(close(Gamma::LINEAR.apply((0.2, 0.4, 0.6)), (0.2, 0.4, 0.6)));
+        assert!(close(Gamma::uniform(2.0).apply((1.5, -0.5, 1.0)), (1.0, 0.0, 1.0)));
+    }
+
+    #[test]
+    fn common_anode_inverts_duty_and_levels() {
+        assert_eq!(RgbCommon::Anode.pin_duty(0.25), 0.75);
+        assert_eq!(RgbCommon::Cathode.pin_duty(0.25), 0.25);
+        assert_eq!(RgbCommon::Anode.on_level(), Level::Low);
+        assert_eq!(RgbCommon::Anode.off_level(), Level::High);
+        assert_eq!(RgbCommon::Cathode.led_polarity(), LedPolarity::ActiveHigh);
+    }
+
+    #[test]
+    fn rgb_common_parses_what_it_prints() {
+        for common in [RgbCommon::Anode, RgbCommon::Cathode] {
+            assert_eq!(common.to_string().parse::<RgbCommon>(), Ok(common));
+        }
+        assert!("both".parse::<RgbCommon>().is_err());
+    }
+}
@@ -0,0 +1,132 @@
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Option values loaded from a `--config` TOML file.
+///
+/// The file is a flat table keyed by option name without the leading `--`,
+/// so anything accepted on the command line can be stored there:
+///
+/// ```toml
+/// log-every = 1000
+/// button-logic = "active-high"
+/// led-pins = [17, 27]
+/// keyboard = true
+/// ```
+#[derive(Debug, Default)]
+pub struct AppConfig {
+    values: toml::Table,
+}
+
+#[derive(Debug)]
+pub enum ConfigError {
+    Read { path: PathBuf, source: io::Error },
+    Parse { path: PathBuf, source: toml::de::Error },
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::Read { path, source } => {
+                write!(f, "cannot read config file {}: {}", path.display(), source)
+            }
+            ConfigError::Parse { path, source } => {
+                write!(f, "invalid config file {}: {}", path.display(), source)
+            }
+        }
+    }
+}
+
+impl Error for ConfigError {}
+
+impl AppConfig {
+    pub fn load(path: &Path) -> Result<Self, ConfigError> {
+        let text = fs::read_to_string(path).map_err(|source| ConfigError::Read {
+            path: path.to_path_buf(),
+            source,
+        })?;
+        Self::parse(&text).map_err(|source| ConfigError::Parse {
+            path: path.to_path_buf(),
+            source,
+        })
+    }
+
+    pub fn parse(text: &str) -> Result<Self, toml::de::Error> {
+        Ok(Self {
+            values: toml::from_str(text)?,
+        })
+    }
+
+    /// The value for option `name` rendered the way it would be written on
+    /// the command line; arrays become comma-separated lists.
+    pub fn get(&self, name: &str) -> Option<String> {
+        self.values.get(name).map(render)
+    }
+
+    pub fn keys(&self) -> impl Iterator<Item = &str> {
+        self.values.keys().map(String::as_str)
+    }
+}
+
+fn render(value: &toml::Value) -> String {
+    match value {
+        toml::Value::String(s) => s.clone(),
+        toml::Value::Array(items) => items.iter().map(render).collect::<Vec<_>>().join(","),
+        other => other.to_string(),
+    }
+}
+
+/// Picks the effective raw value of an option: an explicit command line value
+/// wins over the config file, and `None` means the caller's default applies.
+pub fn resolve(cli: Option<String>, file: Option<String>) -> Option<String> {
+    cli.or(file)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn values_render_like_command_line_arguments() {
+        let config = AppConfig::parse(
+            r#"
+            log-every = 1000
+            button-logic = "active-high"
+            led-pins = [17, 27]
+            keyboard = true
+            gamma = 2.2
+            "#,
+        )
+        .unwrap();
+        assert_eq!(config.get("log-every").as_deref(), Some("1000"));
+        assert_eq!(config.get("button-logic").as_deref(), Some("active-high"));
+        assert_eq!(config.get("led-pins").as_deref(), Some("17,27"));
+        assert_eq!(config.get("keyboard").as_deref(), Some("true"));
+        assert_eq!(config.get("gamma").as_deref(), Some("2.2"));
+        assert_eq!(config.get("missing"), None);
+    }
+
+    #[test]
+    fn keys_lists_every_option() {
+        let config = AppConfig::parse("a = 1\nb = 2").unwrap();
+        let mut keys: Vec<_> = config.keys().collect();
+        keys.sort();
+        assert_eq!(keys, ["a", "b"]);
+    }
+
+    #[test]
+    fn invalid_toml_is_rejected() {
+        assert!(AppConfig::parse("log-every = ").is_err());
+        let err = AppConfig::load(Path::new("/nonexistent/lamp.toml")).unwrap_err();
+        assert!(matches!(err, ConfigError::Read { .. }));
+    }
+
+    #[test]
+    fn command_line_wins_over_file() {
+        assert_eq!(resolve(Some("1".into()), Some("2".into())).as_deref(), Some("1"));
+        assert_eq!(resolve(None, Some("2".into())).as_deref(), Some("2"));
+        assert_eq!(resolve(None, None), None);
+    }
+}
@@ -0,0 +1,148 @@
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/// Whether a file holding `rows` data rows has reached `max_rows`.
+/// `None` never rotates.
+pub fn should_rotate(rows: u64, max_rows: Option<u64>) -> bool {
+    max_rows.is_some_and(|max| rows >= max)
+}
+
+/// Name of a rotated file: `readings.csv` becomes
+/// `readings-<unix_secs>-<seq>.csv` next to it. `seq` keeps names unique
+/// when several rotations happen within one second.
+pub fn rotated_path(base: &Path, unix_secs: u64, seq: u32) -> PathBuf {
+    let stem = base.file_stem().map(|s| s.to_string_lossy()).unwrap_or_default();
+    let name = match base.extension() {
+        Some(ext) => format!("{}-{}-{}.{}", stem, unix_secs, seq, ext.to_string_lossy()),
+        None => format!("{}-{}-{}", stem, unix_secs, seq),
+    };
+    base.with_file_name(name)
+}
+
+/// Appends CSV rows to a file, flushing every `flush_every` and starting a
+/// new file once `max_rows` rows have been written.
+///
+/// The first file is `path` itself; later ones are named by
+/// [`rotated_path`]. Every file starts with the header. The current file is
+/// flushed before rotating and by [`CsvLogger::finish`].
+pub struct CsvLogger {
+    base: PathBuf,
+    header: String,
+    max_rows: Option<u64>,
+    flush_every: Duration,
+    writer: BufWriter<File>,
+    rows: u64,
+    rotations: u32,
+    last_flush: Instant,
+}
+
+impl CsvLogger {
+    pub fn create(
+        path: impl Into<PathBuf>,
+        header: &str,
+        max_rows: Option<u64>,
+        flush_every: Duration,
+    ) -> io::Result<Self> {
+        let base = path.into();
+        let writer = open_with_header(&base, header)?;
+        Ok(Self {
+            base,
+            header: header.to_string(),
+            max_rows,
+            flush_every,
+            writer,
+            rows: 0,
+            rotations: 0,
+            last_flush: Instant::now(),
+        })
+    }
+
+    /// Writes one row; `row` is the already formatted line without newline.
+    pub fn write_row(&mut self, row: &str) -> io::Result<()> {
+        if should_rotate(self.rows, self.max_rows) {
+            self.rotate()?;
+        }
+        writeln!(self.writer, "{}", row)?;
+        self.rows += 1;
+        let now = Instant::now();
+        if now.saturating_duration_since(self.last_flush) >= self.flush_every {
+            self.writer.flush()?;
+            self.last_flush = now;
+        }
+        Ok(())
+    }
+
+    /// Flushes the current file; call on shutdown so no rows are lost.
+    pub fn finish(mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+
+    fn rotate(&mut self) -> io::Result<()> {
+        self.writer.flush()?;
+        let unix_secs = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_secs());
+        let path = rotated_path(&self.base, unix_secs, self.rotations);
+        self.writer = open_with_header(&path, &self.header)?;
+        self.rotations += 1;
+        self.rows = 0;
+        self.last_flush = Instant::now();
+        println!("CSV log rotated to {}", path.display());
+        Ok(())
+    }
+}
+
+fn open_with_header(path: &Path, header: &str) -> io::Result<BufWriter<File>> {
+    let mut writer = BufWriter::new(File::create(path)?);
+    writeln!(writer, "{}", header)?;
+    writer.flush()?;
+    Ok(writer)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+    use std::fs;
+    use std::process;
+
+    #[test]
+    fn rotation_threshold() {
+        assert!(!should_rotate(1_000_000, None));
+        assert!(!should_rotate(9, Some(10)));
+        assert!(should_rotate(10, Some(10)));
+    }
+
+    #[test]
+    fn rotated_names_keep_the_extension() {
+        assert_eq!(
+            rotated_path(Path::new("/var/log/readings.csv"), 1700000000, 2),
+            PathBuf::from("/var/log/readings-1700000000-2.csv")
+        );
+        assert_eq!(rotated_path(Path::new("readings"), 5, 0), PathBuf::from("readings-5-0"));
+    }
+
+    #[test]
+    fn every_file_starts_with_the_header() {
+        let dir = env::temp_dir().join(format!("common-csvlog-test-{}", process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let base = dir.join("readings.csv");
+        let mut logger = CsvLogger::create(&base, "time,value", Some(2), Duration::ZERO).unwrap();
+        for row in ["1,a", "2,b", "3,c"] {
+            logger.write_row(row).unwrap();
+        }
+        logger.finish().unwrap();
+
+        assert_eq!(fs::read_to_string(&base).unwrap(), "time,value\n1,a\n2,b\n");
+        let rotated: Vec<_> = fs::read_dir(&dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .filter(|path| path != &base)
+            .collect();
+        assert_eq!(rotated.len(), 1);
+        assert_eq!(fs::read_to_string(&rotated[0]).unwrap(), "time,value\n3,c\n");
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,84 @@
+/// Triangle wave between 0.0 and 1.0: rises by `step` per call, turns
+/// around exactly at the ends, and never overshoots them.
+pub struct Envelope {
+    level: f64,
+    step: f64,
+    rising: bool,
+}
+
+impl Envelope {
+    /// Starts dark and rising. `step` is taken as an absolute value.
+    pub fn new(step: f64) -> Self {
+        Self {
+            level: 0.0,
+            step: step.abs(),
+            rising: true,
+        }
+    }
+
+    /// Advances one step and returns the new level.
+    pub fn step(&mut self) -> f64 {
+        if self.rising {
+            self.level += self.step;
+            if self.level >= 1.0 {
+                self.level = 1.0;
+                self.rising = false;
+            }
+        } else {
+            self.level -= self.step;
+            if self.level <= 0.0 {
+                self.level = 0.0;
+                self.rising = true;
+            }
+        }
+        self.level
+    }
+
+    pub fn level(&self) -> f64 {
+        self.level
+    }
+
+    /// Direction of the next step.
+    pub fn is_rising(&self) -> bool {
+        self.rising
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rises_then_turns_at_full_brightness() {
+        let mut envelope = Envelope::new(0.4);
+        assert!((envelope.step() - 0.4).abs() < 1e-9);
+        assert!((envelope.step() - 0.8).abs() < 1e-9);
+        // Clamped at 1.0 instead of overshooting to 1.2
+        assert_eq!(envelope.step(), 1.0);
+        assert!(!envelope.is_rising());
+        assert!((envelope.step() - 0.6).abs() < 1e-9);
+    }
+
+    #[test]
+    fn turns_at_dark_and_rises_again() {
+        let mut envelope = Envelope::new(0.5);
+        let levels: Vec<f64> = (0..6).map(|_| envelope.step()).collect();
+        assert_eq!(levels, vec![0.5, 1.0, 0.5, 0.0, 0.5, 1.0]);
+    }
+
+    #[test]
+    fn negative_step_is_taken_as_absolute() {
+        let mut envelope = Envelope::new(-0.25);
+        assert_eq!(envelope.step(), 0.25);
+        assert!(envelope.is_rising());
+    }
+
+    #[test]
+    fn stays_within_bounds() {
+        let mut envelope = Envelope::new(0.3);
+        for _ in 0..100 {
+            let level = envelope.step();
+            assert!((0.0..=1.0).contains(&level));
+        }
+    }
+}
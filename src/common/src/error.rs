@@ -0,0 +1,193 @@
+use std::error::Error;
+use std::fmt;
+use std::io;
+use std::process;
+
+use rppal::gpio::{self, Gpio};
+
+use crate::args::ArgError;
+use crate::config::ConfigError;
+use crate::lock::LockError;
+use crate::logger;
+
+// Process exit codes, stable so scripts can tell failures apart
+pub const EXIT_FAILURE: i32 = 1;
+pub const EXIT_NO_ADC: i32 = 2;
+pub const EXIT_GPIO_UNAVAILABLE: i32 = 3;
+pub const EXIT_CONFIG: i32 = 4;
+pub const EXIT_ALREADY_RUNNING: i32 = 5;
+
+/// Errors the examples report from `main`.
+pub enum AppError {
+    Gpio(gpio::Error),
+    /// No GPIO hardware, typically because the program is not running on a
+    /// Raspberry Pi.
+    GpioUnavailable(gpio::Error),
+    /// A worker thread panicked instead of returning.
+    WorkerPanicked(&'static str),
+    /// The ADC module did not answer; holds the detection error message.
+    AdcNotFound(String),
+}
+
+impl fmt::Display for AppError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AppError::Gpio(e) => write!(f, "GPIO error: {}", e),
+            AppError::GpioUnavailable(e) => write!(
+                f,
+                "GPIO not available ({}) - are you on a Raspberry Pi with GPIO access?",
+                e
+            ),
+            AppError::WorkerPanicked(name) => write!(f, "{} thread panicked", name),
+            AppError::AdcNotFound(msg) => write!(f, "{}", msg),
+        }
+    }
+}
+
+// `main` prints a returned error with `Debug`, so show the readable message
+impl fmt::Debug for AppError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(self, f)
+    }
+}
+
+impl Error for AppError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            AppError::Gpio(e) | AppError::GpioUnavailable(e) => Some(e),
+            AppError::WorkerPanicked(_) | AppError::AdcNotFound(_) => None,
+        }
+    }
+}
+
+impl AppError {
+    /// The process exit code for this error.
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            AppError::Gpio(_) | AppError::WorkerPanicked(_) => EXIT_FAILURE,
+            AppError::GpioUnavailable(_) => EXIT_GPIO_UNAVAILABLE,
+            AppError::AdcNotFound(_) => EXIT_NO_ADC,
+        }
+    }
+}
+
+/// Exit code for any error returned from `main`: the [`AppError`] code
+/// (a bare `gpio::Error` is classified the same way), [`EXIT_CONFIG`] for
+/// command line and config file errors, [`EXIT_ALREADY_RUNNING`] when
+/// another instance holds the lock file, otherwise [`EXIT_FAILURE`].
+pub fn exit_code(e: &(dyn Error + 'static)) -> i32 {
+    if let Some(e) = e.downcast_ref::<AppError>() {
+        e.exit_code()
+    } else if let Some(e) = e.downcast_ref::<gpio::Error>() {
+        if gpio_unavailable(e) { EXIT_GPIO_UNAVAILABLE } else { EXIT_FAILURE }
+    } else if e.is::<ArgError>() || e.is::<ConfigError>() {
+        EXIT_CONFIG
+    } else if let Some(LockError::Held(_)) = e.downcast_ref::<LockError>() {
+        EXIT_ALREADY_RUNNING
+    } else {
+        EXIT_FAILURE
+    }
+}
+
+/// Runs an example's real `main` and exits with [`exit_code`] on error,
+/// after printing the error the way a returned error would be printed.
+/// Installs the stderr logger first, so `log` output works from the start.
+pub fn run(main: impl FnOnce() -> Result<(), Box<dyn Error>>) {
+    logger::init();
+    if let Err(e) = main() {
+        eprintln!("Error: {}", e);
+        process::exit(exit_code(e.as_ref()));
+    }
+}
+
+impl From<gpio::Error> for AppError {
+    fn from(e: gpio::Error) -> Self {
+        classify_gpio_error(e)
+    }
+}
+
+/// Separates "this machine has no GPIO" (unknown board, no GPIO device)
+/// from real GPIO failures on a Pi.
+pub fn classify_gpio_error(e: gpio::Error) -> AppError {
+    if gpio_unavailable(&e) { AppError::GpioUnavailable(e) } else { AppError::Gpio(e) }
+}
+
+fn gpio_unavailable(e: &gpio::Error) -> bool {
+    match e {
+        gpio::Error::UnknownModel => true,
+        gpio::Error::Io(io) => io.kind() == io::ErrorKind::NotFound,
+        _ => false,
+    }
+}
+
+/// `Gpio::new` with the error classified, so running on a non-Pi machine
+/// reports an actionable message.
+pub fn open_gpio() -> Result<Gpio, AppError> {
+    Ok(Gpio::new()?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn code(e: impl Error + 'static) -> i32 {
+        let boxed: Box<dyn Error> = Box::new(e);
+        exit_code(boxed.as_ref())
+    }
+
+    #[test]
+    fn app_errors_keep_their_codes() {
+        let cases = [
+            (AppError::Gpio(gpio::Error::PinUsed(17)), EXIT_FAILURE),
+            (AppError::GpioUnavailable(gpio::Error::UnknownModel), EXIT_GPIO_UNAVAILABLE),
+            (AppError::WorkerPanicked("blink"), EXIT_FAILURE),
+            (AppError::AdcNotFound("no ADC".into()), EXIT_NO_ADC),
+        ];
+        let mut covered = [false; 4];
+        for (e, expected) in cases {
+            // A new variant fails to compile here until it has a case above
+            let variant = match e {
+                AppError::Gpio(_) => 0,
+                AppError::GpioUnavailable(_) => 1,
+                AppError::WorkerPanicked(_) => 2,
+                AppError::AdcNotFound(_) => 3,
+            };
+            covered[variant] = true;
+            assert_eq!(code(e), expected);
+        }
+        assert_eq!(covered, [true; 4]);
+    }
+
+    #[test]
+    fn bare_gpio_errors_are_classified() {
+        assert_eq!(code(gpio::Error::UnknownModel), EXIT_GPIO_UNAVAILABLE);
+        let missing = gpio::Error::Io(io::Error::new(io::ErrorKind::NotFound, "/dev/gpiomem"));
+        assert_eq!(code(missing), EXIT_GPIO_UNAVAILABLE);
+        assert_eq!(code(gpio::Error::PinUsed(17)), EXIT_FAILURE);
+    }
+
+    #[test]
+    fn argument_config_and_lock_errors() {
+        assert_eq!(code(ArgError::MissingValue("--pin".into())), EXIT_CONFIG);
+        let read = ConfigError::Read {
+            path: PathBuf::from("lamp.toml"),
+            source: io::Error::new(io::ErrorKind::NotFound, "missing"),
+        };
+        assert_eq!(code(read), EXIT_CONFIG);
+        assert_eq!(code(LockError::Held(PathBuf::from("/tmp/x.lock"))), EXIT_ALREADY_RUNNING);
+        let io_error = LockError::Io(PathBuf::from("/x.lock"), io::Error::other("denied"));
+        assert_eq!(code(io_error), EXIT_FAILURE);
+    }
+
+    #[test]
+    fn other_errors_are_plain_failures() {
+        assert_eq!(code(io::Error::other("boom")), EXIT_FAILURE);
+    }
+
+    #[test]
+    fn classify_separates_missing_hardware() {
+        assert!(matches!(classify_gpio_error(gpio::Error::UnknownModel), AppError::GpioUnavailable(_)));
+        assert!(matches!(classify_gpio_error(gpio::Error::PinNotAvailable(40)), AppError::Gpio(_)));
+    }
+}
@@ -0,0 +1,119 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::time::Duration;
+
+/// Update period used for fades when the caller has no better choice.
+pub const DEFAULT_TICK: Duration = Duration::from_millis(10);
+
+/// Linear interpolation between `from` and `to`; `t` is clamped to `[0, 1]`.
+pub fn lerp(from: f64, to: f64, t: f64) -> f64 {
+    let t = t.clamp(0.0, 1.0);
+    from + (to - from) * t
+}
+
+/// Number of `tick`-sized steps needed to cover `duration` (at least one).
+pub fn step_count(duration: Duration, tick: Duration) -> u32 {
+    if tick.is_zero() {
+        return 1;
+    }
+    let steps = duration.as_nanos().div_ceil(tick.as_nanos());
+    steps.clamp(1, u32::MAX as u128) as u32
+}
+
+/// Ramps from `from` to `to` over `duration`, calling `apply` once per `tick`
+/// with the interpolated value. The last call always receives exactly `to`,
+/// so a zero duration simply applies the target once.
+pub fn ramp<E>(
+    from: f64,
+    to: f64,
+    duration: Duration,
+    tick: Duration,
+    apply: impl FnMut(f64) -> Result<(), E>,
+) -> Result<(), E> {
+    ramp_while(&AtomicBool::new(true), from, to, duration, tick, apply)?;
+    Ok(())
+}
+
+/// Like [`ramp`], but stops early once `running` is cleared, e.g. by Ctrl+C
+/// during a startup fade. Returns `false` if the ramp was cut short, in
+/// which case `to` may not have been applied.
+pub fn ramp_while<E>(
+    running: &AtomicBool,
+    from: f64,
+    to: f64,
+    duration: Duration,
+    tick: Duration,
+    mut apply: impl FnMut(f64) -> Result<(), E>,
+) -> Result<bool, E> {
+    let steps = step_count(duration, tick);
+    let step_delay = duration / steps;
+    for i in 1..=steps {
+        if !running.load(Ordering::SeqCst) {
+            return Ok(false);
+        }
+        apply(lerp(from, to, i as f64 / steps as f64))?;
+        if i < steps {
+            thread::sleep(step_delay);
+        }
+    }
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lerp_clamps_t() {
+        assert_eq!(lerp(10.0, 20.0, 0.5), 15.0);
+        assert_eq!(lerp(10.0, 20.0, -1.0), 10.0);
+        assert_eq!(lerp(10.0, 20.0, 2.0), 20.0);
+    }
+
+    #[test]
+    fn step_count_rounds_up_and_is_at_least_one() {
+        let tick = Duration::from_millis(10);
+        assert_eq!(step_count(Duration::from_millis(100), tick), 10);
+        assert_eq!(step_count(Duration::from_millis(101), tick), 11);
+        assert_eq!(step_count(Duration::ZERO, tick), 1);
+        assert_eq!(step_count(Duration::from_secs(1), Duration::ZERO), 1);
+    }
+
+    #[test]
+    fn ramp_ends_exactly_on_the_target() {
+        let mut applied = Vec::new();
+        ramp(0.0, 1.0, Duration::ZERO, DEFAULT_TICK, |v| {
+            applied.push(v);
+            Ok::<(), ()>(())
+        })
+        .unwrap();
+        assert_eq!(applied, vec![1.0]);
+
+        let mut applied = Vec::new();
+        ramp(0.0, 100.0, Duration::from_millis(4), Duration::from_millis(1), |v| {
+            applied.push(v);
+            Ok::<(), ()>(())
+        })
+        .unwrap();
+        assert_eq!(applied, vec![25.0, 50.0, 75.0, 100.0]);
+    }
+
+    #[test]
+    fn ramp_while_stops_when_running_is_cleared() {
+        let running = AtomicBool::new(false);
+        let mut calls = 0;
+        let finished = ramp_while(&running, 0.0, 1.0, Duration::from_millis(5), Duration::from_millis(1), |_| {
+            calls += 1;
+            Ok::<(), ()>(())
+        })
+        .unwrap();
+        assert!(!finished);
+        assert_eq!(calls, 0);
+    }
+
+    #[test]
+    fn ramp_propagates_apply_errors() {
+        let result = ramp(0.0, 1.0, Duration::from_millis(3), Duration::from_millis(1), |_| Err("pin gone"));
+        assert_eq!(result, Err("pin gone"));
+    }
+}
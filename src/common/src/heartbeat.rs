@@ -0,0 +1,81 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+/// Liveness signal from a worker thread to the main loop.
+///
+/// The worker calls `beat` every loop; the main loop calls `is_stalled` to
+/// notice a worker that died or hung while the shared state it reads keeps
+/// being updated.
+pub struct Heartbeat {
+    origin: Instant,
+    // Milliseconds since `origin` of the last beat
+    last_beat: AtomicU64,
+}
+
+impl Default for Heartbeat {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Heartbeat {
+    /// Starts as if the worker had just beaten, so it gets a full threshold
+    /// to come up.
+    pub fn new() -> Self {
+        Self {
+            origin: Instant::now(),
+            last_beat: AtomicU64::new(0),
+        }
+    }
+
+    pub fn beat(&self) {
+        self.last_beat.store(self.elapsed_ms(), Ordering::Relaxed);
+    }
+
+    /// `true` once no beat has arrived for longer than `threshold`.
+    pub fn is_stalled(&self, threshold: Duration) -> bool {
+        is_stale(
+            self.last_beat.load(Ordering::Relaxed),
+            self.elapsed_ms(),
+            threshold.as_millis() as u64,
+        )
+    }
+
+    fn elapsed_ms(&self) -> u64 {
+        self.origin.elapsed().as_millis() as u64
+    }
+}
+
+/// Whether a beat at `last_beat` is older than `threshold` at `now`, all in
+/// milliseconds on the same clock.
+pub fn is_stale(last_beat: u64, now: u64, threshold: u64) -> bool {
+    now.saturating_sub(last_beat) > threshold
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn beat_within_threshold_is_fresh() {
+        assert!(!is_stale(1000, 1500, 1000));
+        assert!(!is_stale(1000, 2000, 1000));
+    }
+
+    #[test]
+    fn beat_older_than_threshold_is_stale() {
+        assert!(is_stale(1000, 2001, 1000));
+    }
+
+    #[test]
+    fn clock_behind_the_beat_is_not_stale() {
+        // A beat stored after `now` was read must not underflow
+        assert!(!is_stale(2000, 1500, 100));
+    }
+
+    #[test]
+    fn new_heartbeat_is_not_stalled() {
+        let heartbeat = Heartbeat::new();
+        assert!(!heartbeat.is_stalled(Duration::from_secs(1)));
+    }
+}
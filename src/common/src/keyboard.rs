@@ -0,0 +1,173 @@
+use std::io::{self, BufRead, IsTerminal, Read};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread::{self, JoinHandle};
+
+use nix::sys::termios::{self, LocalFlags, SetArg, SpecialCharacterIndices, Termios};
+
+/// What a key press asks the example to do.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum KeyAction {
+    /// `+`, `k` or the up arrow.
+    Increase,
+    /// `-`, `j` or the down arrow.
+    Decrease,
+    /// `n`, `l` or the right arrow.
+    Next,
+    /// `p`, `h` or the left arrow.
+    Previous,
+}
+
+/// Decodes a chunk of terminal input into actions, ignoring unknown keys.
+/// Arrow keys arrive as the escape sequences `ESC [ A` .. `ESC [ D`.
+pub fn parse_keys(input: &[u8]) -> Vec<KeyAction> {
+    let mut actions = Vec::new();
+    let mut i = 0;
+    while i < input.len() {
+        if input[i] == 0x1b && input.get(i + 1) == Some(&b'[') {
+            if let Some(action) = input.get(i + 2).and_then(|&k| arrow_action(k)) {
+                actions.push(action);
+            }
+            i += 3;
+            continue;
+        }
+        if let Some(action) = key_action(input[i]) {
+            actions.push(action);
+        }
+        i += 1;
+    }
+    actions
+}
+
+fn key_action(key: u8) -> Option<KeyAction> {
+    match key {
+        b'+' | b'=' | b'k' => Some(KeyAction::Increase),
+        b'-' | b'_' | b'j' => Some(KeyAction::Decrease),
+        b'n' | b'l' => Some(KeyAction::Next),
+        b'p' | b'h' => Some(KeyAction::Previous),
+        _ => None,
+    }
+}
+
+fn arrow_action(key: u8) -> Option<KeyAction> {
+    match key {
+        b'A' => Some(KeyAction::Increase),
+        b'B' => Some(KeyAction::Decrease),
+        b'C' => Some(KeyAction::Next),
+        b'D' => Some(KeyAction::Previous),
+        _ => None,
+    }
+}
+
+/// The terminal settings saved when the key reader started; dropping it
+/// puts them back, so keep it alive until the program ends.
+#[must_use = "the terminal is restored as soon as the KeyReader is dropped"]
+pub struct KeyReader {
+    saved: Option<Termios>,
+}
+
+impl Drop for KeyReader {
+    fn drop(&mut self) {
+        if let Some(saved) = &self.saved {
+            let _ = termios::tcsetattr(io::stdin(), SetArg::TCSANOW, saved);
+        }
+    }
+}
+
+/// Switches the stdin terminal to non-canonical mode without echo, so each
+/// key is delivered as soon as it is pressed. Signal keys stay enabled, so
+/// Ctrl+C still stops the program. Returns the previous settings, or `None`
+/// when stdin is not a terminal or cannot be configured.
+fn enter_raw_mode() -> Option<Termios> {
+    let stdin = io::stdin();
+    if !stdin.is_terminal() {
+        return None;
+    }
+    let saved = termios::tcgetattr(&stdin).ok()?;
+    let mut raw = saved.clone();
+    raw.local_flags.remove(LocalFlags::ICANON | LocalFlags::ECHO);
+    raw.control_chars[SpecialCharacterIndices::VMIN as usize] = 1;
+    raw.control_chars[SpecialCharacterIndices::VTIME as usize] = 0;
+    termios::tcsetattr(&stdin, SetArg::TCSANOW, &raw).ok()?;
+    Some(saved)
+}
+
+/// Reads stdin on a background thread and calls `on_action` for every
+/// recognized key, as soon as it is pressed.
+///
+/// The terminal is put in non-canonical mode until the returned
+/// [`KeyReader`] is dropped. When stdin is not a terminal, e.g. keys piped
+/// in from a script, it is read as it is with no terminal changes and the
+/// keys act as the data arrives. The thread blocks on stdin and is not
+/// joined; it ends with the process.
+pub fn spawn_key_reader<F>(mut on_action: F) -> KeyReader
+where
+    F: FnMut(KeyAction) + Send + 'static,
+{
+    let saved = enter_raw_mode();
+    thread::spawn(move || {
+        let mut stdin = io::stdin();
+        let mut buf = [0u8; 64];
+        while let Ok(n) = stdin.read(&mut buf) {
+            if n == 0 {
+                break;
+            }
+            for action in parse_keys(&buf[..n]) {
+                on_action(action);
+            }
+        }
+    });
+    KeyReader { saved }
+}
+
+/// Flips `paused` every time Enter is pressed, on a background thread.
+///
+/// Animations check the flag before stepping, so their state is kept while
+/// paused, and keep checking their `running` flag so Ctrl+C still works.
+/// Like `spawn_key_reader`, the thread is not joined.
+pub fn spawn_pause_toggle(paused: Arc<AtomicBool>) -> JoinHandle<()> {
+    thread::spawn(move || {
+        let stdin = io::stdin();
+        let mut line = String::new();
+        while let Ok(n) = stdin.lock().read_line(&mut line) {
+            if n == 0 {
+                break;
+            }
+            let was_paused = paused.fetch_xor(true, Ordering::SeqCst);
+            println!("{}", if was_paused { "Resumed" } else { "Paused" });
+            line.clear();
+        }
+    })
+}
+
+pub const PAUSE_HELP: &str = "Press Enter to pause or resume";
+
+pub const KEY_HELP: &str = "Keys: + / k / Up = up, - / j / Down = down, n / l / Right = next, p / h / Left = previous";
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn letters_and_symbols_map_to_actions() {
+        assert_eq!(
+            parse_keys(b"+-nk"),
+            vec![KeyAction::Increase, KeyAction::Decrease, KeyAction::Next, KeyAction::Increase]
+        );
+    }
+
+    #[test]
+    fn arrow_sequences_are_decoded() {
+        assert_eq!(
+            parse_keys(b"\x1b[A\x1b[B\x1b[C\x1b[D"),
+            vec![KeyAction::Increase, KeyAction::Decrease, KeyAction::Next, KeyAction::Previous]
+        );
+    }
+
+    #[test]
+    fn unknown_keys_and_newlines_are_ignored() {
+        assert_eq!(parse_keys(b"x\n+\r"), vec![KeyAction::Increase]);
+        // An unknown escape sequence is skipped whole, not read as letters
+        assert_eq!(parse_keys(b"\x1b[Zj"), vec![KeyAction::Decrease]);
+    }
+}
@@ -0,0 +1,95 @@
+use rppal::gpio::{Level, OutputPin, Result};
+
+const PWM_FREQUENCY: f64 = 1000.0;
+
+/// Which output level lights the LED.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LedPolarity {
+    /// Anode on the GPIO pin, cathode to ground: high = on.
+    ActiveHigh,
+    /// Cathode on the GPIO pin, anode to 3.3V: low = on.
+    ActiveLow,
+}
+
+impl LedPolarity {
+    /// `ActiveHigh` when `active_high` is set, e.g. from an `--active-high` flag.
+    pub fn from_active_high(active_high: bool) -> Self {
+        if active_high { LedPolarity::ActiveHigh } else { LedPolarity::ActiveLow }
+    }
+
+    /// The pin level that lights the LED.
+    pub fn on_level(self) -> Level {
+        match self {
+            LedPolarity::ActiveHigh => Level::High,
+            LedPolarity::ActiveLow => Level::Low,
+        }
+    }
+
+    /// The pin level that turns the LED off.
+    pub fn off_level(self) -> Level {
+        !self.on_level()
+    }
+}
+
+/// A single LED on an output pin.
+pub struct Led {
+    pin: OutputPin,
+    polarity: LedPolarity,
+}
+
+impl Led {
+    pub fn new(pin: OutputPin, polarity: LedPolarity) -> Self {
+        Self { pin, polarity }
+    }
+
+    pub fn on(&mut self) {
+        self.pin.write(self.polarity.on_level());
+    }
+
+    pub fn off(&mut self) {
+        self.pin.write(self.polarity.off_level());
+    }
+
+    pub fn is_on(&self) -> bool {
+        Level::from(self.pin.is_set_high()) == self.polarity.on_level()
+    }
+
+    pub fn toggle(&mut self) {
+        if self.is_on() {
+            self.off();
+        } else {
+            self.on();
+        }
+    }
+
+    /// Drives the LED with software PWM; `brightness` is clamped to `[0, 1]`.
+    pub fn set_brightness(&mut self, brightness: f64) -> Result<()> {
+        let brightness = brightness.clamp(0.0, 1.0);
+        let duty = match self.polarity {
+            LedPolarity::ActiveHigh => brightness,
+            LedPolarity::ActiveLow => 1.0 - brightness,
+        };
+        self.pin.set_pwm_frequency(PWM_FREQUENCY, duty)
+    }
+
+    /// Stops software PWM and turns the LED off.
+    pub fn clear(&mut self) -> Result<()> {
+        self.pin.clear_pwm()?;
+        self.off();
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn polarity_levels() {
+        assert_eq!(LedPolarity::ActiveHigh.on_level(), Level::High);
+        assert_eq!(LedPolarity::ActiveHigh.off_level(), Level::Low);
+        assert_eq!(LedPolarity::ActiveLow.on_level(), Level::Low);
+        assert_eq!(LedPolarity::ActiveLow.off_level(), Level::High);
+        assert_eq!(LedPolarity::from_active_high(false), LedPolarity::ActiveLow);
+    }
+}
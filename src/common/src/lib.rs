@@ -0,0 +1,32 @@
+//! Helpers shared by the Freenove example programs.
+
+pub mod args;
+pub mod blinker;
+pub mod button;
+pub mod buzzer;
+pub mod color;
+pub mod config;
+pub mod csvlog;
+pub mod envelope;
+pub mod error;
+pub mod fade;
+pub mod heartbeat;
+pub mod keyboard;
+pub mod led;
+pub mod lock;
+pub mod logger;
+pub mod metrics;
+pub mod pins;
+pub mod pwm;
+pub mod rate;
+pub mod replay;
+pub mod report;
+pub mod retry;
+pub mod shutdown;
+pub mod smoothing;
+pub mod stats;
+pub mod status;
+pub mod style;
+pub mod threshold;
+pub mod throttle;
+pub mod worker;
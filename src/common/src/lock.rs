@@ -0,0 +1,109 @@
+use std::error::Error;
+use std::fmt;
+use std::fs::{File, OpenOptions, TryLockError};
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Why the instance lock could not be taken.
+#[derive(Debug)]
+pub enum LockError {
+    /// Another process holds the lock, usually another copy of the example.
+    Held(PathBuf),
+    /// The lock file could not be opened or locked.
+    Io(PathBuf, io::Error),
+}
+
+impl fmt::Display for LockError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LockError::Held(path) => write!(
+                f,
+                "another instance is already running (lock file {} is held)",
+                path.display()
+            ),
+            LockError::Io(path, e) => write!(f, "cannot lock {}: {}", path.display(), e),
+        }
+    }
+}
+
+impl Error for LockError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            LockError::Held(_) => None,
+            LockError::Io(_, e) => Some(e),
+        }
+    }
+}
+
+/// An exclusive advisory lock (`flock`) on a file, held until dropped.
+///
+/// The kernel also releases it when the process dies, so a signal or panic
+/// that skips `Drop` cannot leave a stale lock behind. The file itself is
+/// left in place; only the lock on it matters.
+#[derive(Debug)]
+pub struct InstanceLock {
+    file: File,
+}
+
+impl InstanceLock {
+    /// Takes the lock on `path`, creating the file if needed. Fails at once
+    /// instead of waiting if another process holds it.
+    pub fn acquire(path: &Path) -> Result<Self, LockError> {
+        let file = OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(path)
+            .map_err(|e| LockError::Io(path.to_path_buf(), e))?;
+        match file.try_lock() {
+            Ok(()) => Ok(Self { file }),
+            Err(TryLockError::WouldBlock) => Err(LockError::Held(path.to_path_buf())),
+            Err(TryLockError::Error(e)) => Err(LockError::Io(path.to_path_buf(), e)),
+        }
+    }
+}
+
+impl Drop for InstanceLock {
+    fn drop(&mut self) {
+        let _ = self.file.unlock();
+    }
+}
+
+/// Takes the `--lock-file` lock if one was given. Keep the returned guard
+/// alive until the pins are released.
+pub fn acquire(path: Option<&str>) -> Result<Option<InstanceLock>, LockError> {
+    path.map(|p| InstanceLock::acquire(Path::new(p))).transpose()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+    use std::fs;
+    use std::process;
+
+    fn lock_path(name: &str) -> PathBuf {
+        env::temp_dir().join(format!("common-lock-test-{}-{}.lock", process::id(), name))
+    }
+
+    #[test]
+    fn second_acquire_fails_until_the_first_is_dropped() {
+        let path = lock_path("held");
+        let first = InstanceLock::acquire(&path).unwrap();
+        assert!(matches!(InstanceLock::acquire(&path), Err(LockError::Held(p)) if p == path));
+        drop(first);
+        assert!(InstanceLock::acquire(&path).is_ok());
+        let _ = fs::remove_file(&path);
+    }
+
+    #[test]
+    fn no_path_means_no_lock() {
+        assert!(acquire(None).unwrap().is_none());
+    }
+
+    #[test]
+    fn unopenable_path_is_an_io_error() {
+        let path = lock_path("missing-dir").join("x.lock");
+        assert!(matches!(InstanceLock::acquire(&path), Err(LockError::Io(..))));
+    }
+}
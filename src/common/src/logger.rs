@@ -0,0 +1,29 @@
+use log::{LevelFilter, Log, Metadata, Record};
+
+/// Writes `log` records to stderr as `LEVEL: message`, for messages that
+/// are not part of an example's normal output, such as panic reports.
+struct StderrLogger;
+
+static LOGGER: StderrLogger = StderrLogger;
+
+impl Log for StderrLogger {
+    fn enabled(&self, metadata: &Metadata<'_>) -> bool {
+        metadata.level() <= log::max_level()
+    }
+
+    fn log(&self, record: &Record<'_>) {
+        if self.enabled(record.metadata()) {
+            eprintln!("{}: {}", record.level(), record.args());
+        }
+    }
+
+    fn flush(&self) {}
+}
+
+/// Installs the stderr logger at `Info` level. Safe to call more than
+/// once; a logger that is already installed is kept.
+pub fn init() {
+    if log::set_logger(&LOGGER).is_ok() {
+        log::set_max_level(LevelFilter::Info);
+    }
+}
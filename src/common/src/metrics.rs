@@ -0,0 +1,196 @@
+use std::fmt::Write as _;
+use std::io::{self, Read, Write};
+use std::net::{TcpListener, TcpStream, ToSocketAddrs};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::thread::{self, JoinHandle};
+
+/// A value that can go up and down, stored as `f64` bits so the control
+/// loop can update it without locking.
+pub struct Gauge {
+    name: &'static str,
+    help: &'static str,
+    bits: AtomicU64,
+    touched: AtomicBool,
+}
+
+impl Gauge {
+    pub const fn new(name: &'static str, help: &'static str) -> Self {
+        Self {
+            name,
+            help,
+            bits: AtomicU64::new(0),
+            touched: AtomicBool::new(false),
+        }
+    }
+
+    pub fn set(&self, value: f64) {
+        self.bits.store(value.to_bits(), Ordering::Relaxed);
+        self.touched.store(true, Ordering::Relaxed);
+    }
+
+    pub fn get(&self) -> f64 {
+        f64::from_bits(self.bits.load(Ordering::Relaxed))
+    }
+
+    fn render(&self, out: &mut String) {
+        if self.touched.load(Ordering::Relaxed) {
+            render_metric(out, self.name, self.help, "gauge", self.get());
+        }
+    }
+}
+
+/// A monotonically increasing count.
+pub struct Counter {
+    name: &'static str,
+    help: &'static str,
+    value: AtomicU64,
+    touched: AtomicBool,
+}
+
+impl Counter {
+    pub const fn new(name: &'static str, help: &'static str) -> Self {
+        Self {
+            name,
+            help,
+            value: AtomicU64::new(0),
+            touched: AtomicBool::new(false),
+        }
+    }
+
+    pub fn inc(&self) {
+        self.value.fetch_add(1, Ordering::Relaxed);
+        self.touched.store(true, Ordering::Relaxed);
+    }
+
+    pub fn get(&self) -> u64 {
+        self.value.load(Ordering::Relaxed)
+    }
+
+    fn render(&self, out: &mut String) {
+        if self.touched.load(Ordering::Relaxed) {
+            render_metric(out, self.name, self.help, "counter", self.get());
+        }
+    }
+}
+
+fn render_metric(out: &mut String, name: &str, help: &str, kind: &str, value: impl std::fmt::Display) {
+    let _ = writeln!(out, "# HELP {} {}", name, help);
+    let _ = writeln!(out, "# TYPE {} {}", name, kind);
+    let _ = writeln!(out, "{} {}", name, value);
+}
+
+/// The metrics every example shares, so dashboards see the same names
+/// whichever example is running. Only metrics an example has updated at
+/// least once are exported.
+pub struct Metrics {
+    pub adc_raw: Gauge,
+    pub adc_voltage: Gauge,
+    pub button_presses_total: Counter,
+    pub pwm_duty: Gauge,
+}
+
+impl Default for Metrics {
+    fn default() -> Self {
+        Self {
+            adc_raw: Gauge::new("adc_raw", "Last raw ADC reading (0-255)."),
+            adc_voltage: Gauge::new("adc_voltage", "Last ADC reading in volts."),
+            button_presses_total: Counter::new("button_presses_total", "Button presses since start."),
+            pwm_duty: Gauge::new("pwm_duty", "Current PWM duty cycle (0-1)."),
+        }
+    }
+}
+
+impl Metrics {
+    /// Serializes the metrics in the Prometheus text exposition format.
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+        self.adc_raw.render(&mut out);
+        self.adc_voltage.render(&mut out);
+        self.button_presses_total.render(&mut out);
+        self.pwm_duty.render(&mut out);
+        out
+    }
+}
+
+/// Serves `metrics` over HTTP on `addr` from a background thread.
+///
+/// Every request gets the current metrics regardless of its path. The
+/// handler only reads atomics, so it never blocks the control loop. The
+/// thread is not joined; it ends with the process.
+pub fn serve(addr: impl ToSocketAddrs, metrics: Arc<Metrics>) -> io::Result<JoinHandle<()>> {
+    let listener = TcpListener::bind(addr)?;
+    Ok(thread::spawn(move || {
+        for stream in listener.incoming() {
+            match stream {
+                Ok(stream) => {
+                    if let Err(e) = respond(stream, &metrics) {
+                        eprintln!("Warning: metrics request failed: {}", e);
+                    }
+                }
+                Err(e) => eprintln!("Warning: metrics connection failed: {}", e),
+            }
+        }
+    }))
+}
+
+fn respond(mut stream: TcpStream, metrics: &Metrics) -> io::Result<()> {
+    // The request itself is not needed; read enough to let the client finish sending
+    let mut request = [0u8; 1024];
+    let _ = stream.read(&mut request)?;
+    let body = metrics.render();
+    write!(
+        stream,
+        "HTTP/1.1 200 OK\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        body.len(),
+        body
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_touched_metrics_are_exported() {
+        let metrics = Metrics::default();
+        assert_eq!(metrics.render(), "");
+        metrics.adc_raw.set(128.0);
+        metrics.button_presses_total.inc();
+        metrics.button_presses_total.inc();
+        assert_eq!(
+            metrics.render(),
+            "# HELP adc_raw Last raw ADC reading (0-255).\n\
+             # TYPE adc_raw gauge\n\
+             adc_raw 128\n\
+             # HELP button_presses_total Button presses since start.\n\
+             # TYPE button_presses_total counter\n\
+             button_presses_total 2\n"
+        );
+    }
+
+    #[test]
+    fn gauge_keeps_the_last_value() {
+        let gauge = Gauge::new("g", "help");
+        gauge.set(0.25);
+        gauge.set(-1.5);
+        assert_eq!(gauge.get(), -1.5);
+    }
+
+    #[test]
+    fn serves_metrics_over_http() {
+        let metrics = Arc::new(Metrics::default());
+        metrics.pwm_duty.set(0.5);
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        drop(listener);
+        serve(addr, metrics).unwrap();
+
+        let mut stream = TcpStream::connect(addr).unwrap();
+        stream.write_all(b"GET /metrics HTTP/1.1\r\n\r\n").unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(response.ends_with("pwm_duty 0.5\n"));
+    }
+}
@@ -0,0 +1,56 @@
+/// GPIO pins with an alternate function that the kit or a common overlay may
+/// already be using, with that function.
+const SPECIAL_PINS: &[(u8, &str)] = &[
+    (0, "ID EEPROM (ID_SD)"),
+    (1, "ID EEPROM (ID_SC)"),
+    (2, "I2C1 SDA, used by the ADC module"),
+    (3, "I2C1 SCL, used by the ADC module"),
+    (7, "SPI0 CE1"),
+    (8, "SPI0 CE0"),
+    (9, "SPI0 MISO"),
+    (10, "SPI0 MOSI"),
+    (11, "SPI0 SCLK"),
+    (14, "UART TXD"),
+    (15, "UART RXD"),
+];
+
+/// The alternate function of `pin` if it is one that can conflict with
+/// plain GPIO use when the matching interface is enabled.
+pub fn special_function(pin: u8) -> Option<&'static str> {
+    SPECIAL_PINS
+        .iter()
+        .find(|&&(p, _)| p == pin)
+        .map(|&(_, function)| function)
+}
+
+/// Prints a warning for every pin in `pins` that doubles as an interface
+/// pin. rppal takes the pin over regardless, so enabling that interface at
+/// the same time makes both misbehave.
+pub fn warn_special_pins(pins: &[u8]) {
+    for &pin in pins {
+        if let Some(function) = special_function(pin) {
+            eprintln!(
+                "Warning: GPIO {} is also {}; it will conflict if that interface is enabled",
+                pin, function
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn i2c_pins_are_special() {
+        assert!(special_function(2).unwrap().contains("SDA"));
+        assert!(special_function(3).unwrap().contains("SCL"));
+    }
+
+    #[test]
+    fn kit_led_pins_are_plain_gpio() {
+        for pin in [17, 18, 27, 22, 23, 24, 25, 5, 6, 12] {
+            assert_eq!(special_function(pin), None, "GPIO {}", pin);
+        }
+    }
+}
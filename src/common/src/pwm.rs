@@ -0,0 +1,176 @@
+use std::thread;
+use std::time::{Duration, Instant};
+
+use rppal::gpio::{Level, OutputPin};
+
+/// A duty in `[0, 1]` as a whole percentage; out-of-range duties are
+/// clamped.
+pub fn duty_percent(duty: f64) -> u8 {
+    (duty.clamp(0.0, 1.0) * 100.0).round() as u8
+}
+
+/// The `duty: 37%` form the examples use when logging a duty.
+pub fn format_duty(duty: f64) -> String {
+    format!("duty: {}%", duty_percent(duty))
+}
+
+/// Start of channel `index`'s PWM period relative to the shared period
+/// start when `count` channels are spread evenly across `period`, so their
+/// rising edges do not coincide and the supply sees one switch-on at a time.
+pub fn phase_offset(index: usize, count: usize, period: Duration) -> Duration {
+    if count == 0 {
+        return Duration::ZERO;
+    }
+    period.mul_f64((index % count) as f64 / count as f64)
+}
+
+/// Number of discrete duty levels a software PWM loop outputs, not counting
+/// "off".
+///
+/// More steps give smoother fades, but each step must still be long enough
+/// for the thread to sleep accurately: with a 10 ms period and Linux sleep
+/// jitter of roughly 100 us, more than about 100 steps buys nothing. A
+/// tick-driven loop is further capped at `period / tick` steps.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PwmResolution(u32);
+
+impl PwmResolution {
+    /// `steps` is at least 1 (plain on/off).
+    pub fn new(steps: u32) -> Self {
+        Self(steps.max(1))
+    }
+
+    pub fn steps(self) -> u32 {
+        self.0
+    }
+
+    /// Rounds a duty in `[0, 1]` to the nearest level this resolution can
+    /// output; out-of-range duties are clamped.
+    pub fn quantize(self, duty: f64) -> f64 {
+        let steps = self.0 as f64;
+        (duty.clamp(0.0, 1.0) * steps).round() / steps
+    }
+}
+
+/// Start of the period to output next when the previous one was meant to
+/// start at `cycle_start`. A thread that fell more than a period behind
+/// skips whole periods, so it stays in phase with other channels instead of
+/// rushing through the backlog.
+pub fn catch_up(cycle_start: Instant, now: Instant, period: Duration) -> Instant {
+    let mut start = cycle_start;
+    if !period.is_zero() {
+        while start + period < now {
+            start += period;
+        }
+    }
+    start
+}
+
+/// Outputs one software PWM period beginning at `cycle_start`: `on` for
+/// `on_time`, then `off` for the rest of `period`.
+///
+/// The thread sleeps through each segment instead of waking on a fixed
+/// tick, so it wakes at most twice a period whatever the duty resolution.
+/// Returns the start of the following period.
+pub fn output_period(
+    pin: &mut OutputPin,
+    cycle_start: Instant,
+    period: Duration,
+    on_time: Duration,
+    on: Level,
+    off: Level,
+) -> Instant {
+    thread::sleep(cycle_start.saturating_duration_since(Instant::now()));
+    let cycle_start = catch_up(cycle_start, Instant::now(), period);
+    if !on_time.is_zero() {
+        pin.write(on);
+        thread::sleep((cycle_start + on_time).saturating_duration_since(Instant::now()));
+    }
+    if on_time < period {
+        pin.write(off);
+    }
+    cycle_start + period
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PERIOD: Duration = Duration::from_millis(10);
+
+    #[test]
+    fn catch_up_keeps_a_punctual_start() {
+        let start = Instant::now();
+        assert_eq!(catch_up(start, start + Duration::from_millis(3), PERIOD), start);
+        assert_eq!(catch_up(start, start + PERIOD, PERIOD), start);
+    }
+
+    #[test]
+    fn catch_up_skips_whole_missed_periods() {
+        let start = Instant::now();
+        let now = start + Duration::from_millis(35);
+        assert_eq!(catch_up(start, now, PERIOD), start + Duration::from_millis(30));
+    }
+
+    #[test]
+    fn catch_up_with_zero_period_returns_start() {
+        let start = Instant::now();
+        assert_eq!(catch_up(start, start + PERIOD, Duration::ZERO), start);
+    }
+
+    #[test]
+    fn quantize_rounds_to_the_nearest_level() {
+        let quarters = PwmResolution::new(4);
+        assert_eq!(quarters.quantize(0.124), 0.0);
+        assert_eq!(quarters.quantize(0.125), 0.25);
+        assert_eq!(quarters.quantize(0.375), 0.5);
+        assert_eq!(quarters.quantize(0.874), 0.75);
+        assert_eq!(quarters.quantize(0.875), 1.0);
+        // Zero steps still gives plain on/off
+        assert_eq!(PwmResolution::new(0).quantize(0.49), 0.0);
+        assert_eq!(PwmResolution::new(0).quantize(0.5), 1.0);
+    }
+
+    #[test]
+    fn quantize_clamps_out_of_range_duties() {
+        let resolution = PwmResolution::new(100);
+        assert_eq!(resolution.quantize(0.0), 0.0);
+        assert_eq!(resolution.quantize(1.0), 1.0);
+        assert_eq!(resolution.quantize(-0.5), 0.0);
+        assert_eq!(resolution.quantize(1.5), 1.0);
+    }
+
+    #[test]
+    fn duty_percent_rounds_and_clamps() {
+        assert_eq!(duty_percent(0.0), 0);
+        assert_eq!(duty_percent(1.0), 100);
+        assert_eq!(duty_percent(0.375), 38);
+        assert_eq!(duty_percent(-0.2), 0);
+        assert_eq!(duty_percent(1.7), 100);
+    }
+
+    #[test]
+    fn format_duty_shows_whole_percent() {
+        assert_eq!(format_duty(0.0), "duty: 0%");
+        assert_eq!(format_duty(1.0), "duty: 100%");
+        assert_eq!(format_duty(0.374), "duty: 37%");
+        assert_eq!(format_duty(2.0), "duty: 100%");
+    }
+
+    #[test]
+    fn phase_offsets_spread_channels_across_the_period() {
+        assert_eq!(phase_offset(0, 4, PERIOD), Duration::ZERO);
+        assert_eq!(phase_offset(1, 4, PERIOD), Duration::from_micros(2500));
+        assert_eq!(phase_offset(3, 4, PERIOD), Duration::from_micros(7500));
+        // Indices past the last channel wrap around
+        assert_eq!(phase_offset(4, 4, PERIOD), Duration::ZERO);
+        assert_eq!(phase_offset(5, 4, PERIOD), phase_offset(1, 4, PERIOD));
+    }
+
+    #[test]
+    fn single_or_no_channel_has_no_offset() {
+        assert_eq!(phase_offset(0, 1, PERIOD), Duration::ZERO);
+        assert_eq!(phase_offset(3, 1, PERIOD), Duration::ZERO);
+        assert_eq!(phase_offset(2, 0, PERIOD), Duration::ZERO);
+    }
+}
@@ -0,0 +1,78 @@
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// How long to sleep so an iteration that started at `iteration_start`
+/// lasts `period`. Zero if the iteration already took that long.
+pub fn remaining_sleep(iteration_start: Instant, period: Duration, now: Instant) -> Duration {
+    period.saturating_sub(now.saturating_duration_since(iteration_start))
+}
+
+/// Caps how often a polling loop runs, so it does not spin a CPU core.
+///
+/// Call `wait` once at the end of every iteration. Iterations that are
+/// slower than the cap are not delayed further.
+pub struct LoopRateLimiter {
+    period: Duration,
+    iteration_start: Instant,
+}
+
+impl LoopRateLimiter {
+    /// Limits the loop to `hz` iterations per second.
+    ///
+    /// Fails for NaN, non-positive rates, and rates so low that one period
+    /// does not fit in a `Duration`. The message completes a sentence
+    /// starting with the option name, e.g. "--loop-hz must be positive".
+    pub fn new(hz: f64) -> Result<Self, String> {
+        if hz.is_nan() || hz <= 0.0 {
+            return Err(format!("must be positive, got {}", hz));
+        }
+        let period = Duration::try_from_secs_f64(1.0 / hz)
+            .map_err(|_| format!("is too low to time a loop, got {}", hz))?;
+        Ok(Self {
+            period,
+            iteration_start: Instant::now(),
+        })
+    }
+
+    /// Sleeps out the rest of the current iteration and starts the next one.
+    pub fn wait(&mut self) {
+        thread::sleep(remaining_sleep(self.iteration_start, self.period, Instant::now()));
+        self.iteration_start = Instant::now();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn remaining_sleep_tops_up_a_short_iteration() {
+        let start = Instant::now();
+        let now = start + Duration::from_millis(30);
+        assert_eq!(remaining_sleep(start, Duration::from_millis(100), now), Duration::from_millis(70));
+    }
+
+    #[test]
+    fn remaining_sleep_is_zero_for_a_slow_iteration() {
+        let start = Instant::now();
+        let now = start + Duration::from_millis(150);
+        assert_eq!(remaining_sleep(start, Duration::from_millis(100), now), Duration::ZERO);
+    }
+
+    #[test]
+    fn new_sets_the_period_from_the_rate() {
+        assert_eq!(LoopRateLimiter::new(50.0).unwrap().period, Duration::from_millis(20));
+    }
+
+    #[test]
+    fn new_rejects_non_positive_and_nan_rates() {
+        assert!(LoopRateLimiter::new(0.0).is_err());
+        assert!(LoopRateLimiter::new(-5.0).is_err());
+        assert!(LoopRateLimiter::new(f64::NAN).is_err());
+    }
+
+    #[test]
+    fn new_rejects_rates_too_low_for_a_duration() {
+        assert!(LoopRateLimiter::new(1e-300).is_err());
+    }
+}
@@ -0,0 +1,179 @@
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use rppal::gpio::Level;
+
+use crate::button::{self, ButtonEvent, ButtonLogic, DebounceStrategy, Debouncer};
+
+/// One raw pin transition, timed from the start of the recording.
+pub type Transition = (Duration, Level);
+
+/// Formats a transition as one line of a recording: `<microseconds> high|low`.
+pub fn format_transition((at, level): Transition) -> String {
+    let level = match level {
+        Level::High => "high",
+        Level::Low => "low",
+    };
+    format!("{} {}", at.as_micros(), level)
+}
+
+/// Parses a line written by [`format_transition`].
+pub fn parse_transition(line: &str) -> Option<Transition> {
+    let mut parts = line.split_whitespace();
+    let micros: u64 = parts.next()?.parse().ok()?;
+    let level = match parts.next()? {
+        "high" => Level::High,
+        "low" => Level::Low,
+        _ => return None,
+    };
+    if parts.next().is_some() {
+        return None;
+    }
+    Some((Duration::from_micros(micros), level))
+}
+
+/// Reads a recording, rejecting it at the first malformed line. Blank lines
+/// and `#` comments are skipped so a recording can be annotated before it is
+/// shared.
+pub fn load(path: &Path) -> io::Result<Vec<Transition>> {
+    let text = fs::read_to_string(path)?;
+    let mut transitions = Vec::new();
+    for (number, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let transition = parse_transition(line).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "{}:{}: expected '<microseconds> high|low', got '{}'",
+                    path.display(),
+                    number + 1,
+                    line
+                ),
+            )
+        })?;
+        transitions.push(transition);
+    }
+    Ok(transitions)
+}
+
+/// Writes every change of a pin's level to a recording file.
+///
+/// Samples that repeat the previous level are dropped, so the file only
+/// holds transitions (plus the initial level).
+pub struct Recorder {
+    writer: BufWriter<File>,
+    start: Instant,
+    last: Option<Level>,
+}
+
+impl Recorder {
+    pub fn create(path: &Path, start: Instant) -> io::Result<Self> {
+        Ok(Self {
+            writer: BufWriter::new(File::create(path)?),
+            start,
+            last: None,
+        })
+    }
+
+    /// Records `level` sampled at `now` if it differs from the last one.
+    pub fn sample(&mut self, level: Level, now: Instant) -> io::Result<()> {
+        if self.last == Some(level) {
+            return Ok(());
+        }
+        self.last = Some(level);
+        let at = now.saturating_duration_since(self.start);
+        writeln!(self.writer, "{}", format_transition((at, level)))
+    }
+
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+}
+
+/// Runs a recording through a [`Debouncer`] the way
+/// [`ButtonEvents`](crate::button::ButtonEvents) would have seen it on the
+/// pin, and returns the debounced events with their times.
+///
+/// Between transitions the held level is re-sampled every `poll`, as the
+/// live loop does on its poll timeouts, so a change that landed inside the
+/// debounce window is still picked up afterwards.
+pub fn replay(
+    transitions: &[Transition],
+    logic: ButtonLogic,
+    debounce: Duration,
+    strategy: DebounceStrategy,
+    poll: Duration,
+) -> Vec<(Duration, ButtonEvent)> {
+    // The debouncer only needs instants that are ordered and spaced like
+    // the recording, so any base works
+    let base = Instant::now();
+    // Like the live loop, start from the first recorded level
+    let initial = transitions.first().is_some_and(|&(_, level)| button::is_pressed(level, logic));
+    let mut debouncer = Debouncer::with_state(debounce, initial).with_strategy(strategy);
+    let mut events = Vec::new();
+    for (i, &(at, level)) in transitions.iter().enumerate() {
+        let pressed = button::is_pressed(level, logic);
+        // Re-sample until the next transition, and for one window plus a poll
+        // after the last so an integrating debouncer can settle too
+        let until = transitions.get(i + 1).map_or(at + debounce + poll, |&(next, _)| next);
+        let mut t = at;
+        loop {
+            if let Some(event) = debouncer.update(pressed, base + t) {
+                events.push((t, event));
+            }
+            t += poll.max(Duration::from_micros(1));
+            if t >= until {
+                break;
+            }
+        }
+    }
+    events
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ms(ms: u64) -> Duration {
+        Duration::from_millis(ms)
+    }
+
+    #[test]
+    fn transitions_round_trip() {
+        for transition in [(Duration::ZERO, Level::High), (Duration::from_micros(1234567), Level::Low)] {
+            assert_eq!(parse_transition(&format_transition(transition)), Some(transition));
+        }
+        assert_eq!(format_transition((ms(1), Level::Low)), "1000 low");
+    }
+
+    #[test]
+    fn malformed_lines_are_rejected() {
+        for line in ["", "12", "12 up", "x high", "12 high extra", "-5 low"] {
+            assert_eq!(parse_transition(line), None, "{:?}", line);
+        }
+    }
+
+    #[test]
+    fn bounce_yields_a_single_press_and_release() {
+        // Active-low button bouncing on press and on release
+        let transitions = [
+            (ms(0), Level::High),
+            (ms(100), Level::Low),
+            (ms(101), Level::High),
+            (ms(102), Level::Low),
+            (ms(300), Level::High),
+            (ms(301), Level::Low),
+            (ms(302), Level::High),
+        ];
+        let events = replay(&transitions, ButtonLogic::ActiveLow, ms(20), DebounceStrategy::Window, ms(1));
+        let kinds: Vec<_> = events.iter().map(|&(_, event)| event).collect();
+        assert_eq!(kinds, [ButtonEvent::Pressed, ButtonEvent::Released]);
+        assert!(events[0].0 >= ms(100) && events[0].0 < ms(300));
+        assert!(events[1].0 >= ms(300));
+    }
+}
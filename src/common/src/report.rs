@@ -0,0 +1,73 @@
+use std::time::{Duration, Instant};
+
+use crate::throttle::Throttle;
+
+/// Counts events and produces a periodic one-line summary such as
+/// "120 blinks in 60s", so long runs don't need per-event output.
+pub struct Reporter {
+    throttle: Option<Throttle>,
+    label: &'static str,
+    count: u64,
+    window_start: Instant,
+}
+
+impl Reporter {
+    /// A zero `interval` disables reporting.
+    pub fn new(interval: Duration, label: &'static str, now: Instant) -> Self {
+        let throttle = (!interval.is_zero()).then(|| {
+            let mut throttle = Throttle::new(interval);
+            // Consume the immediate first emit so the first summary comes
+            // after a full interval
+            throttle.should_emit(now);
+            throttle
+        });
+        Self {
+            throttle,
+            label,
+            count: 0,
+            window_start: now,
+        }
+    }
+
+    pub fn record(&mut self) {
+        self.count += 1;
+    }
+
+    /// Returns the summary for the finished interval, if one is due, and
+    /// starts counting the next one.
+    pub fn poll(&mut self, now: Instant) -> Option<String> {
+        if !self.throttle.as_mut()?.should_emit(now) {
+            return None;
+        }
+        let elapsed = now.saturating_duration_since(self.window_start);
+        let summary = format!("{} {} in {}s", self.count, self.label, elapsed.as_secs());
+        self.count = 0;
+        self.window_start = now;
+        Some(summary)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn summarizes_each_interval() {
+        let start = Instant::now();
+        let mut reporter = Reporter::new(Duration::from_secs(60), "blinks", start);
+        reporter.record();
+        reporter.record();
+        assert_eq!(reporter.poll(start + Duration::from_secs(30)), None);
+        assert_eq!(reporter.poll(start + Duration::from_secs(60)).as_deref(), Some("2 blinks in 60s"));
+        // The count restarts for the next interval
+        assert_eq!(reporter.poll(start + Duration::from_secs(120)).as_deref(), Some("0 blinks in 60s"));
+    }
+
+    #[test]
+    fn zero_interval_disables_reports() {
+        let start = Instant::now();
+        let mut reporter = Reporter::new(Duration::ZERO, "blinks", start);
+        reporter.record();
+        assert_eq!(reporter.poll(start + Duration::from_secs(3600)), None);
+    }
+}
@@ -0,0 +1,106 @@
+use std::time::Duration;
+
+/// Counts consecutive failures and signals when it is time to recover
+/// (e.g. reopen a device) instead of retrying the same handle forever.
+pub struct FailureCounter {
+    threshold: u32,
+    consecutive: u32,
+}
+
+impl FailureCounter {
+    /// `threshold` of 0 disables recovery: `record_failure` never fires.
+    pub fn new(threshold: u32) -> Self {
+        Self {
+            threshold,
+            consecutive: 0,
+        }
+    }
+
+    pub fn record_success(&mut self) {
+        self.consecutive = 0;
+    }
+
+    /// Records a failure and returns `true` once `threshold` failures have
+    /// happened in a row. The count restarts so the next recovery attempt
+    /// waits for another full run of failures.
+    pub fn record_failure(&mut self) -> bool {
+        self.consecutive += 1;
+        if self.threshold > 0 && self.consecutive >= self.threshold {
+            self.consecutive = 0;
+            return true;
+        }
+        false
+    }
+
+    pub fn consecutive(&self) -> u32 {
+        self.consecutive
+    }
+}
+
+/// Exponential backoff between retries: each delay doubles, up to `max`.
+pub struct Backoff {
+    initial: Duration,
+    max: Duration,
+    next: Duration,
+}
+
+impl Backoff {
+    pub fn new(initial: Duration, max: Duration) -> Self {
+        Self {
+            initial,
+            max,
+            next: initial,
+        }
+    }
+
+    /// Returns the delay to wait before the next attempt and doubles it.
+    pub fn next_delay(&mut self) -> Duration {
+        let delay = self.next;
+        self.next = (self.next * 2).min(self.max);
+        delay
+    }
+
+    /// Starts over from the initial delay, e.g. after a success.
+    pub fn reset(&mut self) {
+        self.next = self.initial;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fires_after_threshold_failures_in_a_row() {
+        let mut counter = FailureCounter::new(3);
+        assert!(!counter.record_failure());
+        assert!(!counter.record_failure());
+        assert!(counter.record_failure());
+        // The count restarts after firing
+        assert_eq!(counter.consecutive(), 0);
+        assert!(!counter.record_failure());
+    }
+
+    #[test]
+    fn success_resets_the_run() {
+        let mut counter = FailureCounter::new(2);
+        counter.record_failure();
+        counter.record_success();
+        assert!(!counter.record_failure());
+    }
+
+    #[test]
+    fn zero_threshold_never_fires() {
+        let mut counter = FailureCounter::new(0);
+        assert!((0..10).all(|_| !counter.record_failure()));
+    }
+
+    #[test]
+    fn backoff_doubles_up_to_max() {
+        let mut backoff = Backoff::new(Duration::from_millis(100), Duration::from_millis(500));
+        let delays: Vec<_> = (0..5).map(|_| backoff.next_delay().as_millis()).collect();
+        assert_eq!(delays, [100, 200, 400, 500, 500]);
+        backoff.reset();
+        assert_eq!(backoff.next_delay(), Duration::from_millis(100));
+    }
+}
@@ -0,0 +1,191 @@
+use std::io;
+use std::panic::{self, PanicHookInfo};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+
+use signal_hook::consts::{SIGHUP, SIGINT, SIGTERM};
+use signal_hook::iterator::Signals;
+
+use crate::logger;
+
+/// Replaces the default panic output with a one-line report, logged at
+/// error level, and clears `running`, so the other threads leave their
+/// loops and run their cleanup (turning LEDs and buzzers off) instead of
+/// being left active.
+pub fn install_panic_hook(running: Arc<AtomicBool>) {
+    logger::init();
+    panic::set_hook(Box::new(move |info| {
+        log::error!("{}", panic_report(info));
+        running.store(false, Ordering::SeqCst);
+    }));
+}
+
+fn panic_report(info: &PanicHookInfo<'_>) -> String {
+    let thread = std::thread::current();
+    let thread = thread.name().unwrap_or("<unnamed>");
+    let message = info
+        .payload()
+        .downcast_ref::<&str>()
+        .copied()
+        .or_else(|| info.payload().downcast_ref::<String>().map(String::as_str))
+        .unwrap_or("<non-string panic payload>");
+    match info.location() {
+        Some(loc) => format!(
+            "Thread '{}' panicked at {}:{}: {}; shutting down",
+            thread,
+            loc.file(),
+            loc.line(),
+            message
+        ),
+        None => format!("Thread '{}' panicked: {}; shutting down", thread, message),
+    }
+}
+
+/// What the program does when it receives a signal.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SignalAction {
+    /// Leave the main loop and run the usual cleanup.
+    Stop,
+    /// Re-read the `--config` file and carry on.
+    Reload,
+}
+
+/// The action for `signal`. SIGINT (Ctrl+C) and SIGTERM (a systemd stop)
+/// always stop; SIGHUP reloads when the program `reloads` its config and
+/// otherwise stops, as it would when the terminal closes. Other signals
+/// are not handled.
+pub fn signal_action(signal: i32, reloads: bool) -> Option<SignalAction> {
+    match signal {
+        SIGINT | SIGTERM => Some(SignalAction::Stop),
+        SIGHUP if reloads => Some(SignalAction::Reload),
+        SIGHUP => Some(SignalAction::Stop),
+        _ => None,
+    }
+}
+
+/// The logic run for each signal, kept apart from the thread that waits
+/// for them.
+pub struct SignalHandler<F> {
+    on_stop: F,
+    reload: Option<Arc<AtomicBool>>,
+}
+
+impl<F: FnMut()> SignalHandler<F> {
+    /// Calls `on_stop` for a stop; with a `reload` flag, SIGHUP sets it
+    /// instead.
+    pub fn new(on_stop: F, reload: Option<Arc<AtomicBool>>) -> Self {
+        Self { on_stop, reload }
+    }
+
+    pub fn handle(&mut self, signal: i32) {
+        match signal_action(signal, self.reload.is_some()) {
+            Some(SignalAction::Stop) => (self.on_stop)(),
+            Some(SignalAction::Reload) => {
+                if let Some(reload) = &self.reload {
+                    reload.store(true, Ordering::SeqCst);
+                }
+            }
+            None => {}
+        }
+    }
+}
+
+/// Calls `on_stop` on Ctrl+C, SIGTERM and SIGHUP, so a service stop or a
+/// closed terminal runs the same cleanup as Ctrl+C. The handler runs on a
+/// background thread, not in signal context, so it may print.
+pub fn set_handler(on_stop: impl FnMut() + Send + 'static) -> io::Result<()> {
+    spawn_signal_thread(SignalHandler::new(on_stop, None))
+}
+
+/// Like [`set_handler`], but SIGHUP sets `reload` instead of stopping. The
+/// main loop should `swap` it back to `false` and re-read its settings.
+pub fn set_handler_with_reload(
+    on_stop: impl FnMut() + Send + 'static,
+    reload: Arc<AtomicBool>,
+) -> io::Result<()> {
+    spawn_signal_thread(SignalHandler::new(on_stop, Some(reload)))
+}
+
+fn spawn_signal_thread<F: FnMut() + Send + 'static>(mut handler: SignalHandler<F>) -> io::Result<()> {
+    let mut signals = Signals::new([SIGINT, SIGTERM, SIGHUP])?;
+    thread::Builder::new().name("signals".to_string()).spawn(move || {
+        for signal in signals.forever() {
+            handler.handle(signal);
+        }
+    })?;
+    Ok(())
+}
+
+/// What an example does with its outputs when it exits.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum CleanupPolicy {
+    /// Drive outputs off (the default).
+    #[default]
+    TurnOff,
+    /// Leave outputs as they are, e.g. a lamp that should stay lit while the
+    /// program is restarted. Interrupts are still cleared.
+    KeepState,
+}
+
+impl CleanupPolicy {
+    pub fn from_keep_flag(keep_on_exit: bool) -> Self {
+        if keep_on_exit {
+            CleanupPolicy::KeepState
+        } else {
+            CleanupPolicy::TurnOff
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn counting_handler(reload: Option<Arc<AtomicBool>>) -> (SignalHandler<impl FnMut()>, Arc<AtomicBool>) {
+        let stopped = Arc::new(AtomicBool::new(false));
+        let s = stopped.clone();
+        (SignalHandler::new(move || s.store(true, Ordering::SeqCst), reload), stopped)
+    }
+
+    #[test]
+    fn interrupt_and_terminate_stop() {
+        assert_eq!(signal_action(SIGINT, true), Some(SignalAction::Stop));
+        assert_eq!(signal_action(SIGTERM, true), Some(SignalAction::Stop));
+        assert_eq!(signal_action(SIGTERM, false), Some(SignalAction::Stop));
+    }
+
+    #[test]
+    fn hangup_reloads_only_when_supported() {
+        assert_eq!(signal_action(SIGHUP, true), Some(SignalAction::Reload));
+        assert_eq!(signal_action(SIGHUP, false), Some(SignalAction::Stop));
+    }
+
+    #[test]
+    fn other_signals_are_ignored() {
+        assert_eq!(signal_action(signal_hook::consts::SIGUSR1, true), None);
+    }
+
+    #[test]
+    fn handler_stops_on_terminate() {
+        let (mut handler, stopped) = counting_handler(None);
+        handler.handle(SIGTERM);
+        assert!(stopped.load(Ordering::SeqCst));
+    }
+
+    #[test]
+    fn handler_sets_reload_flag_on_hangup_without_stopping() {
+        let reload = Arc::new(AtomicBool::new(false));
+        let (mut handler, stopped) = counting_handler(Some(reload.clone()));
+        handler.handle(SIGHUP);
+        assert!(reload.load(Ordering::SeqCst));
+        assert!(!stopped.load(Ordering::SeqCst));
+    }
+
+    #[test]
+    fn cleanup_turns_off_unless_asked_to_keep_state() {
+        assert_eq!(CleanupPolicy::default(), CleanupPolicy::TurnOff);
+        assert_eq!(CleanupPolicy::from_keep_flag(false), CleanupPolicy::TurnOff);
+        assert_eq!(CleanupPolicy::from_keep_flag(true), CleanupPolicy::KeepState);
+    }
+}
@@ -0,0 +1,62 @@
+/// Exponential moving average: each sample moves the output `alpha` of the
+/// way towards it. Smaller `alpha` smooths more but lags more.
+pub struct Ema {
+    alpha: f64,
+    value: Option<f64>,
+}
+
+impl Ema {
+    /// `alpha` is clamped to `(0, 1]`; 1 disables smoothing.
+    pub fn new(alpha: f64) -> Self {
+        Self {
+            alpha: alpha.clamp(f64::MIN_POSITIVE, 1.0),
+            value: None,
+        }
+    }
+
+    /// Feeds a sample and returns the smoothed value. The first sample is
+    /// taken as is.
+    pub fn update(&mut self, sample: f64) -> f64 {
+        let value = match self.value {
+            Some(value) => value + self.alpha * (sample - value),
+            None => sample,
+        };
+        self.value = Some(value);
+        value
+    }
+
+    pub fn value(&self) -> Option<f64> {
+        self.value
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn first_sample_is_taken_as_is() {
+        let mut ema = Ema::new(0.2);
+        assert_eq!(ema.value(), None);
+        assert_eq!(ema.update(100.0), 100.0);
+    }
+
+    #[test]
+    fn later_samples_move_alpha_of_the_way() {
+        let mut ema = Ema::new(0.25);
+        ema.update(0.0);
+        assert_eq!(ema.update(100.0), 25.0);
+        assert_eq!(ema.update(100.0), 43.75);
+    }
+
+    #[test]
+    fn alpha_is_clamped() {
+        let mut unsmoothed = Ema::new(5.0);
+        unsmoothed.update(0.0);
+        assert_eq!(unsmoothed.update(80.0), 80.0);
+        // A zero alpha would freeze the output; it still moves, if barely
+        let mut slow = Ema::new(0.0);
+        slow.update(0.0);
+        assert!(slow.update(1.0) > 0.0);
+    }
+}
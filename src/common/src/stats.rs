@@ -0,0 +1,165 @@
+use std::collections::VecDeque;
+use std::time::Duration;
+
+/// Min, max and mean over the most recent `capacity` readings.
+pub struct RollingStats {
+    window: VecDeque<f64>,
+    capacity: usize,
+    sum: f64,
+}
+
+impl RollingStats {
+    /// `capacity` is at least 1.
+    pub fn new(capacity: usize) -> Self {
+        let capacity = capacity.max(1);
+        Self {
+            window: VecDeque::with_capacity(capacity),
+            capacity,
+            sum: 0.0,
+        }
+    }
+
+    /// Adds a reading, evicting the oldest one once the window is full.
+    pub fn push(&mut self, value: f64) {
+        if self.window.len() == self.capacity
+            && let Some(oldest) = self.window.pop_front()
+        {
+            self.sum -= oldest;
+        }
+        self.window.push_back(value);
+        self.sum += value;
+    }
+
+    pub fn len(&self) -> usize {
+        self.window.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.window.is_empty()
+    }
+
+    pub fn min(&self) -> Option<f64> {
+        self.window.iter().copied().reduce(f64::min)
+    }
+
+    pub fn max(&self) -> Option<f64> {
+        self.window.iter().copied().reduce(f64::max)
+    }
+
+    pub fn mean(&self) -> Option<f64> {
+        if self.window.is_empty() {
+            None
+        } else {
+            Some(self.sum / self.window.len() as f64)
+        }
+    }
+
+    /// `min/max/mean` of the window for a status line, or `-` when empty.
+    pub fn summary(&self) -> String {
+        match (self.min(), self.max(), self.mean()) {
+            (Some(min), Some(max), Some(mean)) => {
+                format!("min {:.0} max {:.0} mean {:.1} (n={})", min, max, mean, self.len())
+            }
+            _ => "-".to_string(),
+        }
+    }
+}
+
+/// Running min, max and mean of durations, e.g. from an input edge to the
+/// output it caused. Keeps only totals, so recording costs nothing that
+/// would skew the measurement.
+#[derive(Clone, Debug, Default)]
+pub struct LatencyStats {
+    count: u32,
+    total: Duration,
+    min: Option<Duration>,
+    max: Duration,
+}
+
+impl LatencyStats {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn record(&mut self, latency: Duration) {
+        self.count += 1;
+        self.total += latency;
+        self.min = Some(self.min.map_or(latency, |min| min.min(latency)));
+        self.max = self.max.max(latency);
+    }
+
+    pub fn count(&self) -> u32 {
+        self.count
+    }
+
+    pub fn min(&self) -> Option<Duration> {
+        self.min
+    }
+
+    pub fn max(&self) -> Option<Duration> {
+        self.min.map(|_| self.max)
+    }
+
+    pub fn mean(&self) -> Option<Duration> {
+        (self.count > 0).then(|| self.total / self.count)
+    }
+
+    /// `min/max/mean` in microseconds for printing on exit, or a note that
+    /// nothing was recorded.
+    pub fn summary(&self) -> String {
+        match (self.min(), self.max(), self.mean()) {
+            (Some(min), Some(max), Some(mean)) => format!(
+                "min {} us max {} us mean {} us (n={})",
+                min.as_micros(),
+                max.as_micros(),
+                mean.as_micros(),
+                self.count
+            ),
+            _ => "no samples".to_string(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rolling_window_evicts_oldest() {
+        let mut stats = RollingStats::new(3);
+        assert!(stats.is_empty());
+        assert_eq!(stats.summary(), "-");
+        for value in [10.0, 20.0, 30.0, 40.0] {
+            stats.push(value);
+        }
+        assert_eq!(stats.len(), 3);
+        assert_eq!(stats.min(), Some(20.0));
+        assert_eq!(stats.max(), Some(40.0));
+        assert_eq!(stats.mean(), Some(30.0));
+        assert_eq!(stats.summary(), "min 20 max 40 mean 30.0 (n=3)");
+    }
+
+    #[test]
+    fn zero_capacity_keeps_one_reading() {
+        let mut stats = RollingStats::new(0);
+        stats.push(1.0);
+        stats.push(2.0);
+        assert_eq!(stats.len(), 1);
+        assert_eq!(stats.mean(), Some(2.0));
+    }
+
+    #[test]
+    fn latency_totals() {
+        let mut stats = LatencyStats::new();
+        assert_eq!(stats.max(), None);
+        assert_eq!(stats.summary(), "no samples");
+        for us in [300, 100, 200] {
+            stats.record(Duration::from_micros(us));
+        }
+        assert_eq!(stats.count(), 3);
+        assert_eq!(stats.min(), Some(Duration::from_micros(100)));
+        assert_eq!(stats.max(), Some(Duration::from_micros(300)));
+        assert_eq!(stats.mean(), Some(Duration::from_micros(200)));
+        assert_eq!(stats.summary(), "min 100 us max 300 us mean 200 us (n=3)");
+    }
+}
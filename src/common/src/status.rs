@@ -0,0 +1,34 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use crate::error::{AppError, open_gpio};
+use crate::led::{Led, LedPolarity};
+use crate::worker;
+
+// 1 Hz blink: half a second on, half off
+const HALF_PERIOD: Duration = Duration::from_millis(500);
+// How often the thread checks `running` while waiting
+const POLL: Duration = Duration::from_millis(50);
+
+/// Blinks the LED on `pin` at 1 Hz on a background thread, so a headless
+/// example shows it is alive. The thread stops and turns the LED off once
+/// `running` is cleared; join it with `worker::join`.
+pub fn spawn_status_led(pin: u8, running: Arc<AtomicBool>) -> Result<JoinHandle<()>, AppError> {
+    let init = move || {
+        let pin = open_gpio()?.get(pin)?.into_output_low();
+        Ok(Led::new(pin, LedPolarity::ActiveHigh))
+    };
+    worker::spawn_initialized("status LED", init, move |mut led| {
+        while running.load(Ordering::SeqCst) {
+            led.toggle();
+            let mut waited = Duration::ZERO;
+            while waited < HALF_PERIOD && running.load(Ordering::SeqCst) {
+                thread::sleep(POLL);
+                waited += POLL;
+            }
+        }
+        led.off();
+    })
+}
@@ -0,0 +1,82 @@
+use std::env;
+use std::ffi::OsStr;
+use std::io::{self, IsTerminal};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+const GREEN: &str = "\x1b[32m";
+const RED: &str = "\x1b[31m";
+const RESET: &str = "\x1b[0m";
+
+// Set once by `init`; plain text until then
+static ENABLED: AtomicBool = AtomicBool::new(false);
+// Set by `Args::finish` for `--quiet`
+static QUIET: AtomicBool = AtomicBool::new(false);
+
+/// Whether human output should use ANSI colours: only on a terminal, and
+/// not when `--no-color` was given or `NO_COLOR` is set to a non-empty value
+/// (see no-color.org).
+pub fn should_colorize(stdout_is_tty: bool, no_color_flag: bool, no_color_env: Option<&OsStr>) -> bool {
+    stdout_is_tty && !no_color_flag && no_color_env.is_none_or(OsStr::is_empty)
+}
+
+/// Decides once per process, from stdout, `--no-color` and `NO_COLOR`,
+/// whether `on` and `off` add colour.
+pub fn init(no_color_flag: bool) {
+    let enabled = should_colorize(
+        io::stdout().is_terminal(),
+        no_color_flag,
+        env::var_os("NO_COLOR").as_deref(),
+    );
+    ENABLED.store(enabled, Ordering::Relaxed);
+}
+
+/// Text for something turning on, in green when colour is enabled.
+pub fn on(text: &str) -> String {
+    paint(GREEN, text)
+}
+
+/// Text for something turning off, in red when colour is enabled.
+pub fn off(text: &str) -> String {
+    paint(RED, text)
+}
+
+fn paint(color: &str, text: &str) -> String {
+    if ENABLED.load(Ordering::Relaxed) {
+        format!("{}{}{}", color, text, RESET)
+    } else {
+        text.to_string()
+    }
+}
+
+/// Suppresses [`banner`] lines for the rest of the process.
+pub fn set_quiet(quiet: bool) {
+    QUIET.store(quiet, Ordering::Relaxed);
+}
+
+pub fn is_quiet() -> bool {
+    QUIET.load(Ordering::Relaxed)
+}
+
+/// Prints a decorative line such as "Program is starting...". These are
+/// the lines `--quiet` drops; readings, state changes, warnings and errors
+/// are printed directly and are unaffected.
+pub fn banner(text: &str) {
+    if !is_quiet() {
+        println!("{}", text);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn colour_only_on_a_terminal_without_opt_out() {
+        assert!(should_colorize(true, false, None));
+        assert!(!should_colorize(false, false, None));
+        assert!(!should_colorize(true, true, None));
+        assert!(!should_colorize(true, false, Some(OsStr::new("1"))));
+        // An empty NO_COLOR does not disable colour
+        assert!(should_colorize(true, false, Some(OsStr::new(""))));
+    }
+}
@@ -0,0 +1,108 @@
+use std::time::{Duration, Instant};
+
+/// A threshold with hysteresis, so a reading hovering around the trip point
+/// does not flip the state on every sample.
+///
+/// The state goes active once a reading reaches `high` and only goes
+/// inactive again once it falls to `low` or below. With a minimum hold
+/// (see [`Hysteresis::with_min_hold`]) it must also stay there that long,
+/// so brief dips do not clear it.
+#[derive(Clone, Debug)]
+pub struct Hysteresis {
+    low: f64,
+    high: f64,
+    min_hold: Duration,
+    active: bool,
+    // When the current run of readings at or below `low` started
+    below_since: Option<Instant>,
+}
+
+impl Hysteresis {
+    /// A threshold at `level` with a dead band of `band` below it. Starts
+    /// inactive.
+    pub fn new(level: f64, band: f64) -> Self {
+        Self {
+            low: level - band.abs(),
+            high: level,
+            min_hold: Duration::ZERO,
+            active: false,
+            below_since: None,
+        }
+    }
+
+    /// Only clears once readings have stayed at or below the low threshold
+    /// for `min_hold`.
+    pub fn with_min_hold(mut self, min_hold: Duration) -> Self {
+        self.min_hold = min_hold;
+        self
+    }
+
+    /// Feeds one reading taken at `now`; returns the new state when it
+    /// changes.
+    pub fn update(&mut self, value: f64, now: Instant) -> Option<bool> {
+        if !self.active {
+            if value >= self.high {
+                self.active = true;
+                return Some(true);
+            }
+            return None;
+        }
+        if value > self.low {
+            self.below_since = None;
+            return None;
+        }
+        let since = *self.below_since.get_or_insert(now);
+        if now.saturating_duration_since(since) < self.min_hold {
+            return None;
+        }
+        self.active = false;
+        self.below_since = None;
+        Some(false)
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.active
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ms(start: Instant, ms: u64) -> Instant {
+        start + Duration::from_millis(ms)
+    }
+
+    #[test]
+    fn trips_at_the_high_threshold() {
+        let now = Instant::now();
+        let mut threshold = Hysteresis::new(100.0, 10.0);
+        assert_eq!(threshold.update(99.0, now), None);
+        assert_eq!(threshold.update(100.0, now), Some(true));
+        assert!(threshold.is_active());
+    }
+
+    #[test]
+    fn hovering_inside_the_band_does_not_flip() {
+        let now = Instant::now();
+        let mut threshold = Hysteresis::new(100.0, 10.0);
+        threshold.update(105.0, now);
+        for value in [99.0, 95.0, 91.0, 100.0, 92.0] {
+            assert_eq!(threshold.update(value, now), None);
+        }
+        assert_eq!(threshold.update(90.0, now), Some(false));
+    }
+
+    #[test]
+    fn min_hold_ignores_brief_dips() {
+        let start = Instant::now();
+        let mut threshold = Hysteresis::new(100.0, 10.0).with_min_hold(Duration::from_millis(500));
+        threshold.update(100.0, start);
+        assert_eq!(threshold.update(80.0, ms(start, 100)), None);
+        // Back above the low threshold restarts the hold
+        assert_eq!(threshold.update(95.0, ms(start, 400)), None);
+        assert_eq!(threshold.update(80.0, ms(start, 500)), None);
+        assert_eq!(threshold.update(80.0, ms(start, 900)), None);
+        assert_eq!(threshold.update(80.0, ms(start, 1000)), Some(false));
+    }
+}
@@ -0,0 +1,74 @@
+use std::time::{Duration, Instant};
+
+/// Rate limiter for status output.
+///
+/// The control loop can run as fast as it likes; `should_emit` only returns
+/// `true` once per `interval`, so the terminal stays readable.
+pub struct Throttle {
+    interval: Duration,
+    last_emit: Option<Instant>,
+}
+
+impl Throttle {
+    pub fn new(interval: Duration) -> Self {
+        Self {
+            interval,
+            last_emit: None,
+        }
+    }
+
+    /// Returns `true` if at least `interval` has passed since the last emit
+    /// (or nothing has been emitted yet), and records `now` as the last emit.
+    pub fn should_emit(&mut self, now: Instant) -> bool {
+        let due = match self.last_emit {
+            Some(last) => now.saturating_duration_since(last) >= self.interval,
+            None => true,
+        };
+        if due {
+            self.last_emit = Some(now);
+        }
+        due
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const INTERVAL: Duration = Duration::from_millis(500);
+
+    #[test]
+    fn first_call_always_emits() {
+        let mut throttle = Throttle::new(INTERVAL);
+        assert!(throttle.should_emit(Instant::now()));
+    }
+
+    #[test]
+    fn calls_inside_the_interval_are_suppressed() {
+        let start = Instant::now();
+        let mut throttle = Throttle::new(INTERVAL);
+        assert!(throttle.should_emit(start));
+        assert!(!throttle.should_emit(start + Duration::from_millis(100)));
+        assert!(!throttle.should_emit(start + Duration::from_millis(499)));
+        assert!(throttle.should_emit(start + INTERVAL));
+    }
+
+    #[test]
+    fn interval_restarts_from_the_last_emit() {
+        let start = Instant::now();
+        let mut throttle = Throttle::new(INTERVAL);
+        throttle.should_emit(start);
+        assert!(throttle.should_emit(start + Duration::from_millis(700)));
+        // Measured from 700 ms, not from a fixed 500 ms grid
+        assert!(!throttle.should_emit(start + Duration::from_millis(1100)));
+        assert!(throttle.should_emit(start + Duration::from_millis(1200)));
+    }
+
+    #[test]
+    fn zero_interval_emits_every_time() {
+        let now = Instant::now();
+        let mut throttle = Throttle::new(Duration::ZERO);
+        assert!(throttle.should_emit(now));
+        assert!(throttle.should_emit(now));
+    }
+}
@@ -0,0 +1,135 @@
+use std::sync::mpsc;
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use crate::error::AppError;
+
+/// Spawns a worker thread named `name` that first runs `init` and reports
+/// its outcome back, then runs `body` with the initialized state.
+///
+/// Setup errors (e.g. a GPIO pin that is already in use) are returned to
+/// the caller instead of being printed, or panicking, inside the thread.
+pub fn spawn_initialized<S, I, B>(name: &'static str, init: I, body: B) -> Result<JoinHandle<()>, AppError>
+where
+    I: FnOnce() -> Result<S, AppError> + Send + 'static,
+    B: FnOnce(S) + Send + 'static,
+{
+    let (tx, rx) = mpsc::channel();
+    let handle = thread::Builder::new()
+        .name(name.to_string())
+        .spawn(move || match init() {
+            Ok(state) => {
+                let _ = tx.send(Ok(()));
+                body(state);
+            }
+            Err(e) => {
+                let _ = tx.send(Err(e));
+            }
+        })
+        .map_err(|_| AppError::WorkerPanicked(name))?;
+
+    match rx.recv() {
+        Ok(Ok(())) => Ok(handle),
+        Ok(Err(e)) => {
+            let _ = handle.join();
+            Err(e)
+        }
+        // The sender was dropped without a message: `init` panicked
+        Err(_) => {
+            let _ = handle.join();
+            Err(AppError::WorkerPanicked(name))
+        }
+    }
+}
+
+/// How long `join_timeout` callers wait for a worker on shutdown; workers
+/// check their stop flag at least every few tens of milliseconds.
+pub const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(2);
+// How often `join_timeout` checks whether the worker has finished
+const JOIN_POLL: Duration = Duration::from_millis(5);
+
+/// Joins a worker, turning a panic into an error.
+pub fn join(name: &'static str, handle: JoinHandle<()>) -> Result<(), AppError> {
+    handle.join().map_err(|_| AppError::WorkerPanicked(name))
+}
+
+/// Joins a worker that has been told to stop, but gives up after `timeout`
+/// so a stuck worker cannot hang the exit. Returns `false` after printing a
+/// warning if it timed out; the thread is then left to end with the process.
+pub fn join_timeout(
+    name: &'static str,
+    handle: JoinHandle<()>,
+    timeout: Duration,
+) -> Result<bool, AppError> {
+    let deadline = Instant::now() + timeout;
+    while !handle.is_finished() {
+        if Instant::now() >= deadline {
+            eprintln!("Warning: {} thread did not stop within {:?}, exiting anyway", name, timeout);
+            return Ok(false);
+        }
+        thread::sleep(JOIN_POLL);
+    }
+    join(name, handle).map(|()| true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicBool, Ordering};
+
+    #[test]
+    fn body_runs_with_the_initialized_state() {
+        let (tx, rx) = mpsc::channel();
+        let handle = spawn_initialized("test", || Ok(21), move |n| tx.send(n * 2).unwrap()).unwrap();
+        join("test", handle).unwrap();
+        assert_eq!(rx.recv().unwrap(), 42);
+    }
+
+    #[test]
+    fn init_errors_are_returned_to_the_caller() {
+        let result = spawn_initialized("test", || Err::<(), _>(AppError::AdcNotFound("no ADC".into())), |_| {});
+        assert!(matches!(result, Err(AppError::AdcNotFound(_))));
+    }
+
+    #[test]
+    fn panics_become_errors() {
+        let result = spawn_initialized::<(), _, _>("init", || panic!("init failed"), |_| {});
+        assert!(matches!(result, Err(AppError::WorkerPanicked("init"))));
+        let handle = spawn_initialized("body", || Ok(()), |_| panic!("body failed")).unwrap();
+        assert!(matches!(join("body", handle), Err(AppError::WorkerPanicked("body"))));
+    }
+
+    #[test]
+    fn join_timeout_gives_up_on_a_stuck_worker() {
+        let running = Arc::new(AtomicBool::new(true));
+        let flag = running.clone();
+        let handle = spawn_initialized("stuck", || Ok(()), move |_| {
+            while flag.load(Ordering::SeqCst) {
+                thread::sleep(Duration::from_millis(1));
+            }
+        })
+        .unwrap();
+        assert!(!join_timeout("stuck", handle, Duration::from_millis(20)).unwrap());
+        running.store(false, Ordering::SeqCst);
+
+        let handle = spawn_initialized("quick", || Ok(()), |_| {}).unwrap();
+        assert!(join_timeout("quick", handle, SHUTDOWN_TIMEOUT).unwrap());
+    }
+
+    #[test]
+    fn join_timeout_gives_up_on_a_worker_that_outlives_it() {
+        let finished = Arc::new(AtomicBool::new(false));
+        let flag = finished.clone();
+        let handle = spawn_initialized("slow", || Ok(()), move |_| {
+            thread::sleep(Duration::from_millis(500));
+            flag.store(true, Ordering::SeqCst);
+        })
+        .unwrap();
+        let start = Instant::now();
+        assert!(matches!(join_timeout("slow", handle, Duration::from_millis(20)), Ok(false)));
+        // Returned at the timeout, without waiting for the worker
+        assert!(start.elapsed() < Duration::from_millis(500));
+        assert!(!finished.load(Ordering::SeqCst));
+    }
+}
@@ -0,0 +1,17 @@
+//! The panic hook is process-wide, so it is installed here, in this test's
+//! own process, rather than in the unit tests.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+
+use common::shutdown;
+
+#[test]
+fn panic_on_a_thread_clears_running() {
+    let running = Arc::new(AtomicBool::new(true));
+    shutdown::install_panic_hook(running.clone());
+    let result = thread::spawn(|| panic!("worker failed")).join();
+    assert!(result.is_err());
+    assert!(!running.load(Ordering::SeqCst));
+}
@@ -0,0 +1,23 @@
+//! Real signal delivery, kept out of the unit tests: the handler thread and
+//! the raised SIGTERM stay inside this test's own process.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use common::shutdown;
+use signal_hook::consts::SIGTERM;
+
+#[test]
+fn installed_handler_runs_on_a_real_sigterm() {
+    let stopped = Arc::new(AtomicBool::new(false));
+    let s = stopped.clone();
+    shutdown::set_handler(move || s.store(true, Ordering::SeqCst)).unwrap();
+    signal_hook::low_level::raise(SIGTERM).unwrap();
+    let deadline = Instant::now() + Duration::from_secs(2);
+    while !stopped.load(Ordering::SeqCst) && Instant::now() < deadline {
+        thread::sleep(Duration::from_millis(5));
+    }
+    assert!(stopped.load(Ordering::SeqCst));
+}
@@ -0,0 +1,68 @@
+//! One-stop API for the starter kit's parts.
+//!
+//! Every constructor picks the wiring used in the Freenove tutorials, so a
+//! lesson only needs the pin number:
+//!
+//! ```no_run
+//! # fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! let mut led = kit::Kit::led(17)?;
+//! loop {
+//!     led.toggle();
+//!     std::thread::sleep(std::time::Duration::from_secs(1));
+//! }
+//! # }
+//! ```
+
+use rppal::gpio::{Gpio, Result};
+
+pub use adc::{Adc, AdcError, Chip};
+pub use common::button::Button;
+pub use common::buzzer::Buzzer;
+pub use common::led::{Led, LedPolarity};
+
+pub struct Kit;
+
+impl Kit {
+    /// An LED wired anode-to-pin, starting off.
+    pub fn led(pin: u8) -> Result<Led> {
+        Kit::led_with_polarity(pin, LedPolarity::ActiveHigh)
+    }
+
+    /// An LED with explicit polarity, starting off.
+    pub fn led_with_polarity(pin: u8, polarity: LedPolarity) -> Result<Led> {
+        let mut led = Led::new(Gpio::new()?.get(pin)?.into_output(), polarity);
+        led.off();
+        Ok(led)
+    }
+
+    /// A push button to ground, with the internal pull-up enabled.
+    pub fn button(pin: u8) -> Result<Button> {
+        Ok(Button::new(Gpio::new()?.get(pin)?.into_input_pullup()))
+    }
+
+    /// A buzzer, starting silent.
+    ///
+    /// ```no_run
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let mut buzzer = kit::Kit::buzzer(17)?;
+    /// buzzer.tone(440.0)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn buzzer(pin: u8) -> Result<Buzzer> {
+        Ok(Buzzer::new(Gpio::new()?.get(pin)?.into_output_low()))
+    }
+
+    /// The ADC module, searched for on the usual buses.
+    ///
+    /// ```no_run
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let mut adc = kit::Kit::adc()?;
+    /// println!("{} reads {}", adc.chip().name(), adc.read(0)?);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn adc() -> std::result::Result<Adc, AdcError> {
+        Adc::detect(&adc::DEFAULT_BUSES, adc::DEFAULT_RETRIES)
+    }
+}
@@ -2,69 +2,128 @@ use std::error::Error;
 use std::sync::atomic::{AtomicBool, AtomicU8, Ordering};
 use std::sync::Arc;
 use std::thread;
-use std::time::Duration;
-
-use rppal::gpio::Gpio;
-use rppal::i2c::I2c;
-
-const PCF8591_ADDR: u16 = 0x48;
-const ADS7830_ADDR: u16 = 0x4b;
+use std::time::{Duration, Instant};
+
+use adc::{Adc, AdcError, Chip};
+use common::args::{Args, Opt};
+use common::color::{self, RgbCommon};
+use common::error::{self, AppError, open_gpio};
+use common::lock;
+use common::shutdown::{self, install_panic_hook};
+use common::stats::RollingStats;
+use common::status;
+use common::style;
+use common::throttle::Throttle;
+use common::worker;
 
 // GPIO Pins for RGB LED
 const RED_PIN: u8 = 22;
 const GREEN_PIN: u8 = 27;
 const BLUE_PIN: u8 = 17;
 
-fn main() -> Result<(), Box<dyn Error>> {
-    println!("Program is starting ...");
+// Minimum interval between status lines
+const DEFAULT_LOG_EVERY_MS: u64 = 500;
+// Readings kept per channel for --stats
+const DEFAULT_STATS_WINDOW: usize = 100;
+
+// ADC channels of the red, green and blue potentiometers in the tutorial wiring
+const DEFAULT_CHANNELS: [u8; 3] = [0, 1, 2];
+
+/// Validates a `--channels r,g,b` list against the chip's channel count.
+fn parse_channels(channels: &[u8], chip: Chip) -> Result<[u8; 3], String> {
+    let mapping: [u8; 3] = channels
+        .try_into()
+        .map_err(|_| format!("--channels needs exactly 3 channels (r,g,b), got {}", channels.len()))?;
+    for &ch in &mapping {
+        if ch >= chip.channel_count() {
+            return Err(format!(
+                "--channels: channel {} out of range, {} has channels 0-{}",
+                ch,
+                chip.name(),
+                chip.channel_count() - 1
+            ));
+        }
+    }
+    Ok(mapping)
+}
 
-    // Initialize I2C - try multiple buses
-    let buses = [1, 13, 14];
-    let mut i2c_n = None;
-    let mut is_pcf8591 = None;
-
-    for &bus in &buses {
-        println!("Checking I2C bus {} ...", bus);
-        let mut i2c = match I2c::with_bus(bus) {
-            Ok(i) => i,
-            Err(_) => continue,
-        };
+/// Returns `new` if it moved more than `deadband` counts from `last_applied`,
+/// otherwise holds `last_applied`, so the last bit of ADC noise does not
+/// make the colour flicker.
+fn apply_deadband(last_applied: u8, new: u8, deadband: u8) -> u8 {
+    if last_applied.abs_diff(new) > deadband { new } else { last_applied }
+}
 
-        for _ in 0..3 {
-            if i2c.set_slave_address(PCF8591_ADDR).is_ok() && i2c.read(&mut [0]).is_ok() {
-                is_pcf8591 = Some(true);
-                i2c_n = Some(i2c);
-                break;
-            } else if i2c.set_slave_address(ADS7830_ADDR).is_ok() && i2c.read(&mut [0]).is_ok() {
-                is_pcf8591 = Some(false);
-                i2c_n = Some(i2c);
-                break;
-            }
-            thread::sleep(Duration::from_millis(50));
-        }
+/// Reads the red, green and blue potentiometers from the mapped channels.
+fn read_rgb(adc: &mut Adc, channels: [u8; 3]) -> Result<[u8; 3], AdcError> {
+    Ok([adc.read(channels[0])?, adc.read(channels[1])?, adc.read(channels[2])?])
+}
 
-        if i2c_n.is_some() {
-            println!("Found device on bus {}", bus);
-            break;
-        }
-    }
-    let (mut i2c, is_pcf8591) = match (i2c_n, is_pcf8591) {
-        (Some(i), Some(p)) => (i, p),
-        _ => {
-            eprintln!("No correct I2C device (PCF8591 or ADS7830) found on buses [1, 13, 14].");
-            eprintln!("Please check your wiring and ensure I2C is enabled.");
-            eprintln!("Program Exit.");
-            std::process::exit(-1);
-        }
-    };
+const OPTIONS: &[Opt] = &[
+    Opt::Value("--log-every"),
+    Opt::Value("--channels"),
+    Opt::Value("--deadband"),
+    Opt::Value("--i2c-hz"),
+    Opt::Value("--pcf-dummy-reads"),
+    Opt::Value("--heartbeat-pin"),
+    Opt::Flag("--stats"),
+    Opt::Value("--stats-window"),
+    Opt::Value("--rgb-common"),
+    Opt::Value("--labels"),
+    Opt::Value("--lock-file"),
+];
+
+fn main() {
+    error::run(try_main);
+}
+
+fn try_main() -> Result<(), Box<dyn Error>> {
+    let args = Args::from_env()?.with_version(env!("CARGO_PKG_VERSION")).with_options(OPTIONS);
+    let log_every = Duration::from_millis(args.value_or("--log-every", DEFAULT_LOG_EVERY_MS)?);
+    let channels: Vec<u8> = args.list("--channels")?.unwrap_or(DEFAULT_CHANNELS.to_vec());
+    let deadband: u8 = args.value_or("--deadband", 0)?;
+    let i2c_hz: Option<u32> = args.value("--i2c-hz")?;
+    // Raise if the PCF8591 readings lag one sample behind
+    let pcf_dummy_reads = args.value_or("--pcf-dummy-reads", adc::DEFAULT_PCF_DUMMY_READS)?;
+    let heartbeat_pin: Option<u8> = args.value("--heartbeat-pin")?;
+    let stats_mode = args.flag("--stats");
+    let stats_window = args.value_or("--stats-window", DEFAULT_STATS_WINDOW)?;
+    let rgb_common: RgbCommon = args.value_or("--rgb-common", RgbCommon::default())?;
+    // Names for the red, green and blue channels in the output
+    let labels: Option<Vec<String>> = args.list("--labels")?;
+    let lock_file: Option<String> = args.value("--lock-file")?;
+    args.finish();
+    args.exit_if_dry_run();
+    let _lock = lock::acquire(lock_file.as_deref())?;
+
+    style::banner("Program is starting ...");
+
+    // Initialize I2C - try multiple buses
+    let mut adc = Adc::detect(&adc::DEFAULT_BUSES, adc::DEFAULT_RETRIES)
+        .map_err(|e| AppError::AdcNotFound(e.to_string()))?;
 
     println!(
-        "Detected I2C device: {}",
-        if is_pcf8591 { "PCF8591" } else { "ADS7830" }
+        "Detected I2C device: {} on bus {}",
+        adc.chip().name(),
+        adc.bus()
     );
+    adc.log_clock(i2c_hz)?;
+    adc.set_pcf_dummy_reads(pcf_dummy_reads);
+    let channels = parse_channels(&channels, adc.chip())?;
+    println!(
+        "Channel mapping: red={}, green={}, blue={}",
+        channels[0], channels[1], channels[2]
+    );
+    let labels = labels.map(|l| adc::resolve_labels(&l, &channels)).transpose()?;
 
     // Shared state for PWM
     let running = Arc::new(AtomicBool::new(true));
+    install_panic_hook(running.clone());
+
+    // Optional 1 Hz "still alive" LED for headless runs
+    let status_handle = heartbeat_pin
+        .map(|pin| status::spawn_status_led(pin, running.clone()))
+        .transpose()?;
     let duty_r = Arc::new(AtomicU8::new(0));
     let duty_g = Arc::new(AtomicU8::new(0));
     let duty_b = Arc::new(AtomicU8::new(0));
@@ -76,114 +135,120 @@ fn main() -> Result<(), Box<dyn Error>> {
         let duty_g = duty_g.clone();
         let duty_b = duty_b.clone();
 
-        thread::spawn(move || {
-            let gpio = match Gpio::new() {
-                Ok(g) => g,
-                Err(e) => {
-                    eprintln!("Failed to access GPIO: {}", e);
-                    return;
-                }
-            };
-
-            let mut pin_r = gpio.get(RED_PIN).unwrap().into_output();
-            let mut pin_g = gpio.get(GREEN_PIN).unwrap().into_output();
-            let mut pin_b = gpio.get(BLUE_PIN).unwrap().into_output();
-
-            // 1 kHz frequency = 1000 us period
-            let period_micros = 1000u64;
-
+        let init = move || {
+            let gpio = open_gpio()?;
+            let mut pins = [
+                gpio.get(RED_PIN)?.into_output(),
+                gpio.get(GREEN_PIN)?.into_output(),
+                gpio.get(BLUE_PIN)?.into_output(),
+            ];
+            // Start with the LED off
+            for pin in pins.iter_mut() {
+                pin.write(rgb_common.off_level());
+            }
+            Ok(pins)
+        };
+        worker::spawn_initialized("PWM", init, move |mut pins| {
             while running.load(Ordering::SeqCst) {
-                let dr = duty_r.load(Ordering::SeqCst) as u64;
-                let dg = duty_g.load(Ordering::SeqCst) as u64;
-                let db = duty_b.load(Ordering::SeqCst) as u64;
-
-                // Simple Software PWM for 3 channels
-                // We use 100 steps for granularity to keep CPU usage reasonable
-                // Alternatively, we could use rppal's hardware PWM if available or its own SoftPwm
-                // But for consistency with Softlight example, we'll do a simple bit-banging approach or use rppal's SoftPwm.
-                // Actually, rppal's OutputPin has set_pwm which is easier.
-
-                // Using rppal's built-in software PWM for simplicity and efficiency
-                let _ = pin_r.set_pwm(
-                    Duration::from_micros(period_micros),
-                    Duration::from_micros(period_micros * dr / 255),
-                );
-                let _ = pin_g.set_pwm(
-                    Duration::from_micros(period_micros),
-                    Duration::from_micros(period_micros * dg / 255),
-                );
-                let _ = pin_b.set_pwm(
-                    Duration::from_micros(period_micros),
-                    Duration::from_micros(period_micros * db / 255),
+                let rgb = (
+                    duty_r.load(Ordering::SeqCst) as f64 / 255.0,
+                    duty_g.load(Ordering::SeqCst) as f64 / 255.0,
+                    duty_b.load(Ordering::SeqCst) as f64 / 255.0,
                 );
+                // rppal's software PWM keeps running between updates
+                let _ = color::apply_rgb(&mut pins, rgb, rgb_common);
 
                 thread::sleep(Duration::from_millis(10));
             }
             // Turn off LEDs on exit
-            pin_r.set_low();
-            pin_g.set_low();
-            pin_b.set_low();
-        })
+            let _ = color::clear_rgb(&mut pins, rgb_common);
+        })?
     };
 
     // Setup CTRL-C handler
     let running_clone = running.clone();
-    ctrlc::set_handler(move || {
-        println!("\nEnding program");
+    shutdown::set_handler(move || {
+        style::banner("\nEnding program");
         running_clone.store(false, Ordering::SeqCst);
     })?;
 
     // Main loop
+    let mut log_throttle = Throttle::new(log_every);
+    let mut applied = [0u8; 3];
+    let mut stats: [RollingStats; 3] = std::array::from_fn(|_| RollingStats::new(stats_window));
+    let stat_names = labels.clone().unwrap_or_else(|| ["Red", "Green", "Blue"].map(String::from).to_vec());
     while running.load(Ordering::SeqCst) {
-        let mut read_adc = |channel: u8| -> Result<u8, Box<dyn Error>> {
-            if is_pcf8591 {
-                i2c.set_slave_address(PCF8591_ADDR)?;
-                i2c.write(&[0x40 | channel])?;
-                let mut buf = [0u8; 1];
-                i2c.read(&mut buf)?; // Dummy read
-                i2c.read(&mut buf)?; // Actual read
-                Ok(buf[0])
-            } else {
-                // ADS7830
-                // Command byte: 1 (SD) | Channel (3 bits) | 01 (Internal Ref) | 00 (Unused)
-                // Channel 0: 0x84, Channel 1: 0xc4, Channel 2: 0x94, Channel 3: 0xd4...
-                // Actually, ADS7830 channel mapping:
-                // Ch0: 0x84, Ch1: 0xC4, Ch2: 0x94, Ch3: 0xD4, Ch4: 0xA4, Ch5: 0xE4, Ch6: 0xB4, Ch7: 0xF4
-                let cmd = match channel {
-                    0 => 0x84,
-                    1 => 0xc4,
-                    2 => 0x94,
-                    3 => 0xd4,
-                    4 => 0xa4,
-                    5 => 0xe4,
-                    6 => 0xb4,
-                    7 => 0xf4,
-                    _ => 0x84,
-                };
-                i2c.set_slave_address(ADS7830_ADDR)?;
-                i2c.write(&[cmd])?;
-                let mut buf = [0u8; 1];
-                i2c.read(&mut buf)?;
-                Ok(buf[0])
+        match read_rgb(&mut adc, channels) {
+            Ok(reading) => {
+                for (last, new) in applied.iter_mut().zip(reading) {
+                    *last = apply_deadband(*last, new, deadband);
+                }
+                let [val_r, val_g, val_b] = applied;
+                duty_r.store(val_r, Ordering::SeqCst);
+                duty_g.store(val_g, Ordering::SeqCst);
+                duty_b.store(val_b, Ordering::SeqCst);
+                for (channel, value) in stats.iter_mut().zip(reading) {
+                    channel.push(value as f64);
+                }
+
+                if log_throttle.should_emit(Instant::now()) {
+                    match &labels {
+                        Some(labels) => println!(
+                            "ADC Value {}: {}, {}: {}, {}: {}",
+                            labels[0], val_r, labels[1], val_g, labels[2], val_b
+                        ),
+                        None => println!(
+                            "ADC Value val_Red: {}, val_Green: {}, val_Blue: {}",
+                            val_r, val_g, val_b
+                        ),
+                    }
+                    if stats_mode {
+                        for (name, channel) in stat_names.iter().zip(&stats) {
+                            println!("  {:<7}{}", format!("{}:", name), channel.summary());
+                        }
+                    }
+                }
             }
-        };
+            Err(e) => {
+                eprintln!("Error reading ADC: {}", e);
+            }
+        }
 
-        let val_r = read_adc(0).unwrap_or(0);
-        let val_g = read_adc(1).unwrap_or(0);
-        let val_b = read_adc(2).unwrap_or(0);
+        thread::sleep(Duration::from_millis(10));
+    }
 
-        duty_r.store(val_r, Ordering::SeqCst);
-        duty_g.store(val_g, Ordering::SeqCst);
-        duty_b.store(val_b, Ordering::SeqCst);
+    worker::join_timeout("PWM", pwm_handle, worker::SHUTDOWN_TIMEOUT)?;
+    if let Some(handle) = status_handle {
+        worker::join_timeout("status LED", handle, worker::SHUTDOWN_TIMEOUT)?;
+    }
+    Ok(())
+}
 
-        println!(
-            "ADC Value val_Red: {}, val_Green: {}, val_Blue: {}",
-            val_r, val_g, val_b
-        );
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-        thread::sleep(Duration::from_millis(10));
+    #[test]
+    fn channels_map_in_order() {
+        assert_eq!(parse_channels(&[2, 0, 1], Chip::Pcf8591), Ok([2, 0, 1]));
+        assert_eq!(parse_channels(&[7, 6, 5], Chip::Ads7830), Ok([7, 6, 5]));
     }
 
-    let _ = pwm_handle.join();
-    Ok(())
+    #[test]
+    fn channels_must_be_three_and_exist() {
+        assert!(parse_channels(&[0, 1], Chip::Pcf8591).is_err());
+        assert!(parse_channels(&[0, 1, 2, 3], Chip::Pcf8591).is_err());
+        assert_eq!(
+            parse_channels(&[0, 1, 4], Chip::Pcf8591),
+            Err("--channels: channel 4 out of range, PCF8591 has channels 0-3".to_string())
+        );
+    }
+
+    #[test]
+    fn deadband_holds_small_changes() {
+        assert_eq!(apply_deadband(100, 102, 2), 100);
+        assert_eq!(apply_deadband(100, 97, 2), 97);
+        assert_eq!(apply_deadband(100, 101, 0), 101);
+        assert_eq!(apply_deadband(0, 255, 254), 255);
+    }
 }